@@ -1,3 +1,7 @@
 fn main() {
-    tauri_build::build()
+    // The headless `snapsort` CLI is built without the gui feature and
+    // without Tauri, so there is nothing for tauri-build to do.
+    if std::env::var_os("CARGO_FEATURE_GUI").is_some() {
+        tauri_build::build()
+    }
 }
@@ -0,0 +1,594 @@
+//! Opt-in HTTP API on localhost so other tools (hotkey daemons, editor
+//! plugins) can drive SnapSort.
+//!
+//! Every request needs `Authorization: Bearer <token>`, or `?token=` for
+//! EventSource clients that cannot set headers. The token is generated on
+//! first use and kept in `api.json` in the app config directory.
+//!
+//! Routes:
+//! - `POST /v1/scan` starts a run and returns its id
+//! - `GET /v1/runs`, `GET /v1/runs/{run_id}` and
+//!   `GET /v1/runs/{run_id}/plan?format=json|csv`
+//! - `POST /v1/runs/{run_id}/apply` with an optional edited plan as body;
+//!   409 with the problems when the plan would not apply cleanly
+//! - `POST /v1/runs/{run_id}/undo`
+//! - `POST /v1/search`
+//! - `GET /v1/events` streams progress of every run as Server-Sent Events
+
+use crate::credentials::KeyStore;
+use crate::events::{EventSink, PipelineEvent};
+use crate::pipeline::{self, Library, MoveOutcome, RunContext, ScanOptions};
+use crate::plan::{Plan, PlanFormat};
+use crate::search::{SearchFilters, SearchHit};
+use crate::session_store::{RunOptions, RunSnapshot, SessionSummary};
+use axum::extract::{Path as UrlPath, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{broadcast, oneshot};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+pub const DEFAULT_PORT: u16 = 47813;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ApiConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub token: String,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl ApiConfig {
+    /// Read the config, creating it with a fresh token on first use.
+    pub fn load(path: &Path) -> Result<ApiConfig, String> {
+        let mut config = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| e.to_string())?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ApiConfig {
+                enabled: false,
+                port: DEFAULT_PORT,
+                token: String::new(),
+            },
+            Err(e) => return Err(e.to_string()),
+        };
+        if config.token.is_empty() {
+            config.token = new_token();
+            config.save(path)?;
+        }
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| e.to_string())?;
+        // The token grants file moves; keep it private to the user.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+fn new_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A pipeline event tagged with the run it belongs to.
+#[derive(Serialize, Clone)]
+pub struct RunEvent {
+    pub run_id: String,
+    #[serde(flatten)]
+    pub event: PipelineEvent,
+}
+
+/// Publishes one run's events to the `/v1/events` subscribers.
+pub struct BroadcastSink {
+    run_id: String,
+    events: broadcast::Sender<RunEvent>,
+}
+
+impl EventSink for BroadcastSink {
+    fn emit(&self, event: PipelineEvent) {
+        // No subscriber is not an error.
+        let _ = self.events.send(RunEvent {
+            run_id: self.run_id.clone(),
+            event,
+        });
+    }
+}
+
+/// What the server needs from the app hosting it.
+#[derive(Clone)]
+pub struct ApiContext {
+    pub library: Arc<Library>,
+    /// The key scans run with; requests cannot pass one of their own.
+    pub keys: Arc<KeyStore>,
+}
+
+#[derive(Clone)]
+struct ServerState {
+    context: ApiContext,
+    token: Arc<str>,
+    // Weak so that dropping the server ends open event streams.
+    events: broadcast::WeakSender<RunEvent>,
+}
+
+/// A running server; dropping it shuts the server down.
+pub struct ApiServer {
+    port: u16,
+    events: broadcast::Sender<RunEvent>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl ApiServer {
+    /// Bind to 127.0.0.1 only; the API is never reachable from the network.
+    pub async fn start(config: &ApiConfig, context: ApiContext) -> Result<ApiServer, String> {
+        let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, config.port))
+            .await
+            .map_err(|e| format!("Could not listen on port {}: {}", config.port, e))?;
+
+        let (events, _) = broadcast::channel(256);
+        let state = ServerState {
+            context,
+            token: Arc::from(config.token.as_str()),
+            events: events.downgrade(),
+        };
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let router = router(state);
+        tokio::spawn(async move {
+            let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+                let _ = stopped.await;
+            });
+            if let Err(e) = server.await {
+                eprintln!("[RUST] ❌ API server failed: {}", e);
+            }
+        });
+
+        eprintln!(
+            "[RUST] ✅ API listening on http://127.0.0.1:{}",
+            config.port
+        );
+        Ok(ApiServer {
+            port: config.port,
+            events,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Sink for a run started outside the API, e.g. from the app window.
+    pub fn sink(&self, run_id: &str) -> Arc<dyn EventSink> {
+        Arc::new(BroadcastSink {
+            run_id: run_id.to_string(),
+            events: self.events.clone(),
+        })
+    }
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        eprintln!("[RUST] API server on port {} stopped", self.port);
+    }
+}
+
+fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/v1/scan", post(scan))
+        .route("/v1/runs", get(list_runs))
+        .route("/v1/runs/{run_id}", get(get_run))
+        .route("/v1/runs/{run_id}/plan", get(get_plan))
+        .route("/v1/runs/{run_id}/apply", post(apply))
+        .route("/v1/runs/{run_id}/undo", post(undo))
+        .route("/v1/search", post(search))
+        .route("/v1/events", get(events))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state)
+}
+
+struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> ApiError {
+        ApiError(StatusCode::BAD_REQUEST, message.into())
+    }
+
+    fn not_found(message: impl Into<String>) -> ApiError {
+        ApiError(StatusCode::NOT_FOUND, message.into())
+    }
+}
+
+impl From<String> for ApiError {
+    fn from(message: String) -> ApiError {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        #[derive(Serialize)]
+        struct Body {
+            error: String,
+        }
+        (self.0, Json(Body { error: self.1 })).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+async fn authorize(State(state): State<ServerState>, request: Request, next: Next) -> Response {
+    let from_header = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let from_query = request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+    });
+    let authorized = from_header
+        .or(from_query)
+        .is_some_and(|token| tokens_match(token, &state.token));
+
+    if authorized {
+        next.run(request).await
+    } else {
+        ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Invalid or missing token".to_string(),
+        )
+        .into_response()
+    }
+}
+
+// Compare in constant time so the token cannot be guessed byte by byte.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[derive(Deserialize)]
+struct ScanRequest {
+    path: String,
+    selected_paths: Option<Vec<String>>,
+    #[serde(default)]
+    options: ScanOptions,
+}
+
+#[derive(Serialize)]
+struct ScanStarted {
+    run_id: String,
+    files: usize,
+}
+
+async fn scan(
+    State(state): State<ServerState>,
+    Json(request): Json<ScanRequest>,
+) -> ApiResult<ScanStarted> {
+    let ScanRequest {
+        path,
+        selected_paths,
+        options,
+    } = request;
+    let root = PathBuf::from(&path);
+    if !root.is_dir() {
+        return Err(ApiError::bad_request(format!("Not a directory: {}", path)));
+    }
+    let Some(api_key) = state.context.keys.get() else {
+        return Err(ApiError::bad_request(
+            "No API key saved: set one in the app settings",
+        ));
+    };
+
+    let library = &state.context.library;
+    let scan = pipeline::collect_files(&root, selected_paths);
+    let run_id = library.sessions.start(
+        &path,
+        &scan.files,
+        RunOptions {
+            descriptions: options.descriptions,
+            write_tags: options.write_tags.unwrap_or(false),
+            layout: options.layout.unwrap_or_default(),
+        },
+    )?;
+    let started = ScanStarted {
+        run_id: run_id.clone(),
+        files: scan.files.len(),
+    };
+
+    let events = state
+        .events
+        .upgrade()
+        .ok_or_else(|| ApiError(StatusCode::SERVICE_UNAVAILABLE, "Server is stopping".into()))?;
+    let sink: Arc<dyn EventSink> = Arc::new(BroadcastSink {
+        run_id: run_id.clone(),
+        events,
+    });
+    let run = RunContext {
+        run_id,
+        api_key,
+        layout: options.layout.unwrap_or_default(),
+        descriptions: options.descriptions,
+        prices: library.prices.clone(),
+        models: library.models(),
+        client: library.client(),
+        privacy: library.privacy(),
+    };
+    let store = library.sessions.clone();
+    tokio::spawn(async move {
+        if let Err(e) = pipeline::run_scan(scan, options, run, store, sink, None).await {
+            eprintln!("[RUST] ❌ API scan failed: {}", e);
+        }
+    });
+
+    Ok(Json(started))
+}
+
+async fn list_runs(State(state): State<ServerState>) -> ApiResult<Vec<SessionSummary>> {
+    Ok(Json(state.context.library.sessions.list()?))
+}
+
+fn load_run(state: &ServerState, run_id: &str) -> Result<RunSnapshot, ApiError> {
+    state
+        .context
+        .library
+        .sessions
+        .load(run_id)
+        .map_err(ApiError::not_found)
+}
+
+async fn get_run(
+    State(state): State<ServerState>,
+    UrlPath(run_id): UrlPath<String>,
+) -> ApiResult<RunSnapshot> {
+    Ok(Json(load_run(&state, &run_id)?))
+}
+
+#[derive(Deserialize)]
+struct PlanQuery {
+    format: Option<PlanFormat>,
+}
+
+async fn get_plan(
+    State(state): State<ServerState>,
+    UrlPath(run_id): UrlPath<String>,
+    Query(query): Query<PlanQuery>,
+) -> Result<Response, ApiError> {
+    let plan = Plan::from_run(&load_run(&state, &run_id)?);
+    Ok(match query.format.unwrap_or(PlanFormat::Json) {
+        PlanFormat::Json => Json(plan).into_response(),
+        PlanFormat::Csv => (
+            [(header::CONTENT_TYPE, "text/csv; charset=utf-8")],
+            plan.export(PlanFormat::Csv)?,
+        )
+            .into_response(),
+    })
+}
+
+#[derive(Serialize)]
+struct InvalidPlan {
+    error: String,
+    problems: Vec<String>,
+}
+
+// Without a body every selected pending proposal is applied as proposed.
+// Nothing moves when any selected move would fail.
+async fn apply(
+    State(state): State<ServerState>,
+    UrlPath(run_id): UrlPath<String>,
+    plan: Option<Json<Plan>>,
+) -> Result<Response, ApiError> {
+    let mut plan = match plan {
+        Some(Json(plan)) if plan.run_id != run_id => {
+            return Err(ApiError::bad_request("Plan belongs to another run"));
+        }
+        Some(Json(plan)) => plan,
+        None => Plan::from_run(&load_run(&state, &run_id)?),
+    };
+    plan.detect_conflicts();
+    let problems = plan.validate(&state.context.library)?;
+    if !problems.is_empty() {
+        let body = InvalidPlan {
+            error: format!("{} problem(s), no file moved", problems.len()),
+            problems,
+        };
+        return Ok((StatusCode::CONFLICT, Json(body)).into_response());
+    }
+
+    let library = state.context.library.clone();
+    let outcomes = tokio::task::spawn_blocking(move || plan.apply(&library))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Json(outcomes).into_response())
+}
+
+async fn undo(
+    State(state): State<ServerState>,
+    UrlPath(run_id): UrlPath<String>,
+) -> ApiResult<Vec<MoveOutcome>> {
+    load_run(&state, &run_id)?;
+    let library = state.context.library.clone();
+    let outcomes = tokio::task::spawn_blocking(move || library.undo_run(&run_id))
+        .await
+        .map_err(|e| e.to_string())??;
+    Ok(Json(outcomes))
+}
+
+#[derive(Deserialize)]
+struct SearchRequest {
+    query: String,
+    #[serde(default)]
+    filters: SearchFilters,
+}
+
+async fn search(
+    State(state): State<ServerState>,
+    Json(request): Json<SearchRequest>,
+) -> ApiResult<Vec<SearchHit>> {
+    let index = state
+        .context
+        .library
+        .search_index()
+        .map_err(|e| ApiError(StatusCode::SERVICE_UNAVAILABLE, e))?;
+    Ok(Json(index.search(&request.query, &request.filters)?))
+}
+
+// Slow subscribers that fall behind miss events rather than stall the scan.
+async fn events(
+    State(state): State<ServerState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let receiver = state
+        .events
+        .upgrade()
+        .ok_or_else(|| ApiError(StatusCode::SERVICE_UNAVAILABLE, "Server is stopping".into()))?
+        .subscribe();
+    let stream = BroadcastStream::new(receiver).filter_map(|received| {
+        let event = received.ok()?;
+        Event::default().json_data(&event).ok().map(Ok)
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "test-token";
+
+    fn test_router(dir: &Path) -> Router {
+        let (events, _) = broadcast::channel(16);
+        router(ServerState {
+            context: ApiContext {
+                library: Arc::new(Library::open(&dir.join("data"))),
+                keys: Arc::new(KeyStore::open(&dir.join("keys"))),
+            },
+            token: Arc::from(TOKEN),
+            events: events.downgrade(),
+        })
+    }
+
+    async fn status_of(router: Router, uri: &str, authorization: Option<&str>) -> StatusCode {
+        let mut request = Request::builder().uri(uri);
+        if let Some(value) = authorization {
+            request = request.header(header::AUTHORIZATION, value);
+        }
+        let response = router
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        response.status()
+    }
+
+    #[test]
+    fn tokens_must_match_exactly() {
+        assert!(tokens_match("abc123", "abc123"));
+        assert!(!tokens_match("abc124", "abc123"));
+        assert!(!tokens_match("abc12", "abc123"));
+        assert!(!tokens_match("abc1234", "abc123"));
+        assert!(!tokens_match("", "abc123"));
+    }
+
+    #[tokio::test]
+    async fn requests_without_the_token_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+
+        for (uri, authorization) in [
+            ("/v1/runs", None),
+            ("/v1/runs", Some("Bearer wrong-token")),
+            ("/v1/runs", Some(TOKEN)),
+            ("/v1/runs", Some("Basic test-token")),
+            ("/v1/runs?token=wrong-token", None),
+            ("/v1/runs/unknown", None),
+        ] {
+            assert_eq!(
+                status_of(router.clone(), uri, authorization).await,
+                StatusCode::UNAUTHORIZED,
+                "{} {:?}",
+                uri,
+                authorization
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn the_token_opens_every_route() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+
+        assert_eq!(
+            status_of(router.clone(), "/v1/runs", Some("Bearer test-token")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status_of(router.clone(), "/v1/runs?token=test-token", None).await,
+            StatusCode::OK
+        );
+
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/runs")
+                    .header(header::AUTHORIZATION, "Bearer test-token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"[]");
+    }
+
+    #[tokio::test]
+    async fn unknown_runs_are_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+
+        for uri in [
+            "/v1/runs/20250101-000000-unknown",
+            "/v1/runs/20250101-000000-unknown/plan",
+        ] {
+            assert_eq!(
+                status_of(router.clone(), uri, Some("Bearer test-token")).await,
+                StatusCode::NOT_FOUND,
+                "{}",
+                uri
+            );
+        }
+    }
+}
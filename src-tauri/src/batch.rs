@@ -0,0 +1,552 @@
+//! Batch mode for large backlogs: the classification requests of a run go
+//! out as Message Batches at the batch discount, and results are reported
+//! once the API has worked through them, usually within the hour.
+//!
+//! Batch IDs and the files behind every request are written to the run
+//! journal on submission, so polling picks up again after a restart
+//! (`resume`, `snapsort batch`). Pointing the client's base URL at a local
+//! stand-in server makes it testable offline, as `tests/batch.rs` does.
+
+use crate::bursts;
+use crate::events::{EventSink, PipelineEvent};
+use crate::pipeline::{self, Library, Reporter, RunContext, Screened};
+use crate::session_store::{RunSnapshot, SessionEvent, SessionStore};
+use crate::usage::{TokenUsage, BATCH_PRICE_FACTOR};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// The API takes up to 100,000 requests or 256 MB per batch
+const MAX_BATCH_REQUESTS: usize = 100_000;
+const MAX_BATCH_BYTES: usize = 200 * 1024 * 1024;
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+// Status checks may fail for a while, e.g. while the laptop sleeps
+const MAX_POLL_FAILURES: u32 = 20;
+const SESSION_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+// Runs whose batches are being polled in this process
+static COLLECTING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// One request of a batch and the files it classifies.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BatchItem {
+    pub custom_id: String,
+    pub paths: Vec<String>,
+    /// Set when the request classifies a burst as one session.
+    #[serde(default)]
+    pub session: Option<BatchSession>,
+    /// Kinds of what was blacked out in the uploaded images.
+    #[serde(default)]
+    pub redactions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BatchSession {
+    pub id: String,
+    /// Capture time of the first shot, `%Y-%m-%dT%H:%M:%S`.
+    pub started_at: String,
+}
+
+/// Request counts of a batch as reported by the API.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct RequestCounts {
+    #[serde(default)]
+    pub processing: u32,
+    #[serde(default)]
+    pub succeeded: u32,
+    #[serde(default)]
+    pub errored: u32,
+    #[serde(default)]
+    pub canceled: u32,
+    #[serde(default)]
+    pub expired: u32,
+}
+
+impl RequestCounts {
+    pub fn done(&self) -> u32 {
+        self.succeeded + self.errored + self.canceled + self.expired
+    }
+
+    pub fn total(&self) -> u32 {
+        self.processing + self.done()
+    }
+}
+
+#[derive(Deserialize)]
+struct BatchStatus {
+    processing_status: String,
+    #[serde(default)]
+    request_counts: RequestCounts,
+    results_url: Option<String>,
+}
+
+#[derive(Clone)]
+enum Request {
+    File(PathBuf),
+    Session(bursts::Session),
+}
+
+impl Request {
+    fn paths(&self) -> Vec<PathBuf> {
+        match self {
+            Request::File(path) => vec![path.clone()],
+            Request::Session(session) => session.paths.clone(),
+        }
+    }
+}
+
+// Requests waiting to be submitted together
+#[derive(Default)]
+struct Chunk {
+    lines: Vec<String>,
+    items: Vec<BatchItem>,
+    // Budget reserved for each item, given back if the batch is refused
+    reserved: Vec<f64>,
+    bytes: usize,
+}
+
+/// Submit every classification request of a prepared scan as batches, then
+/// wait for the results.
+pub(crate) async fn run(
+    files: Vec<PathBuf>,
+    sessions: Vec<bursts::Session>,
+    run: &RunContext,
+    reporter: &Reporter,
+) -> Result<(), String> {
+    let requests = sessions
+        .into_iter()
+        .map(Request::Session)
+        .chain(files.into_iter().map(Request::File));
+
+    let mut chunk = Chunk::default();
+    for (index, request) in requests.enumerate() {
+        let paths = request.paths();
+        let estimate = || match &request {
+            Request::File(path) => pipeline::estimate_file(path, run.descriptions),
+            Request::Session(session) => pipeline::estimate_session(session, run.descriptions),
+        };
+        let Some(reserved) =
+            reporter.reserve(run, &run.models.classify, BATCH_PRICE_FACTOR, estimate)
+        else {
+            for path in &paths {
+                reporter.deferred(pipeline::file_name(path));
+            }
+            continue;
+        };
+
+        let (base_tokens, session) = match &request {
+            Request::File(_) => (pipeline::FILE_MAX_TOKENS, None),
+            Request::Session(session) => (
+                pipeline::SESSION_MAX_TOKENS,
+                Some(BatchSession {
+                    id: session.id.clone(),
+                    started_at: session.started_at.format(SESSION_TIME_FORMAT).to_string(),
+                }),
+            ),
+        };
+        let screened = {
+            let (request, run) = (request.clone(), run.clone());
+            tokio::task::spawn_blocking(move || match &request {
+                Request::File(path) => pipeline::screen_file(path, &run),
+                Request::Session(session) => pipeline::screen_session(session, &run),
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|screened| screened)
+        };
+        let (content, redactions) = match screened {
+            Ok(Screened::Upload {
+                content,
+                redactions,
+            }) => (content, redactions),
+            Ok(Screened::Offline(proposals)) => {
+                reporter.release(reserved);
+                for proposal in proposals {
+                    reporter.proposed(proposal);
+                }
+                continue;
+            }
+            Err(e) => {
+                reporter.release(reserved);
+                for path in &paths {
+                    reporter.failed(pipeline::file_name(path), e.clone());
+                }
+                continue;
+            }
+        };
+        for path in &paths {
+            reporter.sink.emit(PipelineEvent::Processing {
+                name: pipeline::file_name(path),
+            });
+        }
+
+        let custom_id = format!("r{}", index);
+        let line = serde_json::to_string(&serde_json::json!({
+            "custom_id": custom_id,
+            "params": pipeline::message_params(
+                &run.models.classify,
+                pipeline::system_prompt(run.descriptions),
+                content,
+                pipeline::max_tokens(base_tokens, run.descriptions),
+            ),
+        }))
+        .map_err(|e| {
+            reporter.release(reserved);
+            e.to_string()
+        })?;
+        if !chunk.lines.is_empty()
+            && (chunk.lines.len() == MAX_BATCH_REQUESTS
+                || chunk.bytes + line.len() > MAX_BATCH_BYTES)
+        {
+            submit(run, reporter, std::mem::take(&mut chunk)).await;
+        }
+        chunk.bytes += line.len() + 1;
+        chunk.lines.push(line);
+        chunk.items.push(BatchItem {
+            custom_id,
+            paths: paths
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
+            session,
+            redactions,
+        });
+        chunk.reserved.push(reserved);
+    }
+    if !chunk.lines.is_empty() {
+        submit(run, reporter, chunk).await;
+    }
+
+    collect(run, reporter).await
+}
+
+// Create one batch and journal it; its files fail if the API refuses it
+async fn submit(run: &RunContext, reporter: &Reporter, chunk: Chunk) {
+    let body = format!("{{\"requests\":[{}]}}", chunk.lines.join(","));
+    let created = async {
+        let res = run
+            .client
+            .post("/v1/messages/batches", &run.api_key)
+            .header("content-type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = res.status();
+        let json: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+        json["id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| api_error(&json, status))
+    }
+    .await;
+
+    match created {
+        Ok(batch_id) => {
+            eprintln!(
+                "[RUST] ✅ Submitted batch {} with {} request(s)",
+                batch_id,
+                chunk.items.len()
+            );
+            reporter.record(SessionEvent::BatchSubmitted {
+                batch_id,
+                items: chunk.items,
+            });
+        }
+        Err(e) => {
+            eprintln!("[RUST] ❌ Failed to submit batch: {}", e);
+            for (item, reserved) in chunk.items.into_iter().zip(chunk.reserved) {
+                reporter.release(reserved);
+                for path in item.paths {
+                    reporter.failed(pipeline::file_name(Path::new(&path)), e.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Pick up the open batches of a run, e.g. after a restart, and report
+/// their results to `sink`.
+pub async fn resume(
+    run_id: &str,
+    library: &Library,
+    api_key: String,
+    sink: Arc<dyn EventSink>,
+) -> Result<(), String> {
+    let snapshot = library.sessions.load(run_id)?;
+    let run = RunContext {
+        run_id: run_id.to_string(),
+        api_key,
+        layout: snapshot.options.layout,
+        descriptions: snapshot.options.descriptions,
+        prices: library.prices.clone(),
+        models: library.models(),
+        client: library.client(),
+        privacy: library.privacy(),
+    };
+    let reporter = Reporter::new(
+        run_id,
+        library.sessions.clone(),
+        sink,
+        snapshot.cost(),
+        None,
+    );
+    collect(&run, &reporter).await?;
+    if snapshot.finished_at.is_none() {
+        reporter.record(SessionEvent::Finished {
+            finished_at: chrono::Local::now().to_rfc3339(),
+        });
+    }
+    Ok(())
+}
+
+/// Runs with batches still waiting for results.
+pub fn pending_runs(store: &SessionStore) -> Result<Vec<String>, String> {
+    Ok(store
+        .list()?
+        .into_iter()
+        .filter(|summary| summary.pending_batches > 0)
+        .map(|summary| summary.run_id)
+        .collect())
+}
+
+// Claims a run for polling until dropped, so a run is never collected twice
+struct Collecting(String);
+
+impl Collecting {
+    fn claim(run_id: &str) -> Result<Collecting, String> {
+        let mut runs = COLLECTING.lock().unwrap();
+        if runs.iter().any(|id| id == run_id) {
+            return Err(format!(
+                "Batches of run {} are already being collected",
+                run_id
+            ));
+        }
+        runs.push(run_id.to_string());
+        Ok(Collecting(run_id.to_string()))
+    }
+}
+
+impl Drop for Collecting {
+    fn drop(&mut self) {
+        COLLECTING.lock().unwrap().retain(|id| *id != self.0);
+    }
+}
+
+/// Wait for the run's open batches and report their results. Files that
+/// already have an outcome in the journal are not reported again.
+pub(crate) async fn collect(run: &RunContext, reporter: &Reporter) -> Result<(), String> {
+    let _claim = Collecting::claim(&run.run_id)?;
+    let snapshot = reporter.store.load(&run.run_id)?;
+    let done = reported_files(&snapshot);
+
+    for batch in snapshot.batches.iter().filter(|batch| !batch.ended) {
+        let status = wait(run, reporter, &batch.batch_id).await?;
+        let url = status.results_url.unwrap_or_else(|| {
+            run.client
+                .url(&format!("/v1/messages/batches/{}/results", batch.batch_id))
+        });
+        let results = run
+            .client
+            .get_url(&url, &run.api_key)?
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut outcomes = HashMap::new();
+        for line in results.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<serde_json::Value>(line) {
+                Ok(json) => {
+                    if let Some(custom_id) = json["custom_id"].as_str() {
+                        outcomes.insert(custom_id.to_string(), json["result"].clone());
+                    }
+                }
+                Err(e) => eprintln!("[RUST] ⚠️ Unreadable batch result: {}", e),
+            }
+        }
+        for item in &batch.items {
+            if item.paths.iter().all(|path| done.contains(path)) {
+                continue;
+            }
+            match outcomes.get(&item.custom_id) {
+                Some(result) => report_result(item, result, run, reporter),
+                None => fail_item(item, "missing from the batch results", reporter),
+            }
+        }
+        reporter.record(SessionEvent::BatchEnded {
+            batch_id: batch.batch_id.clone(),
+        });
+    }
+    Ok(())
+}
+
+// Poll until the batch has ended, reporting progress after every check
+async fn wait(
+    run: &RunContext,
+    reporter: &Reporter,
+    batch_id: &str,
+) -> Result<BatchStatus, String> {
+    let mut failures = 0;
+    loop {
+        let checked = async {
+            let res = run
+                .client
+                .get(&format!("/v1/messages/batches/{}", batch_id), &run.api_key)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            let status = res.status();
+            let json: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+            serde_json::from_value::<BatchStatus>(json.clone())
+                .map_err(|_| api_error(&json, status))
+        }
+        .await;
+
+        match checked {
+            Ok(status) => {
+                failures = 0;
+                reporter.sink.emit(PipelineEvent::BatchStatus {
+                    batch_id: batch_id.to_string(),
+                    status: status.processing_status.clone(),
+                    request_counts: status.request_counts.clone(),
+                });
+                if status.processing_status == "ended" {
+                    return Ok(status);
+                }
+            }
+            Err(e) => {
+                failures += 1;
+                eprintln!("[RUST] ⚠️ Batch {} status check failed: {}", batch_id, e);
+                if failures >= MAX_POLL_FAILURES {
+                    return Err(format!("Gave up polling batch {}: {}", batch_id, e));
+                }
+            }
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+fn report_result(
+    item: &BatchItem,
+    result: &serde_json::Value,
+    run: &RunContext,
+    reporter: &Reporter,
+) {
+    let names = item
+        .paths
+        .iter()
+        .map(|path| pipeline::file_name(Path::new(path)))
+        .collect::<Vec<String>>();
+    match result["type"].as_str() {
+        Some("succeeded") => {
+            let message = &result["message"];
+            let usage = TokenUsage::deserialize(&message["usage"]).unwrap_or_default();
+            let model = message["model"].as_str().unwrap_or(&run.models.classify);
+            reporter.usage(names, run, model, usage, BATCH_PRICE_FACTOR);
+            let proposals = match pipeline::reply_text(message) {
+                Some(text) => proposals(item, run, model, text).map(|mut proposals| {
+                    for proposal in &mut proposals {
+                        proposal.redactions = item.redactions.clone();
+                    }
+                    proposals
+                }),
+                None => Err("No text in batch result".to_string()),
+            };
+            match proposals {
+                Ok(proposals) => {
+                    for proposal in proposals {
+                        reporter.proposed(proposal);
+                    }
+                }
+                Err(e) => fail_item(item, &e, reporter),
+            }
+        }
+        Some("errored") => {
+            let error = result["error"]["error"]["message"]
+                .as_str()
+                .or(result["error"]["message"].as_str())
+                .unwrap_or("request failed");
+            fail_item(item, error, reporter);
+        }
+        Some("expired") => fail_item(item, "batch expired before this request ran", reporter),
+        Some("canceled") => fail_item(item, "batch was canceled", reporter),
+        _ => fail_item(item, "unknown batch result", reporter),
+    }
+}
+
+fn proposals(
+    item: &BatchItem,
+    run: &RunContext,
+    model: &str,
+    text: &str,
+) -> Result<Vec<pipeline::FileProposal>, String> {
+    match &item.session {
+        Some(session) => {
+            let session = bursts::Session {
+                id: session.id.clone(),
+                started_at: NaiveDateTime::parse_from_str(&session.started_at, SESSION_TIME_FORMAT)
+                    .map_err(|e| e.to_string())?,
+                paths: item.paths.iter().map(PathBuf::from).collect(),
+            };
+            pipeline::session_proposals(&session, run, model, text)
+        }
+        None => {
+            let path = item
+                .paths
+                .first()
+                .ok_or_else(|| "Batch request without a file".to_string())?;
+            Ok(vec![pipeline::file_proposal(
+                Path::new(path),
+                run,
+                model,
+                text,
+            )?])
+        }
+    }
+}
+
+fn fail_item(item: &BatchItem, error: &str, reporter: &Reporter) {
+    eprintln!(
+        "[RUST] ❌ Batch request {} failed: {}",
+        item.custom_id, error
+    );
+    for path in &item.paths {
+        reporter.failed(pipeline::file_name(Path::new(path)), error.to_string());
+    }
+}
+
+// Paths that already have a proposal or a failure in the journal
+fn reported_files(snapshot: &RunSnapshot) -> HashSet<String> {
+    let failed = snapshot
+        .failed
+        .iter()
+        .map(|file| file.name.as_str())
+        .collect::<HashSet<&str>>();
+    let mut done = snapshot
+        .proposals
+        .iter()
+        .map(|entry| entry.proposal.original_path.clone())
+        .collect::<HashSet<String>>();
+    for batch in &snapshot.batches {
+        for path in batch.items.iter().flat_map(|item| &item.paths) {
+            if failed.contains(pipeline::file_name(Path::new(path)).as_str()) {
+                done.insert(path.clone());
+            }
+        }
+    }
+    done
+}
+
+fn api_error(json: &serde_json::Value, status: reqwest::StatusCode) -> String {
+    match json["error"]["message"].as_str() {
+        Some(message) => format!("{} (status {})", message, status),
+        None => format!("Unexpected API response (status {})", status),
+    }
+}
@@ -0,0 +1,671 @@
+//! Headless SnapSort for scripts and cron jobs.
+//!
+//! `snapsort plan DIR --json > plan.json` analyzes screenshots and writes a
+//! move plan without touching any file; `snapsort export -o plan.csv` writes
+//! it for a spreadsheet. `snapsort apply plan.json` (or `.csv`) validates and
+//! carries it out and `snapsort undo` reverts the last applied run.
+//! `snapsort plan --batch` sends the requests as a Message Batch at half price
+//! and `snapsort batch` picks up its results after an interruption.
+//! `snapsort plan --privacy redact` blacks out secrets and personal data found
+//! by local OCR before anything is uploaded. Never-upload rules in
+//! `privacy.json` keep matching files from the API in every mode.
+//! `snapsort report` writes an HTML gallery and Markdown summary of a run, and
+//! `snapsort mcp` lets AI agents search the library. Runs, history and the
+//! search index are shared with the desktop app.
+
+use clap::{Args, Parser, Subcommand};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use tauri_app_lib::batch;
+use tauri_app_lib::capture_date::{self, FolderLayout};
+use tauri_app_lib::description::DescriptionStorage;
+use tauri_app_lib::events::{EventSink, JsonLinesSink, PipelineEvent};
+use tauri_app_lib::image_tags;
+use tauri_app_lib::mcp;
+use tauri_app_lib::models::{ModelSettings, MODELS_FILE};
+use tauri_app_lib::pipeline::{self, Library, MoveOutcome, RunContext, ScanOptions};
+use tauri_app_lib::plan::{Plan, PlanFormat};
+use tauri_app_lib::privacy::{PrivacyMode, PrivacySettings, PRIVACY_FILE};
+use tauri_app_lib::report;
+use tauri_app_lib::session_store::{RunOptions, SessionSummary};
+use tauri_app_lib::summary;
+use tauri_app_lib::usage::{self, PriceTable};
+
+#[derive(Parser)]
+#[command(
+    name = "snapsort",
+    version,
+    about = "Sort screenshots into folders with Claude"
+)]
+struct Cli {
+    /// Data directory shared with the desktop app
+    #[arg(long, env = "SNAPSORT_DATA_DIR", global = true)]
+    data_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the screenshots a plan would analyze, without calling the API
+    Scan {
+        dir: PathBuf,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Analyze screenshots and print a move plan; no file is moved
+    Plan {
+        dir: PathBuf,
+        #[arg(long)]
+        json: bool,
+        #[arg(long, env = "ANTHROPIC_API_KEY", hide_env_values = true)]
+        api_key: String,
+        /// category, category_date or date_category
+        #[arg(long, value_parser = parse_enum::<FolderLayout>)]
+        layout: Option<FolderLayout>,
+        /// Embed SnapSort tags into images on apply
+        #[arg(long)]
+        write_tags: bool,
+        #[command(flatten)]
+        selection: Selection,
+        #[command(flatten)]
+        models: ModelChoice,
+        /// Stop sending requests once the run would cost more, in USD;
+        /// the remaining files are deferred
+        #[arg(long)]
+        budget: Option<f64>,
+        /// Send all requests as one Message Batch at half price; results can
+        /// take up to a day
+        #[arg(long)]
+        batch: bool,
+        /// off, redact or offline: black out secrets and personal data found
+        /// by local OCR, or classify such files offline; needs tesseract
+        #[arg(long, value_parser = parse_enum::<PrivacyMode>)]
+        privacy: Option<PrivacyMode>,
+        /// Concurrent API requests
+        #[arg(long, default_value_t = 4)]
+        jobs: usize,
+        /// Report progress on stderr as JSON lines
+        #[arg(long)]
+        events: bool,
+    },
+    /// Wait for the Message Batches of a run and print its plan; defaults to
+    /// the latest run with open batches
+    Batch {
+        run_id: Option<String>,
+        #[arg(long)]
+        json: bool,
+        #[arg(long, env = "ANTHROPIC_API_KEY", hide_env_values = true)]
+        api_key: String,
+        /// Report progress on stderr as JSON lines
+        #[arg(long)]
+        events: bool,
+    },
+    /// Estimate the tokens and cost of a plan without calling the API
+    Estimate {
+        dir: PathBuf,
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        selection: Selection,
+        #[command(flatten)]
+        models: ModelChoice,
+    },
+    /// Write the pending moves of a run as a plan; defaults to the last run
+    Export {
+        run_id: Option<String>,
+        /// json or csv; guessed from --output when omitted
+        #[arg(long, value_parser = parse_enum::<PlanFormat>)]
+        format: Option<PlanFormat>,
+        /// File to write instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Validate a plan (JSON or CSV) and move the files it selects
+    Apply {
+        plan: PathBuf,
+        /// Only validate, move nothing
+        #[arg(long)]
+        check: bool,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Move the files of a run back; defaults to the last applied run
+    Undo {
+        run_id: Option<String>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write an HTML gallery and Markdown summary into the scan folder;
+    /// defaults to the latest run
+    Report {
+        run_id: Option<String>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Serve the screenshot library to AI agents over MCP on stdio
+    Mcp,
+}
+
+#[derive(Serialize)]
+struct ScannedFile {
+    path: String,
+    size: u64,
+    captured_at: Option<String>,
+    tagged_as: Option<String>,
+}
+
+// Plain progress lines on stderr; stdout is reserved for the plan
+struct ConsoleSink;
+
+impl EventSink for ConsoleSink {
+    fn emit(&self, event: PipelineEvent) {
+        match event {
+            PipelineEvent::Skipped { file } => eprintln!("skipped {}: {}", file.name, file.reason),
+            PipelineEvent::Failed { name, error } => eprintln!("failed {}: {}", name, error),
+            PipelineEvent::Deferred { name, reason } => eprintln!("deferred {}: {}", name, reason),
+            PipelineEvent::BatchStatus {
+                batch_id,
+                status,
+                request_counts,
+            } => eprintln!(
+                "batch {}: {}, {}/{} done",
+                batch_id,
+                status,
+                request_counts.done(),
+                request_counts.total()
+            ),
+            PipelineEvent::Summary { actionable, .. } => {
+                eprintln!("Analyzing {} screenshot(s)...", actionable)
+            }
+            PipelineEvent::Processing { .. }
+            | PipelineEvent::Proposed { .. }
+            | PipelineEvent::Usage { .. } => {}
+        }
+    }
+}
+
+/// Options that decide which files are sent to the model and how.
+#[derive(Args)]
+struct Selection {
+    /// Write rich descriptions on apply: sidecar or central
+    #[arg(long, value_parser = parse_enum::<DescriptionStorage>)]
+    describe: Option<DescriptionStorage>,
+    /// Analyze duplicates instead of skipping them
+    #[arg(long)]
+    keep_duplicates: bool,
+    /// Classify burst shots one by one
+    #[arg(long)]
+    no_bursts: bool,
+    /// Re-analyze files SnapSort already tagged
+    #[arg(long)]
+    include_tagged: bool,
+}
+
+impl Selection {
+    fn options(&self) -> ScanOptions {
+        ScanOptions {
+            skip_duplicates: Some(!self.keep_duplicates),
+            group_bursts: Some(!self.no_bursts),
+            descriptions: self.describe,
+            skip_tagged: Some(!self.include_tagged),
+            ..ScanOptions::default()
+        }
+    }
+}
+
+/// Models for classification, over the saved settings.
+#[derive(Args)]
+struct ModelChoice {
+    /// Model that classifies, or takes over escalated files
+    #[arg(long)]
+    model: Option<String>,
+    /// Cheaper model to try first; unsure or unreadable answers go to --model
+    #[arg(long)]
+    escalate_from: Option<String>,
+}
+
+impl ModelChoice {
+    fn apply(&self, mut models: ModelSettings) -> ModelSettings {
+        if let Some(model) = &self.model {
+            models.classify = model.clone();
+        }
+        if let Some(model) = &self.escalate_from {
+            models.escalate_from = Some(model.clone());
+        }
+        models
+    }
+}
+
+// Accept the same snake_case names the app uses in its JSON settings.
+fn parse_enum<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("invalid value '{}'", value))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let Some(data_dir) = cli.data_dir.or_else(pipeline::default_data_dir) else {
+        eprintln!("snapsort: could not determine the data directory, pass --data-dir");
+        return ExitCode::FAILURE;
+    };
+
+    let result = match cli.command {
+        Command::Scan { dir, json } => scan(&dir, json),
+        Command::Plan {
+            dir,
+            json,
+            api_key,
+            layout,
+            write_tags,
+            selection,
+            models,
+            budget,
+            batch,
+            privacy,
+            jobs,
+            events,
+        } => {
+            let library = Library::open(&data_dir);
+            let models = models.apply(library.models());
+            let options = ScanOptions {
+                layout,
+                write_tags: Some(write_tags),
+                budget_usd: budget,
+                batch: Some(batch),
+                privacy,
+                ..selection.options()
+            };
+            match plan(&library, &dir, api_key, models, options, jobs, events).await {
+                Ok(run_id) => print_plan(&library, &run_id, json),
+                Err(e) => Err(e),
+            }
+        }
+        Command::Batch {
+            run_id,
+            json,
+            api_key,
+            events,
+        } => resume_batch(Library::open(&data_dir), run_id, api_key, json, events).await,
+        Command::Estimate {
+            dir,
+            json,
+            selection,
+            models,
+        } => estimate(
+            &PriceTable::load(&data_dir.join(pipeline::PRICES_FILE)),
+            &models.apply(ModelSettings::load(&data_dir.join(MODELS_FILE))),
+            &PrivacySettings::load(&data_dir.join(PRIVACY_FILE)),
+            &dir,
+            &selection,
+            json,
+        ),
+        Command::Export {
+            run_id,
+            format,
+            output,
+        } => export(&Library::open(&data_dir), run_id, format, output),
+        Command::Apply { plan, check, json } => {
+            apply(&Library::open(&data_dir), &plan, check, json)
+        }
+        Command::Undo { run_id, json } => undo(&Library::open(&data_dir), run_id, json),
+        Command::Report { run_id, json } => write_report(&Library::open(&data_dir), run_id, json),
+        // Read-only, so it can run while the app holds the search index.
+        Command::Mcp => mcp::serve(
+            &Library::open_read_only(&data_dir),
+            std::io::stdin().lock(),
+            std::io::stdout().lock(),
+        )
+        .map(|_| ExitCode::SUCCESS),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("snapsort: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn scan_root(dir: &Path) -> Result<PathBuf, String> {
+    std::fs::canonicalize(dir).map_err(|e| format!("{}: {}", dir.display(), e))
+}
+
+fn scan(dir: &Path, json: bool) -> Result<ExitCode, String> {
+    let root = scan_root(dir)?;
+    let scan = pipeline::collect_files(&root, None);
+    let files = scan
+        .files
+        .iter()
+        .map(|path| ScannedFile {
+            path: path.to_string_lossy().to_string(),
+            size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            captured_at: capture_date::capture_date(path)
+                .map(|c| c.captured_at.format("%Y-%m-%dT%H:%M:%S").to_string()),
+            tagged_as: image_tags::read_tags(path).map(|tags| tags.category),
+        })
+        .collect::<Vec<ScannedFile>>();
+
+    if json {
+        print_json(&files)?;
+    } else {
+        for file in &files {
+            println!(
+                "{}\t{}\t{}\t{}",
+                file.path,
+                file.size,
+                file.captured_at.as_deref().unwrap_or("-"),
+                file.tagged_as.as_deref().unwrap_or("-")
+            );
+        }
+        for skipped in &scan.skipped {
+            eprintln!("skipped {}: {}", skipped.name, skipped.reason);
+        }
+        eprintln!("{} screenshot(s) found", files.len());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn estimate(
+    prices: &PriceTable,
+    models: &ModelSettings,
+    privacy: &PrivacySettings,
+    dir: &Path,
+    selection: &Selection,
+    json: bool,
+) -> Result<ExitCode, String> {
+    let root = scan_root(dir)?;
+    let options = selection.options();
+    let estimate = pipeline::estimate_scan(
+        pipeline::collect_files(&root, None).files,
+        &options,
+        privacy,
+        models,
+        prices,
+    );
+    if json {
+        print_json(&estimate)?;
+    } else {
+        println!(
+            "{} file(s) in {} request(s) to {}: ~{} input / ~{} output tokens, ~{}",
+            estimate.files,
+            estimate.requests,
+            estimate.model,
+            summary::format_tokens(estimate.input_tokens),
+            summary::format_tokens(estimate.output_tokens),
+            estimate
+                .cost_usd
+                .map(usage::format_cost)
+                .unwrap_or_else(|| "unknown cost".to_string())
+        );
+        if let Some(model) = &estimate.escalation_model {
+            println!(
+                "Unsure answers go on to {}, which is not included; up to ~{} if every request is escalated.",
+                model,
+                estimate
+                    .max_cost_usd
+                    .map(usage::format_cost)
+                    .unwrap_or_else(|| "an unknown cost".to_string())
+            );
+        }
+        if !estimate.prompt_cached {
+            println!(
+                "The system prompt is too short for {} to cache; every request pays for it in full.",
+                estimate.model
+            );
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// Classify the files of `dir` as a new run and return its ID
+async fn plan(
+    library: &Library,
+    dir: &Path,
+    api_key: String,
+    models: ModelSettings,
+    options: ScanOptions,
+    jobs: usize,
+    events: bool,
+) -> Result<String, String> {
+    let root = scan_root(dir)?;
+    let root_path = root.to_string_lossy().to_string();
+    let scan = pipeline::collect_files(&root, None);
+
+    let run_id = library.sessions.start(
+        &root_path,
+        &scan.files,
+        RunOptions {
+            descriptions: options.descriptions,
+            write_tags: options.write_tags.unwrap_or(false),
+            layout: options.layout.unwrap_or_default(),
+        },
+    )?;
+    let run = RunContext {
+        run_id: run_id.clone(),
+        api_key,
+        layout: options.layout.unwrap_or_default(),
+        descriptions: options.descriptions,
+        prices: library.prices.clone(),
+        models,
+        client: library.client(),
+        privacy: library.privacy(),
+    };
+
+    pipeline::run_scan(
+        scan,
+        options,
+        run,
+        library.sessions.clone(),
+        progress_sink(events),
+        Some(jobs),
+    )
+    .await?;
+    Ok(run_id)
+}
+
+async fn resume_batch(
+    library: Library,
+    run_id: Option<String>,
+    api_key: String,
+    json: bool,
+    events: bool,
+) -> Result<ExitCode, String> {
+    let run_id = match run_id {
+        Some(run_id) => run_id,
+        None => latest_run(&library, |summary| summary.pending_batches > 0)?,
+    };
+    batch::resume(&run_id, &library, api_key, progress_sink(events)).await?;
+    print_plan(&library, &run_id, json)
+}
+
+fn progress_sink(events: bool) -> Arc<dyn EventSink> {
+    if events {
+        Arc::new(JsonLinesSink::new(std::io::stderr()))
+    } else {
+        Arc::new(ConsoleSink)
+    }
+}
+
+fn print_plan(library: &Library, run_id: &str, json: bool) -> Result<ExitCode, String> {
+    let snapshot = library.sessions.load(run_id)?;
+    let root = PathBuf::from(&snapshot.root_path);
+    let plan = Plan::from_run(&snapshot);
+    if json {
+        print_json(&plan)?;
+    } else {
+        for planned in &plan.moves {
+            let relative = Path::new(&planned.proposed_path)
+                .strip_prefix(&root)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| planned.proposed_path.clone());
+            println!(
+                "{} -> {}  ({}){}",
+                file_name(Path::new(&planned.original_path)),
+                relative,
+                planned.reasoning,
+                planned
+                    .conflict
+                    .as_ref()
+                    .map(|conflict| format!("  [conflict: {}]", conflict))
+                    .unwrap_or_default()
+            );
+        }
+        let cost = snapshot.cost();
+        eprintln!(
+            "Run {}: {} move(s) planned, {} conflict(s), {} failed, {} deferred. \
+             {} input / {} output tokens, {} read from cache, {}. \
+             Review with `snapsort export -o plan.csv`, then `snapsort apply plan.csv`.",
+            plan.run_id,
+            plan.moves.len(),
+            plan.conflicts(),
+            snapshot.failed.len(),
+            snapshot.deferred.len(),
+            summary::format_tokens(cost.input_tokens),
+            summary::format_tokens(cost.output_tokens),
+            summary::format_tokens(cost.cache_read_input_tokens),
+            usage::format_cost(cost.cost_usd)
+        );
+    }
+
+    Ok(if snapshot.failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn export(
+    library: &Library,
+    run_id: Option<String>,
+    format: Option<PlanFormat>,
+    output: Option<PathBuf>,
+) -> Result<ExitCode, String> {
+    let run_id = match run_id {
+        Some(run_id) => run_id,
+        None => latest_run(library, |summary| summary.proposed > summary.applied)?,
+    };
+    let plan = Plan::from_run(&library.sessions.load(&run_id)?);
+    let format = format
+        .or_else(|| output.as_deref().map(PlanFormat::from_path))
+        .unwrap_or(PlanFormat::Json);
+    let text = plan.export(format)?;
+    match output {
+        Some(path) => {
+            std::fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+            eprintln!(
+                "Wrote {} move(s), {} conflict(s) to {}",
+                plan.moves.len(),
+                plan.conflicts(),
+                path.display()
+            );
+        }
+        None => print!("{}", text),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn apply(library: &Library, plan_path: &Path, check: bool, json: bool) -> Result<ExitCode, String> {
+    let text = std::fs::read_to_string(plan_path).map_err(|e| e.to_string())?;
+    let plan = Plan::import(&text, PlanFormat::from_path(plan_path), library)?;
+
+    // Nothing moves unless every selected move can go through as written.
+    let problems = plan.validate(library)?;
+    for problem in &problems {
+        eprintln!("invalid {}", problem);
+    }
+    if !problems.is_empty() {
+        eprintln!("{} problem(s), no file moved", problems.len());
+        return Ok(ExitCode::FAILURE);
+    }
+    if check {
+        let selected = plan.moves.iter().filter(|planned| planned.selected).count();
+        eprintln!("Plan is valid: {} move(s) selected", selected);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let outcomes = plan.apply(library);
+    report(&outcomes, "moved", json)
+}
+
+fn latest_run(
+    library: &Library,
+    wanted: impl Fn(&SessionSummary) -> bool,
+) -> Result<String, String> {
+    library
+        .sessions
+        .list()?
+        .into_iter()
+        .find(|summary| wanted(summary))
+        .map(|summary| summary.run_id)
+        .ok_or_else(|| "No matching run, pass a run id".to_string())
+}
+
+fn undo(library: &Library, run_id: Option<String>, json: bool) -> Result<ExitCode, String> {
+    let run_id = match run_id {
+        Some(run_id) => run_id,
+        None => latest_run(library, |summary| summary.applied > 0)?,
+    };
+    let outcomes = library.undo_run(&run_id)?;
+    report(&outcomes, "restored", json)
+}
+
+fn write_report(library: &Library, run_id: Option<String>, json: bool) -> Result<ExitCode, String> {
+    let run_id = match run_id {
+        Some(run_id) => run_id,
+        None => latest_run(library, |_| true)?,
+    };
+    let files = report::write_reports(&library.sessions.load(&run_id)?)?;
+    if json {
+        print_json(&files)?;
+    } else {
+        println!("{}", files.html.display());
+        println!("{}", files.markdown.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn report(outcomes: &[MoveOutcome], verb: &str, json: bool) -> Result<ExitCode, String> {
+    let failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+    if json {
+        print_json(&outcomes)?;
+    } else {
+        for outcome in outcomes {
+            match (&outcome.final_path, &outcome.error) {
+                (Some(final_path), _) => println!("{} -> {}", outcome.original_path, final_path),
+                (None, Some(error)) => eprintln!("failed {}: {}", outcome.original_path, error),
+                (None, None) => {}
+            }
+        }
+        eprintln!(
+            "{} {} file(s), {} failed",
+            verb,
+            outcomes.len() - failed,
+            failed
+        );
+    }
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", text);
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
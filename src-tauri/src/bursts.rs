@@ -0,0 +1,282 @@
+//! Burst grouping: screenshots taken seconds apart while stepping through a
+//! flow (checkout, onboarding, a bug repro) are clustered into one session.
+//!
+//! Capture times come from the screenshot filename (see `capture_date`),
+//! falling back to the file's mtime. Consecutive shots join a session when
+//! they are close in time and still look alike.
+
+use crate::capture_date;
+use crate::dedup::{self, Fingerprint};
+use chrono::{DateTime, Local, NaiveDateTime};
+use std::path::PathBuf;
+
+/// Largest gap between two consecutive shots of the same session.
+pub const DEFAULT_MAX_GAP_SECS: i64 = 90;
+/// Minimum dHash similarity between consecutive shots of the same session.
+/// Screens of one flow share window chrome, unrelated screens hover near 0.5.
+pub const DEFAULT_MIN_SIMILARITY: f32 = 0.7;
+/// The model sees at most this many images per session.
+pub const MAX_SAMPLE_IMAGES: usize = 3;
+
+#[derive(Clone)]
+pub struct Session {
+    pub id: String,
+    /// Capture time of the first shot.
+    pub started_at: NaiveDateTime,
+    /// Members in capture order.
+    pub paths: Vec<PathBuf>,
+}
+
+impl Session {
+    /// First, middle and last shot, enough to tell what the flow is about.
+    pub fn sample_paths(&self) -> Vec<PathBuf> {
+        let mut indices = vec![0, self.paths.len() / 2, self.paths.len() - 1];
+        indices.dedup();
+        indices
+            .into_iter()
+            .take(MAX_SAMPLE_IMAGES)
+            .map(|i| self.paths[i].clone())
+            .collect()
+    }
+}
+
+/// Capture time from the filename, or the file's mtime if the name has none.
+pub fn capture_time(fingerprint: &Fingerprint) -> Option<NaiveDateTime> {
+    let filename = fingerprint.path.file_name()?.to_string_lossy().to_string();
+    let parsed = capture_date::parse_filename(&filename).map(|date| date.captured_at);
+    parsed.or_else(|| {
+        fingerprint
+            .modified
+            .map(|modified| DateTime::<Local>::from(modified).naive_local())
+    })
+}
+
+/// Cluster fingerprinted files into sessions of two or more shots. Files
+/// without any usable timestamp never join a session.
+pub fn group_sessions(
+    fingerprints: &[Fingerprint],
+    max_gap_secs: i64,
+    min_similarity: f32,
+) -> Vec<Session> {
+    let mut timed = fingerprints
+        .iter()
+        .filter_map(|fp| capture_time(fp).map(|time| (time, fp)))
+        .collect::<Vec<(NaiveDateTime, &Fingerprint)>>();
+    timed.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.path.cmp(&b.1.path)));
+
+    let mut runs: Vec<Vec<(NaiveDateTime, &Fingerprint)>> = Vec::new();
+    for item in timed {
+        let joins_previous =
+            runs.last()
+                .and_then(|run| run.last())
+                .is_some_and(|(previous_time, previous)| {
+                    let gap = (item.0 - *previous_time).num_seconds();
+                    gap <= max_gap_secs
+                        && dedup::similarity(previous.dhash, item.1.dhash) >= min_similarity
+                });
+
+        match runs.last_mut() {
+            Some(run) if joins_previous => run.push(item),
+            _ => runs.push(vec![item]),
+        }
+    }
+
+    runs.into_iter()
+        .filter(|run| run.len() > 1)
+        .map(|run| Session {
+            id: format!("burst-{}", run[0].0.format("%Y%m%d-%H%M%S")),
+            started_at: run[0].0,
+            paths: run.into_iter().map(|(_, fp)| fp.path.clone()).collect(),
+        })
+        .collect()
+}
+
+/// Sequential member name, e.g. `checkout_flow_01.png`.
+pub fn sequence_name(session_name: &str, index: usize, total: usize) -> String {
+    let width = total.to_string().len().max(2);
+    format!("{}_{:0width$}.png", session_name, index + 1, width = width)
+}
+
+/// Normalize a model-provided name into a safe snake_case segment.
+pub fn slugify(name: &str) -> String {
+    let slug = name
+        .trim()
+        .trim_end_matches(".png")
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_");
+
+    if slug.is_empty() {
+        "session".to_string()
+    } else {
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    // A shot named after its capture time on 2025-03-14, e.g. `10.22.33`
+    fn shot(time: &str, dhash: u64) -> Fingerprint {
+        Fingerprint {
+            path: PathBuf::from(format!("/shots/Screenshot 2025-03-14 at {}.png", time)),
+            sha256: time.to_string(),
+            dhash,
+            size: 0,
+            modified: None,
+        }
+    }
+
+    fn names(session: &Session) -> Vec<String> {
+        session
+            .paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn shots_within_the_gap_form_one_session() {
+        let sessions = group_sessions(
+            &[
+                shot("10.01.30", 0),
+                shot("10.00.00", 0),
+                shot("10.03.00", 0),
+                // 91 seconds after the previous shot
+                shot("10.04.31", 0),
+                shot("10.05.00", 0),
+                // Alone
+                shot("11.00.00", 0),
+            ],
+            DEFAULT_MAX_GAP_SECS,
+            DEFAULT_MIN_SIMILARITY,
+        );
+
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].id, "burst-20250314-100000");
+        assert_eq!(
+            names(&sessions[0]),
+            vec![
+                "Screenshot 2025-03-14 at 10.00.00.png",
+                "Screenshot 2025-03-14 at 10.01.30.png",
+                "Screenshot 2025-03-14 at 10.03.00.png",
+            ]
+        );
+        assert_eq!(
+            sessions[0].started_at.format("%H:%M:%S").to_string(),
+            "10:00:00"
+        );
+        assert_eq!(sessions[1].id, "burst-20250314-100431");
+        assert_eq!(sessions[1].paths.len(), 2);
+    }
+
+    #[test]
+    fn screens_that_look_different_split_a_session() {
+        // 32 of 64 bits differ: similarity 0.5
+        let sessions = group_sessions(
+            &[
+                shot("10.00.00", 0),
+                shot("10.00.10", 0),
+                shot("10.00.20", u64::MAX >> 32),
+                shot("10.00.30", u64::MAX >> 32),
+            ],
+            DEFAULT_MAX_GAP_SECS,
+            DEFAULT_MIN_SIMILARITY,
+        );
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].paths.len(), 2);
+        assert_eq!(sessions[1].paths.len(), 2);
+
+        // Exactly at the threshold still joins
+        let sessions = group_sessions(
+            &[shot("10.00.00", 0), shot("10.00.10", u64::MAX >> 32)],
+            DEFAULT_MAX_GAP_SECS,
+            0.5,
+        );
+        assert_eq!(sessions.len(), 1);
+    }
+
+    #[test]
+    fn the_mtime_stands_in_for_a_name_without_a_date() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let untimed = |name: &str, modified: Option<SystemTime>| Fingerprint {
+            path: PathBuf::from(format!("/shots/{}", name)),
+            sha256: name.to_string(),
+            dhash: 0,
+            size: 0,
+            modified,
+        };
+        let sessions = group_sessions(
+            &[
+                untimed("a.png", Some(start)),
+                untimed("b.png", Some(start + Duration::from_secs(30))),
+                untimed("c.png", None),
+                untimed("d.png", None),
+            ],
+            DEFAULT_MAX_GAP_SECS,
+            DEFAULT_MIN_SIMILARITY,
+        );
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(names(&sessions[0]), vec!["a.png", "b.png"]);
+    }
+
+    #[test]
+    fn samples_are_the_first_middle_and_last_shot() {
+        let session = |count: usize| Session {
+            id: "burst".to_string(),
+            started_at: NaiveDateTime::default(),
+            paths: (0..count)
+                .map(|i| PathBuf::from(format!("{}.png", i)))
+                .collect(),
+        };
+        let sampled = |count: usize| {
+            session(count)
+                .sample_paths()
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(sampled(1), vec!["0.png"]);
+        assert_eq!(sampled(2), vec!["0.png", "1.png"]);
+        assert_eq!(sampled(3), vec!["0.png", "1.png", "2.png"]);
+        assert_eq!(sampled(10), vec!["0.png", "5.png", "9.png"]);
+    }
+
+    #[test]
+    fn sequence_names_are_numbered_to_a_common_width() {
+        assert_eq!(sequence_name("checkout_flow", 0, 3), "checkout_flow_01.png");
+        assert_eq!(
+            sequence_name("checkout_flow", 8, 12),
+            "checkout_flow_09.png"
+        );
+        assert_eq!(
+            sequence_name("checkout_flow", 4, 120),
+            "checkout_flow_005.png"
+        );
+        assert_eq!(
+            sequence_name("checkout_flow", 119, 120),
+            "checkout_flow_120.png"
+        );
+    }
+
+    #[test]
+    fn slugs_are_lowercase_snake_case() {
+        assert_eq!(slugify("  Checkout Flow.png "), "checkout_flow");
+        assert_eq!(
+            slugify("Stripe -- Billing/Settings"),
+            "stripe_billing_settings"
+        );
+        assert_eq!(slugify("__onboarding__step__"), "onboarding_step");
+        assert_eq!(slugify("Übersicht 2"), "bersicht_2");
+        assert_eq!(slugify("../"), "session");
+        assert_eq!(slugify(""), "session");
+    }
+}
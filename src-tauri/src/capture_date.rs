@@ -0,0 +1,337 @@
+//! Capture-date parsing from screenshot filenames, and the date-based folder
+//! layouts built on top of it.
+//!
+//! Most screenshot tools stamp the capture time into the filename. Each
+//! pattern below covers one family of tools; the first match wins, and the
+//! file's mtime is the last resort.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Where a capture date came from.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSource {
+    /// "Screenshot 2025-03-14 at 10.22.33.png", "Screen Shot 2019-05-01 at 3.04.05 PM.png"
+    Macos,
+    /// "Screenshot 2025-03-14 102233.png" (Snipping Tool)
+    Windows,
+    /// "Screenshot from 2025-03-14 10-22-33.png"
+    Gnome,
+    /// "Screenshot_20250314_102233.png" (Spectacle)
+    Kde,
+    /// "Screenshot_20250314-102233_Chrome.png", "Screenshot_2025-03-14-10-22-33-123_com.app.png"
+    Android,
+    /// "2025-03-14 10_22_33-Window Title.png"
+    Greenshot,
+    /// "2025-03-14_10-22.png"
+    Flameshot,
+    /// "chrome_2025-03-14_10-22-33.png"
+    Sharex,
+    /// Any other name with a recognizable date and time in it
+    Filename,
+    /// No date in the name, fell back to the file's modification time
+    Modified,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CaptureDate {
+    pub captured_at: NaiveDateTime,
+    pub source: CaptureSource,
+}
+
+// Every pattern names its groups y, mo, d, h, mi and optionally s and ampm.
+static PATTERNS: LazyLock<Vec<(CaptureSource, Regex)>> = LazyLock::new(|| {
+    [
+        (
+            CaptureSource::Macos,
+            r"^screen ?shot (?P<y>\d{4})-(?P<mo>\d{2})-(?P<d>\d{2}) at (?P<h>\d{1,2})\.(?P<mi>\d{2})\.(?P<s>\d{2})(?: ?(?P<ampm>[ap]m))?",
+        ),
+        (
+            CaptureSource::Windows,
+            r"^screenshot (?P<y>\d{4})-(?P<mo>\d{2})-(?P<d>\d{2}) (?P<h>\d{2})(?P<mi>\d{2})(?P<s>\d{2})\b",
+        ),
+        (
+            CaptureSource::Gnome,
+            r"^screenshot from (?P<y>\d{4})-(?P<mo>\d{2})-(?P<d>\d{2}) (?P<h>\d{2})[-:](?P<mi>\d{2})[-:](?P<s>\d{2})",
+        ),
+        (
+            CaptureSource::Kde,
+            r"^screenshot_(?P<y>\d{4})(?P<mo>\d{2})(?P<d>\d{2})_(?P<h>\d{2})(?P<mi>\d{2})(?P<s>\d{2})",
+        ),
+        (
+            CaptureSource::Android,
+            r"^screenshot_(?P<y>\d{4})(?P<mo>\d{2})(?P<d>\d{2})-(?P<h>\d{2})(?P<mi>\d{2})(?P<s>\d{2})",
+        ),
+        (
+            CaptureSource::Android,
+            r"^screenshot_(?P<y>\d{4})-(?P<mo>\d{2})-(?P<d>\d{2})-(?P<h>\d{2})-(?P<mi>\d{2})-(?P<s>\d{2})",
+        ),
+        (
+            CaptureSource::Greenshot,
+            r"^(?P<y>\d{4})-(?P<mo>\d{2})-(?P<d>\d{2}) (?P<h>\d{2})_(?P<mi>\d{2})_(?P<s>\d{2})",
+        ),
+        (
+            CaptureSource::Flameshot,
+            r"^(?P<y>\d{4})-(?P<mo>\d{2})-(?P<d>\d{2})_(?P<h>\d{2})-(?P<mi>\d{2})(?:-(?P<s>\d{2}))?\.",
+        ),
+        (
+            CaptureSource::Sharex,
+            r"^[^.]+_(?P<y>\d{4})-(?P<mo>\d{2})-(?P<d>\d{2})_(?P<h>\d{2})-(?P<mi>\d{2})-(?P<s>\d{2})(?:\.\w+)?$",
+        ),
+        (
+            CaptureSource::Filename,
+            r"(?P<y>(?:19|20)\d{2})[-_.]?(?P<mo>\d{2})[-_.]?(?P<d>\d{2})[ _T-]+(?:at )?(?P<h>\d{2})[-_.:]?(?P<mi>\d{2})[-_.:]?(?P<s>\d{2})",
+        ),
+    ]
+    .into_iter()
+    .map(|(source, pattern)| {
+        let regex = Regex::new(&format!("(?i){}", pattern)).expect("valid capture pattern");
+        (source, regex)
+    })
+    .collect()
+});
+
+/// Parse a capture date out of a screenshot filename.
+pub fn parse_filename(filename: &str) -> Option<CaptureDate> {
+    // Recent macOS puts a narrow no-break space before AM/PM.
+    let filename = filename.replace('\u{202f}', " ");
+
+    PATTERNS.iter().find_map(|(source, regex)| {
+        let captures = regex.captures(&filename)?;
+        Some(CaptureDate {
+            captured_at: datetime_from(&captures)?,
+            source: *source,
+        })
+    })
+}
+
+/// Whether the name follows a screenshot tool's naming scheme, including
+/// tools like Greenshot that leave "screenshot" out of the name. Any other
+/// dated name does not count; photos are named like that too.
+pub fn is_screenshot_name(filename: &str) -> bool {
+    parse_filename(filename).is_some_and(|date| date.source != CaptureSource::Filename)
+}
+
+/// Capture date from the filename, or the file's mtime if the name has none.
+pub fn capture_date(path: &Path) -> Option<CaptureDate> {
+    let filename = path.file_name()?.to_string_lossy().to_string();
+    parse_filename(&filename).or_else(|| {
+        let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+        Some(CaptureDate {
+            captured_at: DateTime::<Local>::from(modified).naive_local(),
+            source: CaptureSource::Modified,
+        })
+    })
+}
+
+fn datetime_from(captures: &Captures) -> Option<NaiveDateTime> {
+    let number = |name: &str| -> Option<u32> { captures.name(name)?.as_str().parse().ok() };
+
+    let date = NaiveDate::from_ymd_opt(
+        captures.name("y")?.as_str().parse().ok()?,
+        number("mo")?,
+        number("d")?,
+    )?;
+
+    let mut hour = number("h")?;
+    match captures
+        .name("ampm")
+        .map(|m| m.as_str().to_lowercase())
+        .as_deref()
+    {
+        Some("am") if hour == 12 => hour = 0,
+        Some("pm") if hour < 12 => hour += 12,
+        _ => {}
+    }
+    let time = NaiveTime::from_hms_opt(hour, number("mi")?, number("s").unwrap_or(0))?;
+
+    Some(date.and_time(time))
+}
+
+/// The category part of a destination folder, without the year and month
+/// folders a layout adds: `Finance/Invoices` for `2025/03/Finance/Invoices`.
+pub fn category_of(folder: &str) -> String {
+    folder
+        .split('/')
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty() && !part.chars().all(|c| c.is_ascii_digit()))
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+/// How the date is combined with the category when building a destination.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderLayout {
+    /// `Finance/Invoices`
+    #[default]
+    Category,
+    /// `Finance/Invoices/2025/03`
+    CategoryDate,
+    /// `2025/03/Finance/Invoices`
+    DateCategory,
+}
+
+impl FolderLayout {
+    /// Build the destination folder for a category. Files without a capture
+    /// date keep the plain category.
+    pub fn apply(self, category: &str, captured_at: Option<NaiveDateTime>) -> String {
+        let Some(captured_at) = captured_at else {
+            return category.to_string();
+        };
+        let date = captured_at.format("%Y/%m").to_string();
+
+        match self {
+            FolderLayout::Category => category.to_string(),
+            FolderLayout::CategoryDate => format!("{}/{}", category, date),
+            FolderLayout::DateCategory => format!("{}/{}", date, category),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_tool_naming_scheme() {
+        use CaptureSource::*;
+        let cases = [
+            (
+                "Screenshot 2025-03-14 at 10.22.33.png",
+                Macos,
+                "2025-03-14 10:22:33",
+            ),
+            (
+                "Screen Shot 2019-05-01 at 3.04.05 PM.png",
+                Macos,
+                "2019-05-01 15:04:05",
+            ),
+            (
+                "Screenshot 2025-03-14 at 12.01.02\u{202f}AM.png",
+                Macos,
+                "2025-03-14 00:01:02",
+            ),
+            (
+                "Screenshot 2025-03-14 102233.png",
+                Windows,
+                "2025-03-14 10:22:33",
+            ),
+            (
+                "Screenshot from 2025-03-14 10-22-33.png",
+                Gnome,
+                "2025-03-14 10:22:33",
+            ),
+            ("Screenshot_20250314_102233.png", Kde, "2025-03-14 10:22:33"),
+            (
+                "Screenshot_20250314-102233_Chrome.png",
+                Android,
+                "2025-03-14 10:22:33",
+            ),
+            (
+                "Screenshot_2025-03-14-10-22-33-123_com.app.png",
+                Android,
+                "2025-03-14 10:22:33",
+            ),
+            (
+                "2025-03-14 10_22_33-Window Title.png",
+                Greenshot,
+                "2025-03-14 10:22:33",
+            ),
+            ("2025-03-14_10-22.png", Flameshot, "2025-03-14 10:22:00"),
+            ("2025-03-14_10-22-33.png", Flameshot, "2025-03-14 10:22:33"),
+            (
+                "chrome_2025-03-14_10-22-33.png",
+                Sharex,
+                "2025-03-14 10:22:33",
+            ),
+            ("IMG_20250314_102233.png", Filename, "2025-03-14 10:22:33"),
+        ];
+        for (filename, source, expected) in cases {
+            let date = parse_filename(filename).unwrap_or_else(|| panic!("{}", filename));
+            assert_eq!(date.source, source, "{}", filename);
+            assert_eq!(
+                date.captured_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                expected,
+                "{}",
+                filename
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_names_without_a_valid_date() {
+        for filename in [
+            "Screenshot.png",
+            "holiday.png",
+            "Screenshot 2025-13-01 at 10.22.33.png",
+            "Screenshot 2025-02-30 102233.png",
+            "2025-03-14 25_22_33-Window.png",
+            "report_2025.png",
+        ] {
+            assert!(parse_filename(filename).is_none(), "{}", filename);
+        }
+    }
+
+    #[test]
+    fn sharex_dates_must_end_the_name() {
+        // A ShareX-like date inside a longer name is only a dated file
+        for filename in [
+            "backup_2025-03-14_10-22-33_final.png",
+            "export_2025-03-14_10-22-33.png.bak",
+            "_2025-03-14_10-22-33.png",
+        ] {
+            assert!(
+                parse_filename(filename).is_none_or(|date| date.source != CaptureSource::Sharex),
+                "{}",
+                filename
+            );
+            assert!(!is_screenshot_name(filename), "{}", filename);
+        }
+    }
+
+    #[test]
+    fn tool_names_count_as_screenshots_but_dated_photos_do_not() {
+        assert!(is_screenshot_name("2025-03-14 10_22_33-Window Title.png"));
+        assert!(is_screenshot_name("2025-03-14_10-22.png"));
+        assert!(is_screenshot_name("chrome_2025-03-14_10-22-33.png"));
+        assert!(is_screenshot_name(
+            "Code - Insiders_2025-03-14_10-22-33.jpg"
+        ));
+        assert!(!is_screenshot_name("IMG_20250314_102233.png"));
+        assert!(!is_screenshot_name("holiday.png"));
+    }
+
+    #[test]
+    fn layouts_place_the_date() {
+        let captured_at = parse_filename("Screenshot_20250314_102233.png").map(|d| d.captured_at);
+        assert_eq!(
+            FolderLayout::Category.apply("Finance/Invoices", captured_at),
+            "Finance/Invoices"
+        );
+        assert_eq!(
+            FolderLayout::CategoryDate.apply("Finance/Invoices", captured_at),
+            "Finance/Invoices/2025/03"
+        );
+        assert_eq!(
+            FolderLayout::DateCategory.apply("Finance/Invoices", captured_at),
+            "2025/03/Finance/Invoices"
+        );
+        assert_eq!(FolderLayout::DateCategory.apply("Finance", None), "Finance");
+
+        for layout in [
+            FolderLayout::Category,
+            FolderLayout::CategoryDate,
+            FolderLayout::DateCategory,
+        ] {
+            assert_eq!(
+                category_of(&layout.apply("Finance/Invoices", captured_at)),
+                "Finance/Invoices"
+            );
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! The Anthropic API key, saved once in `api_key.enc` in the app config
+//! directory.
+//!
+//! The file is sealed with ChaCha20-Poly1305 under a random key kept in
+//! `secret.key` beside it; both are readable by the user only. This keeps
+//! the key out of plain-text settings, copies of a single file and logs.
+//! Anyone who can read both files as the user can still recover it.
+
+use crate::http_client::ApiClient;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const KEY_FILE: &str = "api_key.enc";
+const SECRET_FILE: &str = "secret.key";
+const NONCE_LEN: usize = 12;
+
+/// The saved key, decrypted once at startup and held in memory.
+pub struct KeyStore {
+    dir: PathBuf,
+    api_key: Mutex<Option<String>>,
+}
+
+impl KeyStore {
+    /// Store in `dir`. A file that cannot be decrypted is reported and
+    /// treated as no key.
+    pub fn open(dir: &Path) -> KeyStore {
+        let api_key = read(dir).unwrap_or_else(|e| {
+            eprintln!("[RUST] ⚠️ Saved API key not loaded: {}", e);
+            None
+        });
+        KeyStore {
+            dir: dir.to_path_buf(),
+            api_key: Mutex::new(api_key),
+        }
+    }
+
+    pub fn get(&self) -> Option<String> {
+        self.api_key.lock().unwrap().clone()
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.api_key.lock().unwrap().is_some()
+    }
+
+    /// Encrypt `api_key` to disk and use it from now on.
+    pub fn set(&self, api_key: &str) -> Result<(), String> {
+        let cipher = cipher(&self.dir, true)?;
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let mut sealed = nonce.to_vec();
+        sealed.extend(
+            cipher
+                .encrypt(Nonce::from_slice(&nonce), api_key.as_bytes())
+                .map_err(|_| "Failed to encrypt the API key".to_string())?,
+        );
+        write_private(&self.dir.join(KEY_FILE), &sealed)?;
+        *self.api_key.lock().unwrap() = Some(api_key.to_string());
+        Ok(())
+    }
+
+    /// Forget the key and delete its file.
+    pub fn clear(&self) -> Result<(), String> {
+        match std::fs::remove_file(self.dir.join(KEY_FILE)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.to_string()),
+        }
+        *self.api_key.lock().unwrap() = None;
+        Ok(())
+    }
+}
+
+fn read(dir: &Path) -> Result<Option<String>, String> {
+    let sealed = match std::fs::read(dir.join(KEY_FILE)) {
+        Ok(sealed) => sealed,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    if sealed.len() <= NONCE_LEN {
+        return Err(format!("{} is truncated", KEY_FILE));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let plain = cipher(dir, false)?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| format!("{} does not match {}", KEY_FILE, SECRET_FILE))?;
+    String::from_utf8(plain)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Cipher under the key in `secret.key`, created on first save.
+fn cipher(dir: &Path, create: bool) -> Result<ChaCha20Poly1305, String> {
+    let path = dir.join(SECRET_FILE);
+    let secret = match std::fs::read(&path) {
+        Ok(secret) => secret,
+        Err(e) if create && e.kind() == std::io::ErrorKind::NotFound => {
+            let secret: [u8; 32] = rand::random();
+            write_private(&path, &secret)?;
+            secret.to_vec()
+        }
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    if secret.len() != 32 {
+        return Err(format!("{} is not a 32-byte key", path.display()));
+    }
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&secret)))
+}
+
+fn write_private(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, bytes).map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Check `api_key` against the API. Listing one model is authenticated but
+/// uses no tokens.
+pub async fn validate(client: &ApiClient, api_key: &str) -> Result<(), String> {
+    let res = client
+        .get("/v1/models?limit=1", api_key)
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach the API: {}", e))?;
+    match res.status() {
+        status if status.is_success() => Ok(()),
+        reqwest::StatusCode::UNAUTHORIZED => Err("The API key was rejected".to_string()),
+        reqwest::StatusCode::FORBIDDEN => {
+            Err("The API key is not allowed to use the API".to_string())
+        }
+        status => Err(format!("Could not check the API key: HTTP {}", status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_saved_key_is_encrypted_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyStore::open(dir.path());
+        assert!(!store.is_set());
+        store.set("sk-ant-test-123").unwrap();
+
+        let sealed = std::fs::read(dir.path().join(KEY_FILE)).unwrap();
+        assert!(!sealed
+            .windows("sk-ant".len())
+            .any(|window| window == b"sk-ant"));
+        assert_eq!(
+            KeyStore::open(dir.path()).get().as_deref(),
+            Some("sk-ant-test-123")
+        );
+
+        store.clear().unwrap();
+        assert!(!dir.path().join(KEY_FILE).exists());
+        assert!(KeyStore::open(dir.path()).get().is_none());
+    }
+
+    #[test]
+    fn a_tampered_key_file_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        KeyStore::open(dir.path()).set("sk-ant-test-123").unwrap();
+        let path = dir.path().join(KEY_FILE);
+        let mut sealed = std::fs::read(&path).unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        std::fs::write(&path, &sealed).unwrap();
+
+        let error = read(dir.path()).unwrap_err();
+        assert!(error.contains("does not match"), "{}", error);
+        assert!(KeyStore::open(dir.path()).get().is_none());
+
+        std::fs::write(&path, &sealed[..NONCE_LEN]).unwrap();
+        assert!(read(dir.path()).unwrap_err().contains("truncated"));
+    }
+
+    #[test]
+    fn another_secret_cannot_decrypt_the_key() {
+        let dir = tempfile::tempdir().unwrap();
+        KeyStore::open(dir.path()).set("sk-ant-test-123").unwrap();
+        std::fs::write(dir.path().join(SECRET_FILE), [7u8; 32]).unwrap();
+
+        let error = read(dir.path()).unwrap_err();
+        assert!(error.contains("does not match"), "{}", error);
+        assert!(KeyStore::open(dir.path()).get().is_none());
+
+        std::fs::write(dir.path().join(SECRET_FILE), [7u8; 16]).unwrap();
+        assert!(read(dir.path()).unwrap_err().contains("not a 32-byte key"));
+    }
+}
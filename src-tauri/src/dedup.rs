@@ -0,0 +1,332 @@
+//! Exact and near-duplicate detection for scanned screenshots.
+//!
+//! Every file gets a SHA-256 content hash and a 64-bit difference hash
+//! (dHash). Files sharing a SHA-256 are exact duplicates; files whose dHash
+//! is at least `threshold` similar to a group's keeper are near-duplicates.
+//! The keeper is recommended so the rest can be trashed and skipped from
+//! analysis.
+
+use image::imageops::FilterType;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Similarity (0.0-1.0) at or above which two screenshots are near-duplicates.
+pub const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.9;
+
+pub struct Fingerprint {
+    pub path: PathBuf,
+    pub sha256: String,
+    pub dhash: u64,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKind {
+    Exact,
+    Near,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DuplicateMember {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    /// Similarity to the keeper, 1.0 for identical pixels.
+    pub similarity: f32,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DuplicateGroup {
+    pub id: String,
+    pub kind: DuplicateKind,
+    pub keeper: DuplicateMember,
+    pub duplicates: Vec<DuplicateMember>,
+}
+
+/// Hash a file's bytes and pixels.
+pub fn fingerprint(path: &Path) -> Result<Fingerprint, String> {
+    let metadata = std::fs::metadata(path).map_err(|e| e.to_string())?;
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+
+    let sha256 = Sha256::digest(&data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    let dhash = dhash(&data)?;
+
+    Ok(Fingerprint {
+        path: path.to_path_buf(),
+        sha256,
+        dhash,
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+    })
+}
+
+/// Difference hash: shrink to 9x8 grayscale and record whether each pixel is
+/// brighter than its right-hand neighbour.
+pub fn dhash(image_data: &[u8]) -> Result<u64, String> {
+    let image = image::load_from_memory(image_data).map_err(|e| e.to_string())?;
+    let small = image.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+
+    let mut hash: u64 = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+    Ok(hash)
+}
+
+/// Fraction of matching dHash bits, 1.0 for identical hashes.
+pub fn similarity(a: u64, b: u64) -> f32 {
+    1.0 - (a ^ b).count_ones() as f32 / 64.0
+}
+
+/// Fingerprint `paths` and group them.
+pub fn find_duplicates(paths: &[PathBuf], threshold: f32) -> Vec<DuplicateGroup> {
+    group_duplicates(&fingerprint_all(paths), threshold)
+}
+
+/// Fingerprint every path. Files that cannot be decoded are left out rather
+/// than failing the whole scan.
+pub fn fingerprint_all(paths: &[PathBuf]) -> Vec<Fingerprint> {
+    paths
+        .iter()
+        .filter_map(|path| match fingerprint(path) {
+            Ok(fp) => Some(fp),
+            Err(e) => {
+                eprintln!("[RUST] ⚠️ Could not fingerprint {:?}: {}", path, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Group exact and near copies. Identical files are grouped first and
+/// represented by their oldest copy, the original capture. Then the most
+/// detailed image left becomes a keeper and every image similar enough to
+/// the keeper itself joins its group, so a chain of small edits never pulls
+/// in screenshots that look nothing like the one being kept.
+pub fn group_duplicates(fingerprints: &[Fingerprint], threshold: f32) -> Vec<DuplicateGroup> {
+    let mut copies: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, fp) in fingerprints.iter().enumerate() {
+        copies.entry(fp.sha256.as_str()).or_default().push(i);
+    }
+    let mut copy_sets = copies
+        .into_values()
+        .map(|mut set| {
+            set.sort_by(|&a, &b| oldest_first(&fingerprints[a], &fingerprints[b]));
+            set
+        })
+        .collect::<Vec<Vec<usize>>>();
+    copy_sets.sort_by(|a, b| most_detailed_first(&fingerprints[a[0]], &fingerprints[b[0]]));
+
+    let mut grouped = vec![false; copy_sets.len()];
+    let mut groups = Vec::new();
+    for i in 0..copy_sets.len() {
+        if grouped[i] {
+            continue;
+        }
+        let keeper = &fingerprints[copy_sets[i][0]];
+        let mut members = copy_sets[i].clone();
+        for j in (i + 1)..copy_sets.len() {
+            let candidate = &fingerprints[copy_sets[j][0]];
+            if !grouped[j] && similarity(keeper.dhash, candidate.dhash) >= threshold {
+                grouped[j] = true;
+                members.extend(&copy_sets[j]);
+            }
+        }
+        if members.len() > 1 {
+            groups.push(build_group(fingerprints, &members));
+        }
+    }
+    groups.sort_by(|a, b| a.keeper.name.cmp(&b.keeper.name));
+    groups
+}
+
+// Exact copies: keep the oldest, it is the original capture.
+fn oldest_first(a: &Fingerprint, b: &Fingerprint) -> Ordering {
+    a.modified
+        .cmp(&b.modified)
+        .then(b.size.cmp(&a.size))
+        .then_with(|| a.path.cmp(&b.path))
+}
+
+// Near copies: keep the largest file, it usually has the most detail.
+fn most_detailed_first(a: &Fingerprint, b: &Fingerprint) -> Ordering {
+    b.size
+        .cmp(&a.size)
+        .then(a.modified.cmp(&b.modified))
+        .then_with(|| a.path.cmp(&b.path))
+}
+
+// `members[0]` is the keeper.
+fn build_group(fingerprints: &[Fingerprint], members: &[usize]) -> DuplicateGroup {
+    let keeper = &fingerprints[members[0]];
+    let kind = if members
+        .iter()
+        .all(|&i| fingerprints[i].sha256 == keeper.sha256)
+    {
+        DuplicateKind::Exact
+    } else {
+        DuplicateKind::Near
+    };
+
+    let member = |fp: &Fingerprint| DuplicateMember {
+        path: fp.path.to_string_lossy().to_string(),
+        name: fp
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        size: fp.size,
+        similarity: if fp.sha256 == keeper.sha256 {
+            1.0
+        } else {
+            similarity(fp.dhash, keeper.dhash)
+        },
+    };
+
+    DuplicateGroup {
+        id: format!("dup-{}", &keeper.sha256[..12]),
+        kind,
+        keeper: member(keeper),
+        duplicates: members[1..]
+            .iter()
+            .map(|&i| member(&fingerprints[i]))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn fp(name: &str, sha256: &str, dhash: u64, size: u64, age_secs: u64) -> Fingerprint {
+        Fingerprint {
+            path: PathBuf::from(name),
+            sha256: format!("{:0<64}", sha256),
+            dhash,
+            size,
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 - age_secs)),
+        }
+    }
+
+    fn names(group: &DuplicateGroup) -> Vec<&str> {
+        std::iter::once(&group.keeper)
+            .chain(&group.duplicates)
+            .map(|member| member.name.as_str())
+            .collect()
+    }
+
+    fn gradient(path: &Path, shift: u32) {
+        image::GrayImage::from_fn(90, 80, |x, y| {
+            image::Luma([((x * 2 + y + shift) % 256) as u8])
+        })
+        .save(path)
+        .unwrap();
+    }
+
+    #[test]
+    fn hashes_follow_bytes_and_pixels() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("a.png");
+        gradient(&original, 0);
+        let copy = dir.path().join("b.png");
+        std::fs::copy(&original, &copy).unwrap();
+        // Same pixels, different bytes
+        let reencoded = dir.path().join("c.png");
+        image::open(&original)
+            .unwrap()
+            .to_rgb8()
+            .save(&reencoded)
+            .unwrap();
+        let other = dir.path().join("d.png");
+        image::GrayImage::from_fn(90, 80, |x, _| {
+            image::Luma([if x % 20 < 10 { 0 } else { 255 }])
+        })
+        .save(&other)
+        .unwrap();
+
+        let [a, b, c, d] = [&original, &copy, &reencoded, &other].map(|p| fingerprint(p).unwrap());
+        assert_eq!(a.sha256.len(), 64);
+        assert_eq!(a.sha256, b.sha256);
+        assert_ne!(a.sha256, c.sha256);
+        assert_eq!(a.dhash, c.dhash);
+        assert!(similarity(a.dhash, d.dhash) < DEFAULT_SIMILARITY_THRESHOLD);
+        assert!(fingerprint(&dir.path().join("missing.png")).is_err());
+        assert!(dhash(b"not an image").is_err());
+    }
+
+    #[test]
+    fn similarity_counts_matching_bits() {
+        assert_eq!(similarity(0, 0), 1.0);
+        assert_eq!(similarity(0, u64::MAX), 0.0);
+        assert_eq!(similarity(0, 0b111), 61.0 / 64.0);
+    }
+
+    #[test]
+    fn exact_copies_keep_the_oldest() {
+        let fingerprints = [
+            fp("new.png", "aa", 0, 100, 10),
+            fp("old.png", "aa", 0, 100, 500),
+            fp("other.png", "bb", u64::MAX, 100, 10),
+        ];
+        let groups = group_duplicates(&fingerprints, DEFAULT_SIMILARITY_THRESHOLD);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kind, DuplicateKind::Exact);
+        assert_eq!(names(&groups[0]), ["old.png", "new.png"]);
+        assert_eq!(groups[0].duplicates[0].similarity, 1.0);
+    }
+
+    #[test]
+    fn near_copies_are_compared_with_the_keeper_not_chained() {
+        // Six bits apart from each neighbour, twelve from the keeper
+        let fingerprints = [
+            fp("keeper.png", "aa", 0, 300, 0),
+            fp("step.png", "bb", 0b111111, 200, 0),
+            fp("far.png", "cc", 0b111111_111111, 100, 0),
+        ];
+        let groups = group_duplicates(&fingerprints, DEFAULT_SIMILARITY_THRESHOLD);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kind, DuplicateKind::Near);
+        assert_eq!(names(&groups[0]), ["keeper.png", "step.png"]);
+        assert_eq!(groups[0].duplicates[0].similarity, 58.0 / 64.0);
+
+        // A looser threshold takes in the far one, a stricter one nothing
+        let groups = group_duplicates(&fingerprints, 0.8);
+        assert_eq!(names(&groups[0]), ["keeper.png", "step.png", "far.png"]);
+        assert!(group_duplicates(&fingerprints, 0.95).is_empty());
+    }
+
+    #[test]
+    fn near_groups_keep_the_largest_and_bring_its_copies() {
+        let fingerprints = [
+            fp("small.png", "aa", 0, 100, 50),
+            fp("small copy.png", "aa", 0, 100, 10),
+            fp("large.png", "bb", 0b1, 400, 0),
+        ];
+        let groups = group_duplicates(&fingerprints, DEFAULT_SIMILARITY_THRESHOLD);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kind, DuplicateKind::Near);
+        assert_eq!(
+            names(&groups[0]),
+            ["large.png", "small.png", "small copy.png"]
+        );
+        assert_eq!(
+            groups[0].id,
+            format!("dup-{}", &fingerprints[2].sha256[..12])
+        );
+    }
+}
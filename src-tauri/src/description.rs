@@ -0,0 +1,164 @@
+//! Optional rich per-image descriptions: a paragraph summary, the visible
+//! app or site, key entities and the kind of UI on screen.
+//!
+//! Descriptions are kept outside the app so the knowledge survives it, either
+//! as a `<file>.snapsort.json` sidecar next to each moved file or in one
+//! central `descriptions.jsonl` in the app data dir.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Extra rule appended to classification prompts when descriptions are on.
+pub const PROMPT_RULE: &str = "- 'description': object with 'summary' (one short paragraph), \
+'app' (visible app or website), 'entities' (list of {\"kind\", \"value\"} for amounts, people, \
+repo names, products, error codes), 'ui_type' (e.g. dialog, dashboard, code_editor, chat, terminal)";
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct RichDescription {
+    pub summary: String,
+    #[serde(default)]
+    pub app: Option<String>,
+    #[serde(default)]
+    pub entities: Vec<DescriptionEntity>,
+    #[serde(default)]
+    pub ui_type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DescriptionEntity {
+    pub kind: String,
+    pub value: String,
+}
+
+impl RichDescription {
+    /// Flattened text for search and embedding.
+    pub fn text(&self) -> String {
+        let mut parts = vec![self.summary.clone()];
+        parts.extend(self.app.clone());
+        parts.extend(self.ui_type.clone());
+        parts.extend(self.entities.iter().map(|entity| entity.value.clone()));
+        parts
+            .into_iter()
+            .map(|part| part.trim().to_string())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<String>>()
+            .join(". ")
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DescriptionStorage {
+    Sidecar,
+    Central,
+}
+
+/// Everything SnapSort knows about an organized file, as written to disk.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DescriptionRecord {
+    pub path: String,
+    pub original_name: String,
+    pub category: String,
+    pub reasoning: String,
+    pub model: String,
+    pub run_id: Option<String>,
+    pub organized_at: String,
+    pub description: RichDescription,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum CentralLine {
+    Upsert(Box<DescriptionRecord>),
+    Remove { path: String },
+}
+
+pub struct DescriptionStore {
+    central_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl DescriptionStore {
+    pub fn new(central_path: PathBuf) -> Self {
+        DescriptionStore {
+            central_path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// `stripe_invoice.png` -> `stripe_invoice.png.snapsort.json`
+    pub fn sidecar_path(file: &Path) -> PathBuf {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        file.with_file_name(format!("{}.snapsort.json", name))
+    }
+
+    pub fn write(
+        &self,
+        storage: DescriptionStorage,
+        record: &DescriptionRecord,
+    ) -> Result<(), String> {
+        match storage {
+            DescriptionStorage::Sidecar => {
+                let json = serde_json::to_string_pretty(record).map_err(|e| e.to_string())?;
+                std::fs::write(Self::sidecar_path(Path::new(&record.path)), json)
+                    .map_err(|e| e.to_string())
+            }
+            DescriptionStorage::Central => {
+                self.append(CentralLine::Upsert(Box::new(record.clone())))
+            }
+        }
+    }
+
+    /// Forget the description of a file that moved away, e.g. on undo.
+    pub fn remove(&self, storage: DescriptionStorage, path: &str) -> Result<(), String> {
+        match storage {
+            DescriptionStorage::Sidecar => {
+                let sidecar = Self::sidecar_path(Path::new(path));
+                if sidecar.exists() {
+                    std::fs::remove_file(sidecar).map_err(|e| e.to_string())?;
+                }
+                Ok(())
+            }
+            DescriptionStorage::Central => self.append(CentralLine::Remove {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    /// Sidecar first, then the latest central entry.
+    pub fn get(&self, path: &str) -> Option<DescriptionRecord> {
+        if let Ok(json) = std::fs::read_to_string(Self::sidecar_path(Path::new(path))) {
+            if let Ok(record) = serde_json::from_str::<DescriptionRecord>(&json) {
+                return Some(record);
+            }
+        }
+
+        let _guard = self.lock.lock().unwrap();
+        let file = std::fs::File::open(&self.central_path).ok()?;
+        let mut found = None;
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            match serde_json::from_str::<CentralLine>(&line) {
+                Ok(CentralLine::Upsert(record)) if record.path == path => found = Some(*record),
+                Ok(CentralLine::Remove { path: removed }) if removed == path => found = None,
+                _ => {}
+            }
+        }
+        found
+    }
+
+    fn append(&self, line: CentralLine) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        if let Some(parent) = self.central_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string(&line).map_err(|e| e.to_string())?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.central_path)
+            .map_err(|e| e.to_string())?;
+        writeln!(file, "{}", json).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,415 @@
+//! Semantic search over organized screenshots with locally computed
+//! embeddings.
+//!
+//! Category, reasoning, the text the model read off the screenshot and any
+//! rich description are embedded on the CPU with a small sentence-transformer
+//! (all-MiniLM-L6-v2 via fastembed), downloaded once into the model cache on
+//! first use. Vectors live in memory and are persisted as an append-only
+//! `vectors.jsonl` next to the search index. Queries are a brute-force cosine
+//! scan, which is plenty for a personal library.
+//!
+//! Semantic search is off until the user builds the index; until then
+//! nothing is embedded and no model is downloaded. Builds without the
+//! `semantic` feature keep the stored vectors but cannot embed.
+
+#[cfg(feature = "semantic")]
+use fastembed::{EmbeddingModel, TextEmbedding, TextInitOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[cfg(feature = "semantic")]
+const EMBED_BATCH_SIZE: usize = 32;
+const NOT_BUILT: &str = "SnapSort was built without semantic search";
+
+/// What gets embedded for one screenshot.
+pub struct EmbeddingInput {
+    pub path: String,
+    pub original_name: String,
+    pub category: String,
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct EmbeddingRecord {
+    path: String,
+    original_name: String,
+    category: String,
+    text: String,
+    vector: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum LogLine {
+    Upsert(EmbeddingRecord),
+    Remove { path: String },
+}
+
+/// Change to apply after a file moved.
+pub enum EmbeddingUpdate {
+    Upsert(EmbeddingInput),
+    /// Path the file moved away from.
+    Remove(String),
+}
+
+#[derive(Serialize, Clone)]
+pub struct SemanticHit {
+    pub path: String,
+    pub original_name: String,
+    pub category: String,
+    /// Cosine similarity, higher is closer.
+    pub score: f32,
+    pub text: String,
+}
+
+pub struct EmbeddingIndex {
+    log_path: PathBuf,
+    #[cfg(feature = "semantic")]
+    model_dir: PathBuf,
+    #[cfg(feature = "semantic")]
+    model: Mutex<Option<TextEmbedding>>,
+    records: Mutex<HashMap<String, EmbeddingRecord>>,
+}
+
+/// Text embedded for a screenshot; empty parts are left out.
+pub fn document_text(
+    category: &str,
+    reasoning: &str,
+    visible_text: &str,
+    description: &str,
+) -> String {
+    [
+        category.replace('/', " "),
+        reasoning.to_string(),
+        visible_text.to_string(),
+        description.to_string(),
+    ]
+    .into_iter()
+    .map(|part| part.trim().to_string())
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<String>>()
+    .join(". ")
+}
+
+impl EmbeddingIndex {
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let log_path = dir.join("vectors.jsonl");
+
+        let mut records = HashMap::new();
+        let mut lines = 0;
+        if let Ok(file) = std::fs::File::open(&log_path) {
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|e| e.to_string())?;
+                lines += 1;
+                match serde_json::from_str::<LogLine>(&line) {
+                    Ok(LogLine::Upsert(record)) => {
+                        records.insert(record.path.clone(), record);
+                    }
+                    Ok(LogLine::Remove { path }) => {
+                        records.remove(&path);
+                    }
+                    Err(e) => eprintln!("[RUST] ⚠️ Bad line in embedding index: {}", e),
+                }
+            }
+        }
+
+        let index = EmbeddingIndex {
+            log_path,
+            #[cfg(feature = "semantic")]
+            model_dir: dir.join("models"),
+            #[cfg(feature = "semantic")]
+            model: Mutex::new(None),
+            records: Mutex::new(records),
+        };
+
+        // Rewrite the log once superseded lines outnumber live ones.
+        if lines > 2 * index.count() + 100 {
+            index.compact()?;
+        }
+        Ok(index)
+    }
+
+    /// Whether the user turned semantic search on by building the index.
+    pub fn is_enabled(&self) -> bool {
+        self.log_path.exists()
+    }
+
+    /// Turn semantic search on; later moves are embedded as they happen.
+    pub fn enable(&self) -> Result<(), String> {
+        if !cfg!(feature = "semantic") {
+            return Err(NOT_BUILT.to_string());
+        }
+        self.append(std::iter::empty())
+    }
+
+    pub fn count(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.records.lock().unwrap().contains_key(path)
+    }
+
+    /// Embed and store documents, replacing earlier vectors for the same path.
+    pub fn upsert(&self, inputs: Vec<EmbeddingInput>) -> Result<usize, String> {
+        if inputs.is_empty() {
+            return Ok(0);
+        }
+        let texts = inputs
+            .iter()
+            .map(|input| input.text.clone())
+            .collect::<Vec<String>>();
+        let vectors = self.embed(texts)?;
+
+        let records = inputs
+            .into_iter()
+            .zip(vectors)
+            .map(|(input, vector)| EmbeddingRecord {
+                path: input.path,
+                original_name: input.original_name,
+                category: input.category,
+                text: input.text,
+                vector,
+            })
+            .collect::<Vec<EmbeddingRecord>>();
+
+        let count = records.len();
+        let mut stored = self.records.lock().unwrap();
+        self.append(records.iter().cloned().map(LogLine::Upsert))?;
+        for record in records {
+            stored.insert(record.path.clone(), record);
+        }
+        Ok(count)
+    }
+
+    pub fn remove(&self, path: &str) -> Result<(), String> {
+        let mut stored = self.records.lock().unwrap();
+        if stored.remove(path).is_some() {
+            self.append(std::iter::once(LogLine::Remove {
+                path: path.to_string(),
+            }))?;
+        }
+        Ok(())
+    }
+
+    /// Keep the index current after a move; nothing happens while semantic
+    /// search is off.
+    pub fn apply(&self, update: EmbeddingUpdate) -> Result<(), String> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        match update {
+            EmbeddingUpdate::Upsert(input) => self.upsert(vec![input]).map(|_| ()),
+            EmbeddingUpdate::Remove(path) => self.remove(&path),
+        }
+    }
+
+    /// Drop vectors whose file no longer exists.
+    pub fn prune_missing(&self) -> Result<usize, String> {
+        let mut stored = self.records.lock().unwrap();
+        let missing = stored
+            .keys()
+            .filter(|path| !Path::new(path.as_str()).exists())
+            .cloned()
+            .collect::<Vec<String>>();
+        if missing.is_empty() {
+            return Ok(0);
+        }
+        for path in &missing {
+            stored.remove(path);
+        }
+        self.append(
+            missing
+                .iter()
+                .map(|path| LogLine::Remove { path: path.clone() }),
+        )?;
+        Ok(missing.len())
+    }
+
+    /// The `k` stored screenshots closest in meaning to `query`.
+    pub fn search(&self, query: &str, k: usize) -> Result<Vec<SemanticHit>, String> {
+        if !self.is_enabled() {
+            return Err("Semantic search is off; build the index first".to_string());
+        }
+        if query.trim().is_empty() || self.count() == 0 {
+            return Ok(Vec::new());
+        }
+        let query_vector = self
+            .embed(vec![query.to_string()])?
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Failed to embed query".to_string())?;
+        Ok(self.nearest(&query_vector, k))
+    }
+
+    // Stored screenshots by cosine similarity to `query_vector`, best first
+    fn nearest(&self, query_vector: &[f32], k: usize) -> Vec<SemanticHit> {
+        let stored = self.records.lock().unwrap();
+        let mut hits = stored
+            .values()
+            .map(|record| SemanticHit {
+                path: record.path.clone(),
+                original_name: record.original_name.clone(),
+                category: record.category.clone(),
+                score: cosine(query_vector, &record.vector),
+                text: record.text.clone(),
+            })
+            .collect::<Vec<SemanticHit>>();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(k);
+        hits
+    }
+
+    #[cfg(not(feature = "semantic"))]
+    fn embed(&self, _texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        Err(NOT_BUILT.to_string())
+    }
+
+    #[cfg(feature = "semantic")]
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        let mut model = self.model.lock().unwrap();
+        if model.is_none() {
+            eprintln!("[RUST] Loading embedding model into {:?}", self.model_dir);
+            let options = TextInitOptions::new(EmbeddingModel::AllMiniLML6V2)
+                .with_cache_dir(self.model_dir.clone())
+                .with_show_download_progress(false);
+            *model = Some(TextEmbedding::try_new(options).map_err(|e| e.to_string())?);
+        }
+        let Some(model) = model.as_mut() else {
+            return Err("Embedding model unavailable".to_string());
+        };
+        model
+            .embed(texts, Some(EMBED_BATCH_SIZE))
+            .map_err(|e| e.to_string())
+    }
+
+    fn append(&self, lines: impl Iterator<Item = LogLine>) -> Result<(), String> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)
+            .map_err(|e| e.to_string())?;
+        for line in lines {
+            let json = serde_json::to_string(&line).map_err(|e| e.to_string())?;
+            writeln!(file, "{}", json).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn compact(&self) -> Result<(), String> {
+        let stored = self.records.lock().unwrap();
+        let tmp_path = self.log_path.with_extension("jsonl.tmp");
+        let mut file = std::fs::File::create(&tmp_path).map_err(|e| e.to_string())?;
+        for record in stored.values() {
+            let json = serde_json::to_string(&LogLine::Upsert(record.clone()))
+                .map_err(|e| e.to_string())?;
+            writeln!(file, "{}", json).map_err(|e| e.to_string())?;
+        }
+        std::fs::rename(&tmp_path, &self.log_path).map_err(|e| e.to_string())
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(path: &str, vector: Vec<f32>) -> EmbeddingRecord {
+        EmbeddingRecord {
+            path: path.to_string(),
+            original_name: format!("{}.png", path),
+            category: "Code".to_string(),
+            text: String::new(),
+            vector,
+        }
+    }
+
+    // Index in `dir` holding `records`, written the way upserts write them
+    fn index_with(dir: &Path, records: Vec<EmbeddingRecord>) -> EmbeddingIndex {
+        let lines = records
+            .into_iter()
+            .map(|record| serde_json::to_string(&LogLine::Upsert(record)).unwrap())
+            .collect::<Vec<String>>();
+        std::fs::write(dir.join("vectors.jsonl"), lines.join("\n")).unwrap();
+        EmbeddingIndex::open(dir).unwrap()
+    }
+
+    #[test]
+    fn document_text_joins_non_empty_parts() {
+        assert_eq!(
+            document_text("Code/Terminal", " cargo build failed ", "", "A red error"),
+            "Code Terminal. cargo build failed. A red error"
+        );
+        assert_eq!(document_text("", " ", "", ""), "");
+    }
+
+    #[test]
+    fn prune_missing_drops_vectors_of_moved_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = dir.path().join("kept.png");
+        std::fs::write(&kept, b"png").unwrap();
+        let kept = kept.to_string_lossy().to_string();
+        let gone = dir.path().join("gone.png").to_string_lossy().to_string();
+
+        let index = index_with(
+            dir.path(),
+            vec![record(&kept, vec![1.0]), record(&gone, vec![1.0])],
+        );
+        assert_eq!(index.prune_missing().unwrap(), 1);
+        assert_eq!(index.prune_missing().unwrap(), 0);
+        assert!(index.contains(&kept) && !index.contains(&gone));
+
+        let reopened = EmbeddingIndex::open(dir.path()).unwrap();
+        assert_eq!(reopened.count(), 1);
+        assert!(reopened.contains(&kept));
+    }
+
+    #[test]
+    fn nearest_ranks_by_cosine_similarity() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = index_with(
+            dir.path(),
+            vec![
+                record("orthogonal", vec![0.0, 1.0]),
+                record("same", vec![2.0, 0.0]),
+                record("close", vec![0.7, 0.7]),
+                record("empty", vec![0.0, 0.0]),
+            ],
+        );
+
+        let ranked = index
+            .nearest(&[1.0, 0.0], 10)
+            .into_iter()
+            .map(|hit| hit.path)
+            .collect::<Vec<String>>();
+        assert_eq!(ranked[..2], ["same", "close"]);
+        assert_eq!(index.nearest(&[1.0, 0.0], 1)[0].score, 1.0);
+        assert_eq!(index.nearest(&[1.0, 0.0], 2).len(), 2);
+    }
+
+    #[test]
+    fn nothing_is_embedded_until_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = EmbeddingIndex::open(dir.path()).unwrap();
+        assert!(!index.is_enabled());
+        index
+            .apply(EmbeddingUpdate::Remove("a.png".to_string()))
+            .unwrap();
+        assert!(!index.is_enabled());
+        assert!(index.search("terminal", 5).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Progress events of a scan and the sinks that deliver them.
+//!
+//! The pipeline reports through an `EventSink` instead of calling Tauri
+//! directly, so the same run can feed the desktop app, the CLI or a test.
+
+use crate::batch::RequestCounts;
+use crate::dedup::DuplicateGroup;
+use crate::pipeline::{FileProposal, SkippedFile};
+use crate::usage::{RequestUsage, RunCost};
+use serde::Serialize;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PipelineEvent {
+    /// A file was handed to the model.
+    Processing {
+        name: String,
+    },
+    Proposed {
+        proposal: Box<FileProposal>,
+    },
+    Skipped {
+        #[serde(flatten)]
+        file: SkippedFile,
+    },
+    Failed {
+        name: String,
+        error: String,
+    },
+    /// Not sent to the model because the run's budget was used up.
+    Deferred {
+        name: String,
+        reason: String,
+    },
+    /// Tokens one request used, with the run's totals so far.
+    Usage {
+        #[serde(flatten)]
+        request: RequestUsage,
+        total: RunCost,
+    },
+    /// Progress of a Message Batch, after every status check.
+    BatchStatus {
+        batch_id: String,
+        status: String,
+        request_counts: RequestCounts,
+    },
+    /// Sent once the scan is prepared, before classification starts.
+    Summary {
+        actionable: usize,
+        duplicate_groups: Vec<DuplicateGroup>,
+    },
+}
+
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: PipelineEvent);
+}
+
+/// Delivers every event to each of several sinks.
+pub struct FanoutSink(pub Vec<Arc<dyn EventSink>>);
+
+impl EventSink for FanoutSink {
+    fn emit(&self, event: PipelineEvent) {
+        for sink in &self.0 {
+            sink.emit(event.clone());
+        }
+    }
+}
+
+/// Forwards events to an async receiver; dropped receivers are ignored.
+pub struct ChannelSink(pub UnboundedSender<PipelineEvent>);
+
+impl EventSink for ChannelSink {
+    fn emit(&self, event: PipelineEvent) {
+        let _ = self.0.send(event);
+    }
+}
+
+/// Writes one JSON object per line, e.g. to stderr for scripts.
+pub struct JsonLinesSink<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesSink {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W: Write + Send> EventSink for JsonLinesSink<W> {
+    fn emit(&self, event: PipelineEvent) {
+        let Ok(line) = serde_json::to_string(&event) else {
+            return;
+        };
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(e) = writeln!(writer, "{}", line).and_then(|_| writer.flush()) {
+            eprintln!("[RUST] ⚠️ Failed to write event: {}", e);
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Append-only audit log of every file move, so "where did that screenshot
+//! go?" can be answered weeks later.
+//!
+//! One JSON object per line in `history.jsonl`. Entries are never rewritten;
+//! queries scan the whole file, which stays fast well past 100k moves.
+
+use crate::capture_date;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryAction {
+    Apply,
+    Undo,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub run_id: Option<String>,
+    pub timestamp: String,
+    pub action: HistoryAction,
+    pub original_path: String,
+    /// None when the move failed.
+    pub final_path: Option<String>,
+    pub category: Option<String>,
+    pub model: Option<String>,
+    pub reasoning: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Every filter is optional; set filters must all match.
+#[derive(Deserialize, Default)]
+pub struct HistoryQuery {
+    /// Case-insensitive substring of the original or final filename.
+    pub name: Option<String>,
+    /// RFC 3339 timestamp or `YYYY-MM-DD` (start of day).
+    pub from: Option<String>,
+    /// RFC 3339 timestamp or `YYYY-MM-DD` (end of day).
+    pub to: Option<String>,
+    /// Category or category prefix, e.g. `Finance` matches `Finance/Invoices`
+    /// and `2025/03/Finance`, whatever the folder layout.
+    pub category: Option<String>,
+    pub run_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+pub struct HistoryLog {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl HistoryLog {
+    pub fn new(path: PathBuf) -> Self {
+        HistoryLog {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn record(&self, entry: &HistoryEntry) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| e.to_string())?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    }
+
+    /// Matching entries, newest first.
+    pub fn query(&self, query: &HistoryQuery) -> Result<Vec<HistoryEntry>, String> {
+        let from = match query.from.as_deref() {
+            Some(from) => Some(parse_bound(from, NaiveTime::MIN)?),
+            None => None,
+        };
+        let to = match query.to.as_deref() {
+            Some(to) => Some(parse_bound(to, end_of_day())?),
+            None => None,
+        };
+        let name = query.name.as_ref().map(|name| name.to_lowercase());
+        let category = query
+            .category
+            .as_deref()
+            .map(|c| capture_date::category_of(c).to_lowercase());
+
+        let _guard = self.lock.lock().unwrap();
+        let Ok(file) = std::fs::File::open(&self.path) else {
+            return Ok(Vec::new());
+        };
+
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            let Ok(entry) = serde_json::from_str::<HistoryEntry>(&line) else {
+                continue;
+            };
+
+            if let Some(run_id) = &query.run_id {
+                if entry.run_id.as_ref() != Some(run_id) {
+                    continue;
+                }
+            }
+            if let Some(name) = &name {
+                let matches = [Some(&entry.original_path), entry.final_path.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .any(|path| file_name(path).to_lowercase().contains(name));
+                if !matches {
+                    continue;
+                }
+            }
+            if let Some(category) = &category {
+                // Year and month folders of the layout are not part of it
+                let entry_category =
+                    capture_date::category_of(entry.category.as_deref().unwrap_or(""))
+                        .to_lowercase();
+                if entry_category != *category
+                    && !entry_category.starts_with(&format!("{}/", category))
+                {
+                    continue;
+                }
+            }
+            if from.is_some() || to.is_some() {
+                let Ok(timestamp) = DateTime::parse_from_rfc3339(&entry.timestamp) else {
+                    continue;
+                };
+                if from.is_some_and(|from| timestamp < from) || to.is_some_and(|to| timestamp > to)
+                {
+                    continue;
+                }
+            }
+
+            entries.push(entry);
+        }
+
+        entries.reverse();
+        if let Some(limit) = query.limit {
+            entries.truncate(limit);
+        }
+        Ok(entries)
+    }
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn end_of_day() -> NaiveTime {
+    NaiveTime::from_hms_milli_opt(23, 59, 59, 999).unwrap_or(NaiveTime::MIN)
+}
+
+// Accept a full RFC 3339 timestamp or a bare local date.
+fn parse_bound(value: &str, time_of_day: NaiveTime) -> Result<DateTime<Local>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Local));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", value))?;
+    Local
+        .from_local_datetime(&date.and_time(time_of_day))
+        .earliest()
+        .ok_or_else(|| format!("Invalid local date '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture_date::FolderLayout;
+
+    fn entry(name: &str, category: &str, timestamp: &str) -> HistoryEntry {
+        HistoryEntry {
+            run_id: Some("run-1".to_string()),
+            timestamp: timestamp.to_string(),
+            action: HistoryAction::Apply,
+            original_path: format!("/shots/{}", name),
+            final_path: Some(format!("/shots/{}/{}", category, name)),
+            category: Some(category.to_string()),
+            model: None,
+            reasoning: None,
+            error: None,
+        }
+    }
+
+    fn names(entries: &[HistoryEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .map(|entry| file_name(&entry.original_path))
+            .collect()
+    }
+
+    fn log(dir: &Path, entries: &[HistoryEntry]) -> HistoryLog {
+        let log = HistoryLog::new(dir.join("history.jsonl"));
+        for entry in entries {
+            log.record(entry).unwrap();
+        }
+        log
+    }
+
+    #[test]
+    fn categories_match_under_every_folder_layout() {
+        let captured_at = NaiveDate::from_ymd_opt(2025, 3, 14)
+            .unwrap()
+            .and_hms_opt(10, 22, 33);
+        for layout in [
+            FolderLayout::Category,
+            FolderLayout::CategoryDate,
+            FolderLayout::DateCategory,
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let timestamp = "2025-03-14T10:30:00+00:00";
+            let log = log(
+                dir.path(),
+                &[
+                    entry(
+                        "a.png",
+                        &layout.apply("Finance/Invoices", captured_at),
+                        timestamp,
+                    ),
+                    entry("b.png", &layout.apply("Code", captured_at), timestamp),
+                    entry("c.png", &layout.apply("Financial", captured_at), timestamp),
+                ],
+            );
+            let matching = |category: &str| {
+                names(
+                    &log.query(&HistoryQuery {
+                        category: Some(category.to_string()),
+                        ..HistoryQuery::default()
+                    })
+                    .unwrap(),
+                )
+            };
+
+            assert_eq!(matching("Finance"), ["a.png"], "{:?}", layout);
+            assert_eq!(matching("finance/invoices"), ["a.png"], "{:?}", layout);
+            assert_eq!(matching("Code"), ["b.png"], "{:?}", layout);
+            assert!(matching("Fin").is_empty(), "{:?}", layout);
+        }
+    }
+
+    #[test]
+    fn date_bounds_include_the_whole_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = log(
+            dir.path(),
+            &[
+                entry("march.png", "Code", "2025-03-01T09:00:00+00:00"),
+                entry("april.png", "Code", "2025-04-10T12:00:00+00:00"),
+                entry("may.png", "Code", "2025-05-20T12:00:00+00:00"),
+            ],
+        );
+        let between = |from: Option<&str>, to: Option<&str>| {
+            log.query(&HistoryQuery {
+                from: from.map(str::to_string),
+                to: to.map(str::to_string),
+                ..HistoryQuery::default()
+            })
+            .map(|entries| names(&entries))
+        };
+
+        // Newest first
+        assert_eq!(
+            between(None, None).unwrap(),
+            ["may.png", "april.png", "march.png"]
+        );
+        assert_eq!(
+            between(Some("2025-04-01"), Some("2025-04-30")).unwrap(),
+            ["april.png"]
+        );
+        assert_eq!(
+            between(Some("2025-04-10T12:00:00+00:00"), None).unwrap(),
+            ["may.png", "april.png"]
+        );
+        assert_eq!(
+            between(None, Some("2025-04-10T11:59:59+00:00")).unwrap(),
+            ["march.png"]
+        );
+        assert!(between(Some("10/04/2025"), None).is_err());
+    }
+
+    #[test]
+    fn name_run_and_limit_filters_combine() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut other_run = entry("Invoice 2.png", "Finance", "2025-03-02T00:00:00+00:00");
+        other_run.run_id = Some("run-2".to_string());
+        let log = log(
+            dir.path(),
+            &[
+                entry("Invoice 1.png", "Finance", "2025-03-01T00:00:00+00:00"),
+                other_run,
+                entry("terminal.png", "Code", "2025-03-03T00:00:00+00:00"),
+            ],
+        );
+        let query = |query: HistoryQuery| names(&log.query(&query).unwrap());
+
+        assert_eq!(
+            query(HistoryQuery {
+                name: Some("invoice".to_string()),
+                ..HistoryQuery::default()
+            }),
+            ["Invoice 2.png", "Invoice 1.png"]
+        );
+        assert_eq!(
+            query(HistoryQuery {
+                name: Some("invoice".to_string()),
+                run_id: Some("run-1".to_string()),
+                ..HistoryQuery::default()
+            }),
+            ["Invoice 1.png"]
+        );
+        assert_eq!(
+            query(HistoryQuery {
+                limit: Some(1),
+                ..HistoryQuery::default()
+            }),
+            ["terminal.png"]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! The one HTTP client for Anthropic API calls, configured from
+//! `network.json` in the data directory: base URL, timeouts, proxy and extra
+//! root certificates. Connections are pooled across all requests.
+//!
+//! `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` apply unless a proxy is set
+//! here, and `ANTHROPIC_BASE_URL` overrides the configured base URL, e.g. to
+//! point a run at a local stand-in server.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const NETWORK_FILE: &str = "network.json";
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NetworkConfig {
+    /// API server; None uses the Anthropic API.
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout_secs: u64,
+    /// Longest wait for the next bytes of a response.
+    #[serde(default = "default_read_timeout")]
+    pub read_timeout_secs: u64,
+    /// Proxy for every request, e.g. `http://proxy.corp:3128`.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// PEM files with extra root certificates, e.g. a corporate CA.
+    #[serde(default)]
+    pub ca_certificates: Vec<PathBuf>,
+}
+
+fn default_connect_timeout() -> u64 {
+    15
+}
+
+fn default_read_timeout() -> u64 {
+    180
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            base_url: None,
+            connect_timeout_secs: default_connect_timeout(),
+            read_timeout_secs: default_read_timeout(),
+            proxy: None,
+            ca_certificates: Vec::new(),
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Saved config, or the defaults when there is none. A broken file is
+    /// reported and ignored.
+    pub fn load(path: &Path) -> NetworkConfig {
+        match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| eprintln!("[RUST] ⚠️ Ignoring {}: {}", path.display(), e))
+                .unwrap_or_default(),
+            Err(_) => NetworkConfig::default(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| e.to_string())
+    }
+}
+
+/// Pooled client with the API base URL. Cheap to clone; clones share
+/// connections.
+#[derive(Clone)]
+pub struct ApiClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl ApiClient {
+    pub fn new(config: &NetworkConfig) -> Result<ApiClient, String> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+            .read_timeout(Duration::from_secs(config.read_timeout_secs));
+        if let Some(proxy) = config.proxy.as_deref().map(str::trim) {
+            if !proxy.is_empty() {
+                let proxy = reqwest::Proxy::all(proxy)
+                    .map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?;
+                builder = builder.proxy(proxy);
+            }
+        }
+        for path in &config.ca_certificates {
+            let pem = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        let base_url = std::env::var("ANTHROPIC_BASE_URL")
+            .ok()
+            .or_else(|| config.base_url.clone())
+            .filter(|base| !base.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+        Ok(ApiClient {
+            client: builder.build().map_err(|e| e.to_string())?,
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Client for the config at `path`. A config that cannot be applied is
+    /// reported and the defaults are used instead.
+    pub fn load(path: &Path) -> ApiClient {
+        ApiClient::new(&NetworkConfig::load(path))
+            .or_else(|e| {
+                eprintln!("[RUST] ⚠️ Network settings not applied: {}", e);
+                ApiClient::new(&NetworkConfig::default())
+            })
+            .unwrap_or_else(|e| panic!("Failed to build HTTP client: {}", e))
+    }
+
+    /// The same client sending to `base_url`, whatever the config and
+    /// `ANTHROPIC_BASE_URL` say; for stand-in servers in tests.
+    pub fn with_base_url(self, base_url: &str) -> ApiClient {
+        ApiClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            ..self
+        }
+    }
+
+    /// Endpoint for `path`, e.g. `/v1/messages`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// POST to an API endpoint with the key and version headers.
+    pub fn post(&self, path: &str, api_key: &str) -> reqwest::RequestBuilder {
+        authorized(self.client.post(self.url(path)), api_key)
+    }
+
+    /// GET an API endpoint with the key and version headers.
+    pub fn get(&self, path: &str, api_key: &str) -> reqwest::RequestBuilder {
+        authorized(self.client.get(self.url(path)), api_key)
+    }
+
+    /// GET a full URL handed out by the API, e.g. batch results. The key is
+    /// only sent to URLs under the base URL.
+    pub fn get_url(&self, url: &str, api_key: &str) -> Result<reqwest::RequestBuilder, String> {
+        if !url.starts_with(&format!("{}/", self.base_url)) {
+            return Err(format!(
+                "Refusing to send the API key to {}: not under {}",
+                url, self.base_url
+            ));
+        }
+        Ok(authorized(self.client.get(url), api_key))
+    }
+}
+
+fn authorized(request: reqwest::RequestBuilder, api_key: &str) -> reqwest::RequestBuilder {
+    request
+        .header("x-api-key", api_key)
+        .header("anthropic-version", API_VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_key_only_goes_to_the_base_url() {
+        let client = ApiClient::new(&NetworkConfig::default())
+            .unwrap()
+            .with_base_url("https://api.example.com/");
+
+        assert!(client
+            .get_url(
+                "https://api.example.com/v1/messages/batches/b1/results",
+                "key"
+            )
+            .is_ok());
+        for url in [
+            "https://api.example.com.evil.io/v1/results",
+            "https://api.example.com@evil.io/v1/results",
+            "http://api.example.com/v1/results",
+            "https://evil.io/?https://api.example.com/v1",
+            "https://api.example.com",
+        ] {
+            assert!(client.get_url(url, "key").is_err(), "{}", url);
+        }
+    }
+}
@@ -0,0 +1,696 @@
+//! SnapSort tags embedded in the image file itself.
+//!
+//! PNG files get one iTXt chunk per tag (`SnapSort:Category`, ...). JPEG and
+//! WebP files get an `rdf:Description` in the `snapsort` XMP namespace, merged
+//! into any XMP packet already present. Other tools can read the tags, and a
+//! rescan can recognize files SnapSort already organized without a database.
+
+use crate::capture_date;
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::LazyLock;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PNG_KEYWORD_PREFIX: &str = "SnapSort:";
+const XMP_NAMESPACE: &str = "https://snapsort.app/ns/1.0/";
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const WEBP_XMP_FLAG: u8 = 0x04;
+const WEBP_ALPHA_FLAG: u8 = 0x10;
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ImageTags {
+    pub category: String,
+    pub subcategory: Option<String>,
+    pub description: Option<String>,
+    pub original_name: String,
+}
+
+static XMP_BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r#"<rdf:Description rdf:about="" xmlns:snapsort="{}"[^>]*/>"#,
+        regex::escape(XMP_NAMESPACE)
+    ))
+    .unwrap()
+});
+static XMP_ATTRIBUTE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"snapsort:(\w+)="([^"]*)""#).unwrap());
+
+impl ImageTags {
+    /// Split a proposed folder like `Finance/2025/03/Invoices` into category
+    /// and subcategory, ignoring date segments added by the folder layout.
+    pub fn from_folder(
+        folder: &str,
+        description: Option<String>,
+        original_name: String,
+    ) -> ImageTags {
+        let folder = capture_date::category_of(folder);
+        let (category, subcategory) = folder.split_once('/').unwrap_or((&folder, ""));
+        ImageTags {
+            category: category.to_string(),
+            subcategory: Some(subcategory.to_string()).filter(|s| !s.is_empty()),
+            description,
+            original_name,
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, &str)> {
+        let mut fields = vec![
+            ("Category", self.category.as_str()),
+            ("OriginalName", self.original_name.as_str()),
+        ];
+        if let Some(subcategory) = &self.subcategory {
+            fields.push(("Subcategory", subcategory));
+        }
+        if let Some(description) = &self.description {
+            fields.push(("Description", description));
+        }
+        fields
+    }
+
+    fn set_field(&mut self, name: &str, value: String) {
+        match name {
+            "Category" => self.category = value,
+            "Subcategory" => self.subcategory = Some(value),
+            "Description" => self.description = Some(value),
+            "OriginalName" => self.original_name = value,
+            _ => {}
+        }
+    }
+}
+
+enum Format {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+fn detect(data: &[u8]) -> Option<Format> {
+    if data.starts_with(PNG_SIGNATURE) {
+        Some(Format::Png)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        Some(Format::Jpeg)
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some(Format::Webp)
+    } else {
+        None
+    }
+}
+
+/// Tags SnapSort wrote earlier, if any.
+pub fn read_tags(path: &Path) -> Option<ImageTags> {
+    let data = std::fs::read(path).ok()?;
+    match detect(&data)? {
+        Format::Png => read_png(&data),
+        Format::Jpeg => jpeg_xmp(&data).and_then(|xmp| parse_xmp(&xmp)),
+        Format::Webp => webp_xmp(&data).and_then(|xmp| parse_xmp(&xmp)),
+    }
+}
+
+/// Embed tags, replacing any SnapSort tags already present.
+pub fn write_tags(path: &Path, tags: &ImageTags) -> Result<(), String> {
+    rewrite(path, Some(tags))
+}
+
+/// Strip SnapSort tags, leaving other metadata alone.
+pub fn remove_tags(path: &Path) -> Result<(), String> {
+    if read_tags(path).is_none() {
+        return Ok(());
+    }
+    rewrite(path, None)
+}
+
+fn rewrite(path: &Path, tags: Option<&ImageTags>) -> Result<(), String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let updated = match detect(&data) {
+        Some(Format::Png) => rewrite_png(&data, tags)?,
+        Some(Format::Jpeg) => rewrite_jpeg(&data, tags)?,
+        Some(Format::Webp) => rewrite_webp(&data, tags)?,
+        None => return Err("Unsupported image format for tags".to_string()),
+    };
+
+    // Write next to the original and swap, so a crash never leaves half a file.
+    let tmp_path = path.with_file_name(format!(
+        ".{}.snapsort-tmp",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    std::fs::write(&tmp_path, updated).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp_path, path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp_path);
+        e.to_string()
+    })
+}
+
+// --- PNG ---
+
+struct PngChunk<'a> {
+    kind: &'a [u8],
+    data: &'a [u8],
+    raw: &'a [u8],
+}
+
+// Chunks up to and including IEND, and where IEND ends. A file cut short
+// before IEND is an error, so a rewrite never drops the missing tail.
+fn png_chunks(data: &[u8]) -> Result<(Vec<PngChunk<'_>>, usize), String> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return Err("Not a PNG".to_string());
+    }
+    let mut chunks = Vec::new();
+    let mut offset = PNG_SIGNATURE.len();
+    loop {
+        if offset + 12 > data.len() {
+            return Err("Truncated PNG".to_string());
+        }
+        let length = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let end = offset + 12 + length;
+        if end > data.len() {
+            return Err("Truncated PNG chunk".to_string());
+        }
+        let kind = &data[offset + 4..offset + 8];
+        chunks.push(PngChunk {
+            kind,
+            data: &data[offset + 8..offset + 8 + length],
+            raw: &data[offset..end],
+        });
+        if kind == b"IEND" {
+            return Ok((chunks, end));
+        }
+        offset = end;
+    }
+}
+
+// iTXt: keyword \0 compression flag, method, language \0 translated keyword \0 text
+fn parse_itxt(data: &[u8]) -> Option<(&str, String)> {
+    let keyword_end = data.iter().position(|&b| b == 0)?;
+    let keyword = std::str::from_utf8(&data[..keyword_end]).ok()?;
+    let rest = data.get(keyword_end + 1..)?;
+    let (&compressed, rest) = rest.split_first()?;
+    if compressed != 0 {
+        return None;
+    }
+    let rest = rest.get(1..)?;
+    let language_end = rest.iter().position(|&b| b == 0)?;
+    let rest = &rest[language_end + 1..];
+    let translated_end = rest.iter().position(|&b| b == 0)?;
+    let text = String::from_utf8(rest[translated_end + 1..].to_vec()).ok()?;
+    Some((keyword, text))
+}
+
+fn itxt_chunk(keyword: &str, text: &str) -> Vec<u8> {
+    let mut body = b"iTXt".to_vec();
+    body.extend_from_slice(keyword.as_bytes());
+    body.extend_from_slice(&[0, 0, 0, 0, 0]);
+    body.extend_from_slice(text.as_bytes());
+
+    let mut chunk = ((body.len() - 4) as u32).to_be_bytes().to_vec();
+    chunk.extend_from_slice(&body);
+    chunk.extend_from_slice(&crc32fast::hash(&body).to_be_bytes());
+    chunk
+}
+
+fn is_snapsort_itxt(chunk: &PngChunk) -> bool {
+    chunk.kind == b"iTXt" && chunk.data.starts_with(PNG_KEYWORD_PREFIX.as_bytes())
+}
+
+fn read_png(data: &[u8]) -> Option<ImageTags> {
+    let mut tags = ImageTags::default();
+    let mut found = false;
+    for chunk in png_chunks(data).ok()?.0 {
+        if !is_snapsort_itxt(&chunk) {
+            continue;
+        }
+        if let Some((keyword, text)) = parse_itxt(chunk.data) {
+            tags.set_field(&keyword[PNG_KEYWORD_PREFIX.len()..], text);
+            found = true;
+        }
+    }
+    Some(tags).filter(|_| found)
+}
+
+fn rewrite_png(data: &[u8], tags: Option<&ImageTags>) -> Result<Vec<u8>, String> {
+    let (chunks, iend_end) = png_chunks(data)?;
+    let mut out = PNG_SIGNATURE.to_vec();
+    for chunk in chunks {
+        if is_snapsort_itxt(&chunk) {
+            continue;
+        }
+        if chunk.kind == b"IEND" {
+            for (name, value) in tags.map(|t| t.fields()).unwrap_or_default() {
+                out.extend(itxt_chunk(
+                    &format!("{}{}", PNG_KEYWORD_PREFIX, name),
+                    value,
+                ));
+            }
+        }
+        out.extend_from_slice(chunk.raw);
+    }
+    // Some tools append data after IEND; keep it as it was.
+    out.extend_from_slice(&data[iend_end..]);
+    Ok(out)
+}
+
+// --- XMP (JPEG and WebP) ---
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&gt;", ">")
+        .replace("&lt;", "<")
+        .replace("&amp;", "&")
+}
+
+fn xmp_block(tags: &ImageTags) -> String {
+    let attributes = tags
+        .fields()
+        .into_iter()
+        .map(|(name, value)| format!(" snapsort:{}=\"{}\"", name, xml_escape(value)))
+        .collect::<String>();
+    format!(
+        "<rdf:Description rdf:about=\"\" xmlns:snapsort=\"{}\"{}/>",
+        XMP_NAMESPACE, attributes
+    )
+}
+
+fn parse_xmp(xmp: &str) -> Option<ImageTags> {
+    let block = XMP_BLOCK.find(xmp)?;
+    let mut tags = ImageTags::default();
+    for capture in XMP_ATTRIBUTE.captures_iter(block.as_str()) {
+        tags.set_field(&capture[1], xml_unescape(&capture[2]));
+    }
+    Some(tags)
+}
+
+fn new_packet(block: &str) -> String {
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">{}</rdf:RDF>\
+         </x:xmpmeta><?xpacket end=\"w\"?>",
+        block
+    )
+}
+
+/// The packet to store, or None when nothing is left to store. A packet
+/// SnapSort created only for its tags goes away with them.
+fn merge_xmp(existing: Option<&str>, tags: Option<&ImageTags>) -> Option<String> {
+    let block = tags.map(xmp_block).unwrap_or_default();
+    match existing {
+        Some(xmp) if xmp.contains("</rdf:RDF>") => {
+            let stripped = XMP_BLOCK.replace_all(xmp, "");
+            let merged = stripped.replacen("</rdf:RDF>", &format!("{}</rdf:RDF>", block), 1);
+            Some(merged).filter(|packet| *packet != new_packet(""))
+        }
+        _ if tags.is_some() => Some(new_packet(&block)),
+        _ => existing.map(str::to_string),
+    }
+}
+
+// --- JPEG ---
+
+// (marker, payload, raw bytes)
+type JpegSegment<'a> = (u8, &'a [u8], &'a [u8]);
+
+// Marker segments before the scan data, and where the scan data starts.
+fn jpeg_segments(data: &[u8]) -> Result<(Vec<JpegSegment<'_>>, usize), String> {
+    let mut segments = Vec::new();
+    let mut offset = 2;
+    loop {
+        if offset + 4 > data.len() || data[offset] != 0xFF {
+            return Err("Malformed JPEG".to_string());
+        }
+        let marker = data[offset + 1];
+        if marker == 0xDA {
+            return Ok((segments, offset));
+        }
+        let length = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let end = offset + 2 + length;
+        if length < 2 || end > data.len() {
+            return Err("Truncated JPEG segment".to_string());
+        }
+        segments.push((marker, &data[offset + 4..end], &data[offset..end]));
+        offset = end;
+    }
+}
+
+fn jpeg_xmp(data: &[u8]) -> Option<String> {
+    let (segments, _) = jpeg_segments(data).ok()?;
+    segments
+        .into_iter()
+        .find(|(marker, payload, _)| *marker == 0xE1 && payload.starts_with(JPEG_XMP_HEADER))
+        .and_then(|(_, payload, _)| {
+            String::from_utf8(payload[JPEG_XMP_HEADER.len()..].to_vec()).ok()
+        })
+}
+
+fn rewrite_jpeg(data: &[u8], tags: Option<&ImageTags>) -> Result<Vec<u8>, String> {
+    let (segments, scan_start) = jpeg_segments(data)?;
+    let existing = jpeg_xmp(data);
+    let packet = merge_xmp(existing.as_deref(), tags);
+
+    let mut xmp_segment = Vec::new();
+    if let Some(packet) = packet {
+        let length = 2 + JPEG_XMP_HEADER.len() + packet.len();
+        if length > u16::MAX as usize {
+            return Err("XMP metadata too large for JPEG".to_string());
+        }
+        xmp_segment.extend_from_slice(&[0xFF, 0xE1]);
+        xmp_segment.extend_from_slice(&(length as u16).to_be_bytes());
+        xmp_segment.extend_from_slice(JPEG_XMP_HEADER);
+        xmp_segment.extend_from_slice(packet.as_bytes());
+    }
+
+    // XMP goes after the leading JFIF/Exif segments.
+    let mut out = data[..2].to_vec();
+    let mut inserted = false;
+    for (marker, payload, raw) in segments {
+        if marker == 0xE1 && payload.starts_with(JPEG_XMP_HEADER) {
+            continue;
+        }
+        if !inserted && marker != 0xE0 && marker != 0xE1 {
+            out.extend_from_slice(&xmp_segment);
+            inserted = true;
+        }
+        out.extend_from_slice(raw);
+    }
+    if !inserted {
+        out.extend_from_slice(&xmp_segment);
+    }
+    out.extend_from_slice(&data[scan_start..]);
+    Ok(out)
+}
+
+// --- WebP ---
+
+// (fourcc, payload)
+type WebpChunk<'a> = (&'a [u8], &'a [u8]);
+
+// Chunks inside the RIFF container, and where the container ends. A file
+// shorter than its RIFF header says is an error, so a rewrite never drops
+// the missing tail.
+fn webp_chunks(data: &[u8]) -> Result<(Vec<WebpChunk<'_>>, usize), String> {
+    let riff_size = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+    let riff_end = 8 + riff_size;
+    if riff_end > data.len() {
+        return Err("Truncated WebP".to_string());
+    }
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while offset + 8 <= riff_end {
+        let kind = &data[offset..offset + 4];
+        let size = u32::from_le_bytes(data[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let end = offset + 8 + size;
+        if end > riff_end {
+            return Err("Truncated WebP chunk".to_string());
+        }
+        chunks.push((kind, &data[offset + 8..end]));
+        offset = end + size % 2;
+    }
+    Ok((chunks, riff_end))
+}
+
+fn webp_xmp(data: &[u8]) -> Option<String> {
+    webp_chunks(data)
+        .ok()?
+        .0
+        .into_iter()
+        .find(|(kind, _)| *kind == b"XMP ")
+        .and_then(|(_, payload)| String::from_utf8(payload.to_vec()).ok())
+}
+
+// Canvas size and alpha of a simple (non-VP8X) WebP, for synthesizing VP8X.
+fn webp_canvas(kind: &[u8], payload: &[u8]) -> Option<(u32, u32, bool)> {
+    match kind {
+        b"VP8 " if payload.len() >= 10 && payload[3..6] == [0x9D, 0x01, 0x2A] => {
+            let width = u16::from_le_bytes([payload[6], payload[7]]) as u32 & 0x3FFF;
+            let height = u16::from_le_bytes([payload[8], payload[9]]) as u32 & 0x3FFF;
+            Some((width, height, false))
+        }
+        b"VP8L" if payload.len() >= 5 && payload[0] == 0x2F => {
+            let bits = u32::from_le_bytes(payload[1..5].try_into().ok()?);
+            let width = (bits & 0x3FFF) + 1;
+            let height = ((bits >> 14) & 0x3FFF) + 1;
+            Some((width, height, bits >> 28 & 1 == 1))
+        }
+        _ => None,
+    }
+}
+
+fn push_webp_chunk(out: &mut Vec<u8>, kind: &[u8], payload: &[u8]) {
+    out.extend_from_slice(kind);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(payload);
+    if payload.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+fn rewrite_webp(data: &[u8], tags: Option<&ImageTags>) -> Result<Vec<u8>, String> {
+    let (chunks, riff_end) = webp_chunks(data)?;
+    let existing = webp_xmp(data);
+    let packet = merge_xmp(existing.as_deref(), tags);
+
+    // Metadata chunks require the extended (VP8X) header.
+    let mut vp8x = match chunks.iter().find(|(kind, _)| *kind == b"VP8X") {
+        Some((_, payload)) => payload.to_vec(),
+        None => {
+            let (width, height, alpha) = chunks
+                .iter()
+                .find_map(|(kind, payload)| webp_canvas(kind, payload))
+                .ok_or_else(|| "Unrecognized WebP bitstream".to_string())?;
+            let mut header = vec![if alpha { WEBP_ALPHA_FLAG } else { 0 }, 0, 0, 0];
+            header.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+            header.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+            header
+        }
+    };
+    if packet.is_some() {
+        vp8x[0] |= WEBP_XMP_FLAG;
+    } else {
+        vp8x[0] &= !WEBP_XMP_FLAG;
+    }
+
+    let rest = chunks
+        .into_iter()
+        .filter(|(kind, _)| *kind != b"VP8X" && *kind != b"XMP ")
+        .collect::<Vec<WebpChunk>>();
+    // With the tags gone, a lone bitstream needs no extended header, as
+    // before SnapSort added one.
+    let simple = match rest.as_slice() {
+        [(b"VP8 ", _)] => vp8x[0] == 0,
+        [(b"VP8L", _)] => vp8x[0] & !WEBP_ALPHA_FLAG == 0,
+        _ => false,
+    };
+
+    let mut body = b"WEBP".to_vec();
+    if !simple {
+        push_webp_chunk(&mut body, b"VP8X", &vp8x);
+    }
+    for (kind, payload) in rest {
+        push_webp_chunk(&mut body, kind, payload);
+    }
+    if let Some(packet) = packet {
+        push_webp_chunk(&mut body, b"XMP ", packet.as_bytes());
+    }
+
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend(body);
+    // Keep anything appended after the container as it was.
+    out.extend_from_slice(&data[riff_end..]);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screenshot(dir: &Path) -> std::path::PathBuf {
+        screenshot_as(dir, "png")
+    }
+
+    fn screenshot_as(dir: &Path, extension: &str) -> std::path::PathBuf {
+        let path = dir.join(format!("Screenshot 2025-03-14 at 09.26.53.{}", extension));
+        image::RgbImage::from_fn(16, 16, |x, y| {
+            image::Rgb([(x * 16) as u8, (y * 16) as u8, 90])
+        })
+        .save(&path)
+        .unwrap();
+        path
+    }
+
+    fn tags() -> ImageTags {
+        ImageTags::from_folder(
+            "Finance/2025/03/Invoices",
+            Some("Invoice from \"Acme\" — €42 <paid>".to_string()),
+            "Screenshot 2025-03-14 at 09.26.53.png".to_string(),
+        )
+    }
+
+    #[test]
+    fn from_folder_skips_date_segments() {
+        let tags = tags();
+        assert_eq!(tags.category, "Finance");
+        assert_eq!(tags.subcategory.as_deref(), Some("Invoices"));
+        assert_eq!(
+            ImageTags::from_folder("Code", None, String::new()).subcategory,
+            None
+        );
+    }
+
+    #[test]
+    fn tags_round_trip_and_the_image_still_decodes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = screenshot(dir.path());
+        let original = std::fs::read(&path).unwrap();
+        let pixels = image::open(&path).unwrap().to_rgb8();
+        assert_eq!(read_tags(&path), None);
+
+        write_tags(&path, &tags()).unwrap();
+        assert_eq!(read_tags(&path), Some(tags()));
+        assert_eq!(image::open(&path).unwrap().to_rgb8(), pixels);
+
+        remove_tags(&path).unwrap();
+        assert_eq!(read_tags(&path), None);
+        assert_eq!(std::fs::read(&path).unwrap(), original);
+        assert!(!dir
+            .path()
+            .join(format!(".{}.snapsort-tmp", tags().original_name))
+            .exists());
+    }
+
+    #[test]
+    fn retagging_replaces_the_earlier_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = screenshot(dir.path());
+        write_tags(&path, &tags()).unwrap();
+        let retagged = ImageTags::from_folder("Code", None, tags().original_name);
+        write_tags(&path, &retagged).unwrap();
+
+        assert_eq!(read_tags(&path), Some(retagged));
+        let data = std::fs::read(&path).unwrap();
+        let (chunks, _) = png_chunks(&data).unwrap();
+        assert_eq!(chunks.iter().filter(|c| is_snapsort_itxt(c)).count(), 2);
+        assert_eq!(chunks.last().unwrap().kind, b"IEND");
+        assert!(image::open(&path).is_ok());
+    }
+
+    #[test]
+    fn data_after_iend_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = screenshot(dir.path());
+        let mut data = std::fs::read(&path).unwrap();
+        data.extend_from_slice(b"trailer");
+        std::fs::write(&path, &data).unwrap();
+
+        write_tags(&path, &tags()).unwrap();
+        assert!(std::fs::read(&path)
+            .unwrap()
+            .ends_with(b"IEND\xaeB`\x82trailer"));
+        remove_tags(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+    }
+
+    #[test]
+    fn truncated_and_foreign_files_are_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = screenshot(dir.path());
+        write_tags(&path, &tags()).unwrap();
+        let tagged = std::fs::read(&path).unwrap();
+
+        // Cut inside a chunk, and cut cleanly before IEND
+        for cut in [tagged.len() - 20, tagged.len() - 12] {
+            std::fs::write(&path, &tagged[..cut]).unwrap();
+            assert_eq!(read_tags(&path), None);
+            assert!(write_tags(&path, &tags()).is_err());
+            assert!(remove_tags(&path).is_ok());
+            assert_eq!(std::fs::read(&path).unwrap(), &tagged[..cut]);
+        }
+
+        let gif = dir.path().join("anim.gif");
+        std::fs::write(&gif, b"GIF89a not a screenshot").unwrap();
+        assert_eq!(read_tags(&gif), None);
+        assert!(write_tags(&gif, &tags()).is_err());
+        assert_eq!(std::fs::read(&gif).unwrap(), b"GIF89a not a screenshot");
+    }
+
+    #[test]
+    fn xmp_tags_round_trip_in_jpeg_and_webp() {
+        let dir = tempfile::tempdir().unwrap();
+        for extension in ["jpg", "webp"] {
+            let path = screenshot_as(dir.path(), extension);
+            let original = std::fs::read(&path).unwrap();
+            let pixels = image::open(&path).unwrap().to_rgb8();
+            assert_eq!(read_tags(&path), None);
+
+            write_tags(&path, &tags()).unwrap();
+            assert_eq!(read_tags(&path), Some(tags()), "{}", extension);
+            assert_eq!(image::open(&path).unwrap().to_rgb8(), pixels);
+
+            let retagged = ImageTags::from_folder("Code", None, tags().original_name);
+            write_tags(&path, &retagged).unwrap();
+            assert_eq!(read_tags(&path), Some(retagged));
+            let data = String::from_utf8_lossy(&std::fs::read(&path).unwrap()).into_owned();
+            assert_eq!(data.matches(XMP_NAMESPACE).count(), 1);
+            assert_eq!(image::open(&path).unwrap().to_rgb8(), pixels);
+
+            remove_tags(&path).unwrap();
+            assert_eq!(read_tags(&path), None);
+            assert_eq!(std::fs::read(&path).unwrap(), original, "{}", extension);
+        }
+    }
+
+    #[test]
+    fn xmp_written_by_other_tools_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = screenshot_as(dir.path(), "jpg");
+        let foreign = "<rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" dc:format=\"image/jpeg\"/>";
+        let packet = new_packet(foreign);
+        let data = std::fs::read(&path).unwrap();
+        let mut with_xmp = data[..2].to_vec();
+        with_xmp.extend_from_slice(&[0xFF, 0xE1]);
+        with_xmp
+            .extend_from_slice(&((2 + JPEG_XMP_HEADER.len() + packet.len()) as u16).to_be_bytes());
+        with_xmp.extend_from_slice(JPEG_XMP_HEADER);
+        with_xmp.extend_from_slice(packet.as_bytes());
+        with_xmp.extend_from_slice(&data[2..]);
+        std::fs::write(&path, &with_xmp).unwrap();
+
+        write_tags(&path, &tags()).unwrap();
+        let xmp = jpeg_xmp(&std::fs::read(&path).unwrap()).unwrap();
+        assert!(xmp.contains(foreign));
+        assert_eq!(parse_xmp(&xmp), Some(tags()));
+
+        remove_tags(&path).unwrap();
+        assert_eq!(jpeg_xmp(&std::fs::read(&path).unwrap()).unwrap(), packet);
+        assert!(image::open(&path).is_ok());
+    }
+
+    #[test]
+    fn truncated_jpeg_and_webp_headers_are_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        for extension in ["jpg", "webp"] {
+            let path = screenshot_as(dir.path(), extension);
+            write_tags(&path, &tags()).unwrap();
+            let tagged = std::fs::read(&path).unwrap();
+
+            // Inside the JPEG header segments; short of the RIFF size
+            let cut = if extension == "jpg" {
+                40
+            } else {
+                tagged.len() - 10
+            };
+            std::fs::write(&path, &tagged[..cut]).unwrap();
+            assert_eq!(read_tags(&path), None);
+            assert!(write_tags(&path, &tags()).is_err(), "{}", extension);
+            assert_eq!(std::fs::read(&path).unwrap(), &tagged[..cut]);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+//! Model Context Protocol server over stdio, so AI agents can find and read
+//! screenshots ("show me the last error dialog").
+//!
+//! JSON-RPC messages are read one per line from stdin and answered on stdout.
+//! Tools only read: search runs on the shared index, metadata comes from the
+//! history log, description store and embedded tags. Only screenshots that
+//! SnapSort indexed, moved or scanned can be read, and never ones covered by
+//! a never-upload rule, since the agent is usually a remote model.
+
+use crate::capture_date;
+use crate::history::{HistoryEntry, HistoryQuery};
+use crate::image_tags;
+use crate::pipeline::{self, Library, MAX_FILE_SIZE};
+use crate::privacy;
+use crate::search::SearchFilters;
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+const SUPPORTED_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    // Absent for notifications, which get no response.
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Answer requests until the input closes.
+pub fn serve(library: &Library, input: impl BufRead, mut output: impl Write) -> Result<(), String> {
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<RpcRequest>(&line) {
+            Ok(request) => {
+                let Some(id) = request.id else {
+                    continue;
+                };
+                match handle(library, &request.method, request.params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                }
+            }
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": PARSE_ERROR, "message": e.to_string() },
+            }),
+        };
+        writeln!(output, "{}", response).map_err(|e| e.to_string())?;
+        output.flush().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn handle(library: &Library, method: &str, params: Value) -> Result<Value, (i64, String)> {
+    match method {
+        "initialize" => {
+            let requested = params["protocolVersion"].as_str().unwrap_or_default();
+            let version = SUPPORTED_VERSIONS
+                .into_iter()
+                .find(|version| *version == requested)
+                .unwrap_or(SUPPORTED_VERSIONS[0]);
+            Ok(json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "snapsort", "version": env!("CARGO_PKG_VERSION") },
+            }))
+        }
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => {
+            #[derive(Deserialize)]
+            struct ToolCall {
+                name: String,
+                #[serde(default)]
+                arguments: Value,
+            }
+            let call = serde_json::from_value::<ToolCall>(params)
+                .map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+            // Tool failures are results the agent can read, not protocol errors.
+            Ok(match call_tool(library, &call.name, call.arguments) {
+                Ok(content) => json!({ "content": content }),
+                Err(e) => json!({
+                    "content": [{ "type": "text", "text": e }],
+                    "isError": true,
+                }),
+            })
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+    }
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "search_screenshots",
+            "description": "Full-text search over organized screenshots: filename, category, \
+                            visible text and description. Returns paths, categories, capture \
+                            dates and a matching snippet.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Words to look for; empty lists everything" },
+                    "category": { "type": "string", "description": "Category or prefix, e.g. Code or Code/Errors" },
+                    "from": { "type": "string", "description": "Captured on or after, YYYY-MM-DD" },
+                    "to": { "type": "string", "description": "Captured on or before, YYYY-MM-DD" },
+                    "limit": { "type": "integer", "description": "Maximum results, default 20" }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "get_screenshot",
+            "description": "Metadata, stored description and move history of one screenshot, \
+                            optionally with the image itself.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path from search_screenshots" },
+                    "include_image": { "type": "boolean", "description": "Attach the image, default false" }
+                },
+                "required": ["path"]
+            }
+        },
+        {
+            "name": "list_categories",
+            "description": "Categories in the library with the number of screenshots in each.",
+            "inputSchema": { "type": "object", "properties": {} }
+        }
+    ])
+}
+
+fn call_tool(library: &Library, name: &str, arguments: Value) -> Result<Vec<Value>, String> {
+    match name {
+        "search_screenshots" => {
+            #[derive(Deserialize)]
+            struct Args {
+                query: String,
+                category: Option<String>,
+                from: Option<String>,
+                to: Option<String>,
+                limit: Option<usize>,
+            }
+            let args = serde_json::from_value::<Args>(arguments).map_err(|e| e.to_string())?;
+            let hits = library.search_index()?.search(
+                &args.query,
+                &SearchFilters {
+                    category: args.category,
+                    from: args.from,
+                    to: args.to,
+                    limit: args.limit,
+                },
+            )?;
+            text_content(&hits)
+        }
+        "get_screenshot" => {
+            #[derive(Deserialize)]
+            struct Args {
+                path: String,
+                #[serde(default)]
+                include_image: bool,
+            }
+            let args = serde_json::from_value::<Args>(arguments).map_err(|e| e.to_string())?;
+            screenshot_content(library, &args.path, args.include_image)
+        }
+        "list_categories" => text_content(&library.search_index()?.categories()?),
+        _ => Err(format!("Unknown tool: {}", name)),
+    }
+}
+
+fn screenshot_content(
+    library: &Library,
+    path: &str,
+    include_image: bool,
+) -> Result<Vec<Value>, String> {
+    let file = Path::new(path);
+    let filename = file
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    // Moves into or out of this path, newest first
+    let history = library
+        .history
+        .query(&HistoryQuery {
+            name: Some(filename),
+            ..HistoryQuery::default()
+        })?
+        .into_iter()
+        .filter(|entry| entry.original_path == path || entry.final_path.as_deref() == Some(path))
+        .collect::<Vec<_>>();
+    if !is_known(library, path, &history)? {
+        return Err(format!("{} is not a screenshot SnapSort knows", path));
+    }
+    if let Some(protected) = privacy::protected(file, &library.privacy()) {
+        return Err(format!("{} is {}", path, protected.reason));
+    }
+    let metadata = std::fs::metadata(file).map_err(|e| format!("{}: {}", path, e))?;
+    let captured = capture_date::capture_date(file);
+
+    let mut content = text_content(&json!({
+        "path": path,
+        "size": metadata.len(),
+        "captured_at": captured.map(|c| c.captured_at.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        "tags": image_tags::read_tags(file),
+        "description": library.descriptions.get(path),
+        "history": history,
+    }))?;
+
+    if include_image {
+        if metadata.len() > MAX_FILE_SIZE {
+            return Err("Image exceeds 5MB, request it without include_image".to_string());
+        }
+        let data = std::fs::read(file).map_err(|e| e.to_string())?;
+        content.push(json!({
+            "type": "image",
+            "data": base64::engine::general_purpose::STANDARD.encode(&data),
+            "mimeType": pipeline::media_type(file),
+        }));
+    }
+    Ok(content)
+}
+
+// Whether `path` is in the search index, the history or a stored run.
+// Anything else could be any file the user can read.
+fn is_known(library: &Library, path: &str, history: &[HistoryEntry]) -> Result<bool, String> {
+    let indexed = match library.search_index() {
+        Ok(index) => index.contains(path)?,
+        Err(_) => false,
+    };
+    if !history.is_empty() || indexed {
+        return Ok(true);
+    }
+    for summary in library.sessions.list()? {
+        let run = library.sessions.load(&summary.run_id)?;
+        let scanned = run.files.iter().any(|file| file == path);
+        let proposed = run.proposals.iter().any(|entry| {
+            entry.proposal.original_path == path || entry.final_path.as_deref() == Some(path)
+        });
+        if scanned || proposed {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn text_content<T: serde::Serialize>(value: &T) -> Result<Vec<Value>, String> {
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    Ok(vec![json!({ "type": "text", "text": text })])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::privacy::{NeverUpload, PrivacySettings, RuleKind};
+    use crate::session_store::RunOptions;
+
+    fn get_screenshot(library: &Library, path: &Path) -> Result<Vec<Value>, String> {
+        call_tool(
+            library,
+            "get_screenshot",
+            json!({ "path": path.to_string_lossy(), "include_image": true }),
+        )
+    }
+
+    #[test]
+    fn get_screenshot_reads_only_known_unprotected_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::open(&dir.path().join("data"));
+        let scanned = dir.path().join("Screenshot statement.png");
+        let other = dir.path().join("id_ed25519");
+        std::fs::write(&scanned, b"png").unwrap();
+        std::fs::write(&other, b"key").unwrap();
+        library
+            .sessions
+            .start(
+                &dir.path().to_string_lossy(),
+                std::slice::from_ref(&scanned),
+                RunOptions::default(),
+            )
+            .unwrap();
+
+        let unknown = get_screenshot(&library, &other).unwrap_err();
+        assert!(
+            unknown.contains("not a screenshot SnapSort knows"),
+            "{}",
+            unknown
+        );
+        let content = get_screenshot(&library, &scanned).unwrap();
+        assert_eq!(content[1]["type"], "image");
+
+        library
+            .set_privacy(&PrivacySettings {
+                never_upload: vec![NeverUpload {
+                    kind: RuleKind::Pattern,
+                    value: "*statement*".to_string(),
+                    category: None,
+                }],
+                ..PrivacySettings::default()
+            })
+            .unwrap();
+        let protected = get_screenshot(&library, &scanned).unwrap_err();
+        assert!(protected.contains("never uploaded"), "{}", protected);
+    }
+}
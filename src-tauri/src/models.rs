@@ -0,0 +1,153 @@
+//! Which model handles each task, from `models.json` in the data directory.
+//!
+//! Classification starts on a cheaper model by default: when its answer does
+//! not parse or it reports low confidence, the file is sent again to the
+//! `classify` model. Batch runs skip this and go straight to `classify`,
+//! since a second round would mean waiting for another batch. Refining and
+//! summarizing are small tasks and default to cheaper models too.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const MODELS_FILE: &str = "models.json";
+pub const DEFAULT_MODEL: &str = "claude-opus-4-5-20251101";
+const DEFAULT_REFINE_MODEL: &str = "claude-sonnet-4-5-20250929";
+const DEFAULT_FAST_MODEL: &str = "claude-haiku-4-5-20251001";
+const DEFAULT_MIN_CONFIDENCE: f32 = 0.6;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ModelSettings {
+    /// Names and files screenshots.
+    #[serde(default = "default_model")]
+    pub classify: String,
+    /// Suggests a more specific subcategory for one file.
+    #[serde(default = "default_refine_model")]
+    pub refine: String,
+    /// Writes the end-of-run summary when asked to.
+    #[serde(default = "default_fast_model")]
+    pub summarize: String,
+    /// Cheaper model tried first for classification; None sends every
+    /// request to `classify` directly.
+    #[serde(default = "default_escalate_from")]
+    pub escalate_from: Option<String>,
+    /// Answers of the first model below this confidence (0 to 1) are
+    /// classified again by `classify`.
+    #[serde(default = "default_min_confidence")]
+    pub min_confidence: f32,
+}
+
+fn default_model() -> String {
+    DEFAULT_MODEL.to_string()
+}
+
+fn default_refine_model() -> String {
+    DEFAULT_REFINE_MODEL.to_string()
+}
+
+fn default_fast_model() -> String {
+    DEFAULT_FAST_MODEL.to_string()
+}
+
+fn default_escalate_from() -> Option<String> {
+    Some(default_fast_model())
+}
+
+fn default_min_confidence() -> f32 {
+    DEFAULT_MIN_CONFIDENCE
+}
+
+impl Default for ModelSettings {
+    fn default() -> Self {
+        ModelSettings {
+            classify: default_model(),
+            refine: default_refine_model(),
+            summarize: default_fast_model(),
+            escalate_from: default_escalate_from(),
+            min_confidence: DEFAULT_MIN_CONFIDENCE,
+        }
+    }
+}
+
+impl ModelSettings {
+    /// Saved settings, or the defaults when there are none. A broken file
+    /// is reported and ignored.
+    pub fn load(path: &Path) -> ModelSettings {
+        match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| eprintln!("[RUST] ⚠️ Ignoring {}: {}", path.display(), e))
+                .unwrap_or_default(),
+            Err(_) => ModelSettings::default(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| e.to_string())
+    }
+
+    /// Model that gets a classification request first.
+    pub fn first_classify_model(&self) -> &str {
+        self.escalate_from
+            .as_deref()
+            .map(str::trim)
+            .filter(|model| !model.is_empty())
+            .unwrap_or(&self.classify)
+    }
+
+    /// Whether an answer of `model` should be classified again by
+    /// `classify`. Answers without a confidence count as sure.
+    pub fn should_escalate(&self, model: &str, unparsed: bool, confidence: Option<f32>) -> bool {
+        model != self.classify
+            && (unparsed || confidence.is_some_and(|confidence| confidence < self.min_confidence))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opus_is_only_the_escalation_target() {
+        let models = ModelSettings::default();
+        assert_eq!(models.classify, DEFAULT_MODEL);
+        for model in [
+            &models.refine,
+            &models.summarize,
+            models.first_classify_model(),
+        ] {
+            assert!(
+                model.contains("haiku") || model.contains("sonnet"),
+                "{}",
+                model
+            );
+        }
+        assert!(models.should_escalate(models.first_classify_model(), true, None));
+        assert!(models.should_escalate(models.first_classify_model(), false, Some(0.4)));
+        assert!(!models.should_escalate(models.first_classify_model(), false, Some(0.9)));
+        assert!(!models.should_escalate(&models.classify, true, None));
+    }
+
+    #[test]
+    fn saved_settings_fill_in_missing_fields_and_keep_escalation_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(MODELS_FILE);
+
+        std::fs::write(&path, r#"{"classify": "claude-sonnet-4-5"}"#).unwrap();
+        let loaded = ModelSettings::load(&path);
+        assert_eq!(loaded.classify, "claude-sonnet-4-5");
+        assert_eq!(loaded.refine, DEFAULT_REFINE_MODEL);
+        assert_eq!(loaded.escalate_from.as_deref(), Some(DEFAULT_FAST_MODEL));
+
+        // Turned off in settings, saved as null
+        let off = ModelSettings {
+            escalate_from: None,
+            ..ModelSettings::default()
+        };
+        off.save(&path).unwrap();
+        assert_eq!(ModelSettings::load(&path), off);
+        assert_eq!(off.first_classify_model(), DEFAULT_MODEL);
+    }
+}
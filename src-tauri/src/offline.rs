@@ -0,0 +1,297 @@
+//! Classification without the API, for files that must stay on this machine:
+//! a category from keywords in the recognized text and the file name, and a
+//! name from the first words of the text, or from the original name without any.
+
+use crate::bursts;
+use crate::capture_date;
+use crate::pipeline::{self, FileProposal, RunContext};
+use crate::privacy::REDACTED;
+use std::path::Path;
+
+/// Stands in for the model name on proposals made here.
+pub const OFFLINE_MODEL: &str = "offline";
+const NAME_WORDS: usize = 3;
+const VISIBLE_WORDS: usize = 25;
+
+// The category with the most matching keywords wins, the earlier on a tie
+const KEYWORDS: &[(&str, &[&str])] = &[
+    (
+        "Finance",
+        &[
+            "invoice",
+            "receipt",
+            "balance",
+            "payment",
+            "bank",
+            "transaction",
+            "amount due",
+            "total",
+            "iban",
+            "refund",
+        ],
+    ),
+    (
+        "Code",
+        &[
+            "error",
+            "warning",
+            "terminal",
+            "function",
+            "import ",
+            "const ",
+            "def ",
+            "fn ",
+            "class ",
+            "git",
+            "npm",
+            "cargo",
+            "localhost",
+            "traceback",
+        ],
+    ),
+    (
+        "Chat",
+        &[
+            "message",
+            "typing",
+            "delivered",
+            "slack",
+            "whatsapp",
+            "imessage",
+            "discord",
+            "telegram",
+            "signal",
+        ],
+    ),
+    (
+        "Email",
+        &[
+            "inbox",
+            "subject:",
+            "from:",
+            "to:",
+            "reply all",
+            "forward",
+            "gmail",
+            "outlook",
+            "unsubscribe",
+        ],
+    ),
+    (
+        "Shopping",
+        &[
+            "cart",
+            "checkout",
+            "order",
+            "shipping",
+            "delivery",
+            "add to bag",
+            "in stock",
+        ],
+    ),
+    (
+        "Social",
+        &[
+            "followers",
+            "following",
+            "retweet",
+            "repost",
+            "likes",
+            "comments",
+            "twitter",
+            "instagram",
+            "linkedin",
+            "facebook",
+        ],
+    ),
+    (
+        "Settings",
+        &[
+            "settings",
+            "preferences",
+            "enabled",
+            "disabled",
+            "notifications",
+            "sign in",
+            "log in",
+            "password",
+        ],
+    ),
+    ("Browser", &["http", "www.", ".com", "bookmarks", "new tab"]),
+    (
+        "Documents",
+        &["page", "chapter", "document", "pdf", "section", "contents"],
+    ),
+    (
+        "Design",
+        &["figma", "sketch", "layer", "canvas", "artboard", "font"],
+    ),
+    (
+        "Media",
+        &[
+            "playlist", "video", "youtube", "spotify", "netflix", "episode", "pause",
+        ],
+    ),
+];
+
+/// Category for a screenshot from its recognized text and file name;
+/// `Other` when nothing matches.
+pub fn category(text: &str, file_name: &str) -> &'static str {
+    let haystack = format!("{}\n{}", text, file_name).to_lowercase();
+    let mut best = ("Other", 0);
+    for (category, keywords) in KEYWORDS {
+        let score = keywords
+            .iter()
+            .filter(|keyword| haystack.contains(*keyword))
+            .count();
+        if score > best.1 {
+            best = (category, score);
+        }
+    }
+    best.0
+}
+
+/// Proposal for one screenshot from its recognized `text`, in `category`
+/// when a rule names one.
+pub(crate) fn file_proposal(
+    path: &Path,
+    run: &RunContext,
+    text: &str,
+    category: Option<&str>,
+    reasoning: &str,
+) -> FileProposal {
+    let filename = pipeline::file_name(path);
+    let category = category.unwrap_or_else(|| self::category(text, &filename));
+    eprintln!("[RUST] Classified {} offline as {}", filename, category);
+    let captured_at = capture_date::capture_date(path).map(|c| c.captured_at);
+
+    FileProposal {
+        id: filename.clone(),
+        original_path: path.to_string_lossy().to_string(),
+        original_name: filename,
+        proposed_name: pipeline::with_image_extension(&name(text, category, path), path),
+        proposed_category: run.layout.apply(category, captured_at),
+        reasoning: reasoning.to_string(),
+        session_id: None,
+        model: OFFLINE_MODEL.to_string(),
+        visible_text: visible_text(text),
+        confidence: None,
+        description: None,
+        redactions: Vec::new(),
+    }
+}
+
+/// Proposals for a burst from the recognized text of its shots, with a
+/// shared subfolder and sequential names like the API's.
+pub(crate) fn session_proposals(
+    session: &bursts::Session,
+    run: &RunContext,
+    text: &str,
+    reasoning: &str,
+) -> Vec<FileProposal> {
+    let category = category(text, "");
+    let session_name = name(text, category, &session.paths[0]);
+    eprintln!(
+        "[RUST] Classified session {} offline as {}",
+        session.id, category
+    );
+    let proposed_category = run.layout.apply(
+        &format!("{}/{}", category, session_name),
+        Some(session.started_at),
+    );
+
+    let total = session.paths.len();
+    session
+        .paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let filename = pipeline::file_name(path);
+            FileProposal {
+                id: filename.clone(),
+                original_path: path.to_string_lossy().to_string(),
+                original_name: filename,
+                proposed_name: pipeline::with_image_extension(
+                    &bursts::sequence_name(&session_name, index, total),
+                    path,
+                ),
+                proposed_category: proposed_category.clone(),
+                reasoning: reasoning.to_string(),
+                session_id: Some(session.id.clone()),
+                model: OFFLINE_MODEL.to_string(),
+                visible_text: String::new(),
+                confidence: None,
+                description: None,
+                redactions: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+// snake_case name from the first plain words of the text. Without any, the
+// original's name stands in, so that files with no text, such as those a
+// folder or pattern rule keeps local, do not all end up with the same name.
+fn name(text: &str, category: &str, original: &Path) -> String {
+    let words = text
+        .split_whitespace()
+        .filter(|word| !word.contains(REDACTED))
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|word| word.len() >= 3 && word.chars().all(|c| c.is_ascii_alphabetic()))
+        .take(NAME_WORDS)
+        .collect::<Vec<String>>();
+    if !words.is_empty() {
+        return bursts::slugify(&words.join("_"));
+    }
+    let category = bursts::slugify(category);
+    let stem = original
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if stem.chars().any(|c| c.is_ascii_alphanumeric()) {
+        format!("{}_{}", category, bursts::slugify(&stem))
+    } else if let Some(captured) = capture_date::capture_date(original) {
+        format!(
+            "{}_{}",
+            category,
+            captured.captured_at.format("%Y%m%d_%H%M%S")
+        )
+    } else {
+        format!("{}_screenshot", category)
+    }
+}
+
+fn visible_text(text: &str) -> String {
+    text.split_whitespace()
+        .take(VISIBLE_WORDS)
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_come_from_the_text_or_else_the_original() {
+        let shot = Path::new("/shots/Screenshot 2025-03-14 at 10.22.33.png");
+        assert_eq!(
+            name("Stripe Invoice #1042 amount due", "Finance", shot),
+            "stripe_invoice_amount"
+        );
+        assert_eq!(
+            name("", "Finance", shot),
+            "finance_screenshot_2025_03_14_at_10_22_33"
+        );
+        assert_eq!(
+            name(
+                "42 € ██",
+                "Finance/Banking",
+                Path::new("/shots/スクショ.png")
+            ),
+            "finance_banking_screenshot"
+        );
+    }
+}
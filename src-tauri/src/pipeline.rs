@@ -0,0 +1,2277 @@
+//! The screenshot pipeline without any GUI: find candidate files, classify
+//! them with the Anthropic API, and apply or undo the resulting moves.
+//!
+//! The Tauri commands and the `snapsort` CLI are thin layers over this module.
+//! Diagnostics go to stderr so CLI output on stdout stays machine-readable.
+
+use crate::batch;
+use crate::bursts;
+use crate::capture_date;
+use crate::dedup;
+use crate::description::{
+    self, DescriptionRecord, DescriptionStorage, DescriptionStore, RichDescription,
+};
+use crate::embeddings::{self, EmbeddingInput, EmbeddingUpdate};
+use crate::events::{EventSink, PipelineEvent};
+use crate::history::{HistoryAction, HistoryEntry, HistoryLog};
+use crate::http_client::{ApiClient, NetworkConfig, NETWORK_FILE};
+use crate::image_tags;
+use crate::models::{ModelSettings, MODELS_FILE};
+use crate::offline;
+use crate::privacy::{self, PrivacyMode, PrivacySettings, PRIVACY_FILE};
+use crate::search::{SearchDocument, SearchIndex};
+use crate::session_store::{ApplyStatus, SessionEvent, SessionStore};
+use crate::usage::{self, CostEstimate, PriceTable, RequestUsage, RunCost, TokenUsage};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+// 5MB limit
+pub const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+pub const CATEGORY_OPTIONS: &str =
+    "Code, Finance, Social, Shopping, Email, Chat, Browser, Design, Documents, Settings, Media, Other";
+
+/// Per-model price overrides in the data directory.
+pub const PRICES_FILE: &str = "prices.json";
+
+/// Bundle identifier; the desktop app keeps its data under this name.
+pub const APP_IDENTIFIER: &str = "com.pawan.smartdump";
+
+#[derive(Serialize)]
+pub(crate) struct AnthropicMessage {
+    pub(crate) role: String,
+    pub(crate) content: Vec<AnthropicContent>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AnthropicContent {
+    Text { text: String },
+    Image { source: AnthropicImageSource },
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct AnthropicImageSource {
+    #[serde(rename = "type")]
+    pub(crate) source_type: String,
+    pub(crate) media_type: String,
+    pub(crate) data: String,
+}
+
+// Proposal Event Structure
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FileProposal {
+    pub id: String,
+    pub original_path: String,
+    pub original_name: String,
+    pub proposed_name: String,
+    pub proposed_category: String,
+    pub reasoning: String,
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub visible_text: String,
+    /// How sure the model was of the category, 0 to 1.
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    #[serde(default)]
+    pub description: Option<RichDescription>,
+    /// Kinds of what was blacked out before upload, e.g. `card number`.
+    #[serde(default)]
+    pub redactions: Vec<String>,
+}
+
+// Skipped file event
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SkippedFile {
+    pub name: String,
+    pub size: u64,
+    pub reason: String,
+    /// Kept from the API by a never-upload rule and classified locally.
+    #[serde(default)]
+    pub never_upload: bool,
+}
+
+// Everything a pipeline task needs to know about its run
+#[derive(Clone)]
+pub struct RunContext {
+    pub run_id: String,
+    pub api_key: String,
+    pub layout: capture_date::FolderLayout,
+    pub descriptions: Option<DescriptionStorage>,
+    pub prices: Arc<PriceTable>,
+    pub models: ModelSettings,
+    pub client: ApiClient,
+    pub privacy: PrivacySettings,
+}
+
+// Per-run scan options, all optional
+#[derive(Deserialize, Default)]
+pub struct ScanOptions {
+    pub skip_duplicates: Option<bool>,
+    pub duplicate_threshold: Option<f32>,
+    pub group_bursts: Option<bool>,
+    pub layout: Option<capture_date::FolderLayout>,
+    // Rich descriptions are off unless a storage is chosen
+    pub descriptions: Option<DescriptionStorage>,
+    pub write_tags: Option<bool>,
+    pub skip_tagged: Option<bool>,
+    /// Stop sending requests once the run would spend more than this, in USD.
+    pub budget_usd: Option<f64>,
+    /// Submit every request as a Message Batch: half the price, but results
+    /// can take up to a day.
+    pub batch: Option<bool>,
+    /// Overrides the saved privacy mode for this run.
+    pub privacy: Option<PrivacyMode>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct MoveResult {
+    pub original_path: String,
+    pub final_path: String,
+    pub renamed: bool,
+}
+
+/// One file move requested by the GUI, a plan or an undo.
+pub struct MoveRequest {
+    pub original_path: String,
+    pub new_path: String,
+    pub root_path: Option<String>,
+    pub overwrite: bool,
+    pub run_id: Option<String>,
+    pub proposal_id: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct MoveOutcome {
+    pub id: String,
+    pub original_path: String,
+    pub final_path: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Files found in a scan folder, before any analysis.
+pub struct ScanResult {
+    pub files: Vec<PathBuf>,
+    pub skipped: Vec<SkippedFile>,
+    /// Names of files whose metadata could not be read.
+    pub failed: Vec<String>,
+}
+
+/// Files ready for classification after tag, duplicate and burst handling.
+pub struct PreparedScan {
+    /// Files classified one by one.
+    pub files: Vec<PathBuf>,
+    /// Bursts classified with one request each.
+    pub sessions: Vec<bursts::Session>,
+    pub duplicate_groups: Vec<dedup::DuplicateGroup>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+impl PreparedScan {
+    pub fn actionable(&self) -> usize {
+        self.files.len()
+            + self
+                .sessions
+                .iter()
+                .map(|session| session.paths.len())
+                .sum::<usize>()
+    }
+}
+
+/// Where the desktop app keeps its data, shared with the CLI.
+pub fn default_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+/// Image formats a scan picks up; all of them can carry SnapSort tags.
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+pub fn is_screenshot_image(filename: &str) -> bool {
+    let lower = filename.to_lowercase();
+    let is_image = Path::new(&lower)
+        .extension()
+        .is_some_and(|ext| IMAGE_EXTENSIONS.iter().any(|known| ext == *known));
+    let has_screenshot = lower.contains("screenshot")
+        || lower.contains("screen shot")
+        || capture_date::is_screenshot_name(filename);
+    is_image && has_screenshot
+}
+
+/// Lowercase extension of an image, `png` when it has none.
+pub(crate) fn image_extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .unwrap_or_else(|| "png".to_string())
+}
+
+pub(crate) fn media_type(path: &Path) -> &'static str {
+    match image_extension(path).as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        _ => "image/png",
+    }
+}
+
+/// `name` with the extension of the image at `path`, replacing an image
+/// extension the model or the user gave it.
+pub(crate) fn with_image_extension(name: &str, path: &Path) -> String {
+    let extension = image_extension(path);
+    let stem = match name.rsplit_once('.') {
+        Some((_, ext)) if ext.to_lowercase() == extension => return name.to_string(),
+        Some((stem, ext)) if IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()) => stem,
+        _ => name,
+    };
+    format!("{}.{}", stem, extension)
+}
+
+pub fn contains_parent_dir(path: &Path) -> bool {
+    path.components()
+        .any(|component| matches!(component, Component::ParentDir))
+}
+
+pub(crate) fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Screenshots in `root`, or the selected subset of them, split into files
+/// worth analyzing and files skipped for size.
+pub fn collect_files(root: &Path, selected: Option<Vec<String>>) -> ScanResult {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(selected) = selected.filter(|paths| !paths.is_empty()) {
+        eprintln!("[RUST] Using {} selected file(s)", selected.len());
+        let mut seen: HashSet<String> = HashSet::new();
+        for path_str in selected {
+            if !seen.insert(path_str.clone()) {
+                continue;
+            }
+            let file_path = PathBuf::from(&path_str);
+            if !file_path.starts_with(root) {
+                eprintln!("[RUST] ⚠️ Skipping {} - outside scan folder", path_str);
+                continue;
+            }
+            candidates.push(file_path);
+        }
+    } else if let Ok(entries) = std::fs::read_dir(root) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|file_type| file_type.is_file()) {
+                candidates.push(entry.path());
+            }
+        }
+    } else {
+        eprintln!("[RUST] ⚠️ Could not read directory");
+    }
+
+    let mut result = ScanResult {
+        files: Vec::new(),
+        skipped: Vec::new(),
+        failed: Vec::new(),
+    };
+    for file_path in candidates {
+        let filename = file_name(&file_path);
+        if !is_screenshot_image(&filename) {
+            continue;
+        }
+
+        let metadata = match std::fs::metadata(&file_path) {
+            Ok(meta) => meta,
+            Err(e) => {
+                eprintln!("[RUST] ⚠️ Failed to read metadata for {}: {}", filename, e);
+                result.failed.push(filename);
+                continue;
+            }
+        };
+
+        let file_size = metadata.len();
+        if file_size > MAX_FILE_SIZE {
+            eprintln!("[RUST] ⚠️ Skipping {} - exceeds 5MB", filename);
+            result.skipped.push(SkippedFile {
+                name: filename,
+                size: file_size,
+                reason: "exceeds 5MB limit".to_string(),
+                never_upload: false,
+            });
+            continue;
+        }
+
+        result.files.push(file_path);
+    }
+    result
+}
+
+/// Drop files SnapSort already tagged, group duplicates and bursts.
+/// Fingerprinting decodes every image, so call this off any UI thread.
+pub fn prepare(files: Vec<PathBuf>, options: &ScanOptions) -> PreparedScan {
+    let skip_duplicates = options.skip_duplicates.unwrap_or(true);
+    let threshold = options
+        .duplicate_threshold
+        .unwrap_or(dedup::DEFAULT_SIMILARITY_THRESHOLD);
+    let group_bursts = options.group_bursts.unwrap_or(true);
+
+    let mut skipped = Vec::new();
+    let files = if options.skip_tagged.unwrap_or(true) {
+        skip_tagged_files(files, &mut skipped)
+    } else {
+        files
+    };
+
+    let fingerprints = if skip_duplicates || group_bursts {
+        dedup::fingerprint_all(&files)
+    } else {
+        Vec::new()
+    };
+
+    let mut duplicate_groups = Vec::new();
+    let (files, fingerprints) = if skip_duplicates {
+        duplicate_groups = dedup::group_duplicates(&fingerprints, threshold);
+        eprintln!("[RUST] Found {} duplicate group(s)", duplicate_groups.len());
+
+        let mut duplicates: HashSet<PathBuf> = HashSet::new();
+        for group in &duplicate_groups {
+            for member in &group.duplicates {
+                duplicates.insert(PathBuf::from(&member.path));
+                skipped.push(SkippedFile {
+                    name: member.name.clone(),
+                    size: member.size,
+                    reason: format!("duplicate of {}", group.keeper.name),
+                    never_upload: false,
+                });
+            }
+        }
+        (
+            files
+                .into_iter()
+                .filter(|path| !duplicates.contains(path))
+                .collect::<Vec<PathBuf>>(),
+            fingerprints
+                .into_iter()
+                .filter(|fp| !duplicates.contains(&fp.path))
+                .collect::<Vec<dedup::Fingerprint>>(),
+        )
+    } else {
+        (files, fingerprints)
+    };
+
+    let sessions = if group_bursts {
+        bursts::group_sessions(
+            &fingerprints,
+            bursts::DEFAULT_MAX_GAP_SECS,
+            bursts::DEFAULT_MIN_SIMILARITY,
+        )
+    } else {
+        Vec::new()
+    };
+    let in_session: HashSet<PathBuf> = sessions
+        .iter()
+        .flat_map(|session| session.paths.iter().cloned())
+        .collect();
+
+    PreparedScan {
+        files: files
+            .into_iter()
+            .filter(|path| !in_session.contains(path))
+            .collect(),
+        sessions,
+        duplicate_groups,
+        skipped,
+    }
+}
+
+// Skip files that already carry SnapSort tags from an earlier run
+fn skip_tagged_files(files: Vec<PathBuf>, skipped: &mut Vec<SkippedFile>) -> Vec<PathBuf> {
+    files
+        .into_iter()
+        .filter(|path| {
+            let Some(tags) = image_tags::read_tags(path) else {
+                return true;
+            };
+            let name = file_name(path);
+            eprintln!(
+                "[RUST] Skipping {} - already tagged as {}",
+                name, tags.category
+            );
+            skipped.push(SkippedFile {
+                name,
+                size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+                reason: format!("already organized as {}", tags.category),
+                never_upload: false,
+            });
+            false
+        })
+        .collect()
+}
+
+// Extra prompt rule asking for a rich description when the run wants one
+fn description_rule(descriptions: Option<DescriptionStorage>) -> String {
+    match descriptions {
+        Some(_) => format!("\n{}", description::PROMPT_RULE),
+        None => String::new(),
+    }
+}
+
+// What belongs in each category, how to break ties and how to name files.
+// Long enough that every built-in model caches the system prompt.
+const FILING_GUIDE: &str = include_str!("../prompts/filing_guide.md");
+
+// Instructions and taxonomy shared by every request of a run. They go out as
+// the system prompt, cached when `prompt_is_cacheable` says it is long enough.
+pub(crate) fn system_prompt(descriptions: Option<DescriptionStorage>) -> String {
+    let description_rule = description_rule(descriptions);
+    format!(
+        "You name and file screenshots so they can be sorted into folders. Output JSON only.
+
+Categories: ONE simple word from: {CATEGORY_OPTIONS}
+
+For a single screenshot, the rules are:
+- 'new_filename': snake_case, 3-4 words max, descriptive, .png
+- 'category': ONE simple word from the categories
+- 'subcategory': optional, 1-3 words max, snake_case, more specific within the category
+- 'reasoning': 2-3 words why
+- 'visible_text': the most important readable text in the image, up to 25 words
+- 'confidence': 0 to 1, how sure you are of the category{description_rule}
+
+Example: {{\"new_filename\": \"stripe_invoice.png\", \"category\": \"Finance\", \"subcategory\": \"Invoices\", \"reasoning\": \"payment receipt\", \"visible_text\": \"Stripe Invoice #1042 Amount due $49.00\", \"confidence\": 0.9}}
+
+For a burst of screenshots taken while stepping through one flow, the rules are:
+- 'session_name': snake_case, 2-3 words naming the flow, no extension
+- 'category': ONE simple word from the categories
+- 'reasoning': 2-3 words why
+- 'confidence': 0 to 1, how sure you are of the category{description_rule}
+
+Example: {{\"session_name\": \"checkout_flow\", \"category\": \"Shopping\", \"reasoning\": \"cart to payment\", \"confidence\": 0.8}}
+
+{FILING_GUIDE}"
+    )
+}
+
+const FILE_PROMPT: &str = "Analyze this screenshot.";
+const SESSION_PROMPT: &str = "These screenshots were taken seconds apart while stepping through \
+one flow (first, middle and last shot shown). Name the flow.";
+
+pub(crate) fn image_content(path: &Path) -> Result<AnthropicContent, String> {
+    let image_data = std::fs::read(path).map_err(|e| e.to_string())?;
+    Ok(base64_image(media_type(path), &image_data))
+}
+
+fn base64_image(media_type: &str, image_data: &[u8]) -> AnthropicContent {
+    AnthropicContent::Image {
+        source: AnthropicImageSource {
+            source_type: "base64".to_string(),
+            media_type: media_type.to_string(),
+            data: base64::engine::general_purpose::STANDARD.encode(image_data),
+        },
+    }
+}
+
+pub(crate) fn clean_model_json(text: &str) -> String {
+    text.trim()
+        .replace("```json", "")
+        .replace("```", "")
+        .trim()
+        .to_string()
+}
+
+// Whether `model` caches a system prompt of this length
+pub(crate) fn prompt_is_cacheable(model: &str, system: &str) -> bool {
+    usage::text_tokens(system) >= usage::min_cacheable_tokens(model)
+}
+
+// Body of a one-message request, shared by direct and batch requests. A
+// system prompt long enough to cache ends in a cache breakpoint so later
+// requests of the run read it from the prompt cache; a shorter one is sent
+// without, since the API would not cache it anyway.
+pub(crate) fn message_params(
+    model: &str,
+    system: String,
+    content: Vec<AnthropicContent>,
+    max_tokens: u32,
+) -> serde_json::Value {
+    let messages = vec![AnthropicMessage {
+        role: "user".to_string(),
+        content,
+    }];
+    let cacheable = prompt_is_cacheable(model, &system);
+    let mut system_block = serde_json::json!({ "type": "text", "text": system });
+    if cacheable {
+        system_block["cache_control"] = serde_json::json!({ "type": "ephemeral" });
+    }
+    serde_json::json!({
+        "model": model,
+        "max_tokens": max_tokens,
+        "system": [system_block],
+        "messages": messages
+    })
+}
+
+// Text of the first content block of a Messages API reply
+pub(crate) fn reply_text(message: &serde_json::Value) -> Option<&str> {
+    message["content"][0]["text"].as_str()
+}
+
+// Send one user message to `model` under the run's system prompt and return
+// the text of the first content block. Tokens are added to `usage` even when
+// the reply turns out unusable.
+pub(crate) async fn send_message(
+    run: &RunContext,
+    model: &str,
+    content: Vec<AnthropicContent>,
+    max_tokens: u32,
+    usage: &mut TokenUsage,
+) -> Result<String, String> {
+    let request_body = message_params(model, system_prompt(run.descriptions), content, max_tokens);
+
+    let res = run
+        .client
+        .post("/v1/messages", &run.api_key)
+        .json(&request_body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let status = res.status();
+    let json: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+    if let Ok(reply) = TokenUsage::deserialize(&json["usage"]) {
+        usage.add(reply);
+    }
+    match reply_text(&json) {
+        Some(text) => Ok(text.to_string()),
+        None => Err(format!("No text in API response (status {})", status)),
+    }
+}
+
+// Rough reply sizes; the JSON answers are short unless descriptions are on
+const FILE_REPLY_TOKENS: u64 = 90;
+const SESSION_REPLY_TOKENS: u64 = 50;
+const DESCRIPTION_REPLY_TOKENS: u64 = 250;
+
+// Tokens one request is expected to use, from image sizes and prompt length
+fn estimate_request(
+    images: &[PathBuf],
+    prompt: &str,
+    reply_tokens: u64,
+    descriptions: Option<DescriptionStorage>,
+) -> TokenUsage {
+    let image_tokens = images
+        .iter()
+        .filter_map(|path| image::image_dimensions(path).ok())
+        .map(|(width, height)| usage::image_tokens(width, height))
+        .sum::<u64>();
+    // The system prompt is counted as a cache write, the most one request
+    // can pay for it; `estimate_cost` spreads it over the run.
+    TokenUsage {
+        input_tokens: image_tokens + usage::text_tokens(prompt),
+        output_tokens: reply_tokens + descriptions.map_or(0, |_| DESCRIPTION_REPLY_TOKENS),
+        cache_creation_input_tokens: usage::text_tokens(&system_prompt(descriptions)),
+        ..TokenUsage::default()
+    }
+}
+
+pub(crate) fn estimate_file(path: &Path, descriptions: Option<DescriptionStorage>) -> TokenUsage {
+    estimate_request(
+        &[path.to_path_buf()],
+        FILE_PROMPT,
+        FILE_REPLY_TOKENS,
+        descriptions,
+    )
+}
+
+pub(crate) fn estimate_session(
+    session: &bursts::Session,
+    descriptions: Option<DescriptionStorage>,
+) -> TokenUsage {
+    estimate_request(
+        &session.sample_paths(),
+        SESSION_PROMPT,
+        SESSION_REPLY_TOKENS,
+        descriptions,
+    )
+}
+
+/// Predict tokens and cost of classifying a prepared scan on `model`, and
+/// the most it could cost when unsure answers go on to `escalate_to`. Only
+/// image headers are read, so this is cheap next to `prepare`.
+pub fn estimate_cost(
+    prepared: &PreparedScan,
+    descriptions: Option<DescriptionStorage>,
+    model: &str,
+    escalate_to: Option<&str>,
+    prices: &PriceTable,
+) -> CostEstimate {
+    let mut total = TokenUsage::default();
+    for path in &prepared.files {
+        total.add(estimate_file(path, descriptions));
+    }
+    for session in &prepared.sessions {
+        total.add(estimate_session(session, descriptions));
+    }
+    let requests = prepared.files.len() + prepared.sessions.len();
+    let system = system_prompt(descriptions);
+    // The first request writes the system prompt to the cache and the rest
+    // read it, or every request sends it in full when the model cannot cache
+    // it.
+    let usage_on = |model: &str| {
+        let prompt_tokens = usage::text_tokens(&system);
+        let mut usage = TokenUsage {
+            cache_creation_input_tokens: 0,
+            ..total
+        };
+        if prompt_is_cacheable(model, &system) && requests > 0 {
+            usage.cache_creation_input_tokens = prompt_tokens;
+            usage.cache_read_input_tokens = prompt_tokens * (requests as u64 - 1);
+        } else {
+            usage.input_tokens += prompt_tokens * requests as u64;
+        }
+        usage
+    };
+    let usage = usage_on(model);
+    let cost_usd = prices.cost(model, &usage);
+    CostEstimate {
+        files: prepared.actionable(),
+        requests,
+        input_tokens: usage.input_tokens
+            + usage.cache_creation_input_tokens
+            + usage.cache_read_input_tokens,
+        output_tokens: usage.output_tokens,
+        model: model.to_string(),
+        cost_usd,
+        escalation_model: escalate_to.map(str::to_string),
+        max_cost_usd: escalate_to.and_then(|escalation| {
+            Some(cost_usd? + prices.cost(escalation, &usage_on(escalation))?)
+        }),
+        prompt_cached: prompt_is_cacheable(model, &system),
+    }
+}
+
+/// Predict tokens and cost of scanning `files` the way `run_scan` would,
+/// leaving out files the never-upload rules keep on this machine. Batch
+/// runs go straight to `classify`; others may escalate to it.
+pub fn estimate_scan(
+    files: Vec<PathBuf>,
+    options: &ScanOptions,
+    privacy: &PrivacySettings,
+    models: &ModelSettings,
+    prices: &PriceTable,
+) -> CostEstimate {
+    let (files, _) = split_protected(files, privacy);
+    let prepared = prepare(files, options);
+    let (model, escalate_to) = if options.batch.unwrap_or(false) {
+        (models.classify.as_str(), None)
+    } else {
+        let first = models.first_classify_model();
+        (
+            first,
+            Some(models.classify.as_str()).filter(|classify| *classify != first),
+        )
+    };
+    estimate_cost(&prepared, options.descriptions, model, escalate_to, prices)
+}
+
+pub(crate) const FILE_MAX_TOKENS: u32 = 1024;
+pub(crate) const SESSION_MAX_TOKENS: u32 = 512;
+
+// Reply limit of a request, with room for the description when the run
+// wants one, as in `estimate_request`
+pub(crate) fn max_tokens(base: u32, descriptions: Option<DescriptionStorage>) -> u32 {
+    base + descriptions.map_or(0, |_| DESCRIPTION_REPLY_TOKENS as u32)
+}
+
+/// A classification request after the privacy check.
+pub(crate) enum Screened {
+    /// Content to send, and the kinds of what was blacked out in it.
+    Upload {
+        content: Vec<AnthropicContent>,
+        redactions: Vec<String>,
+    },
+    /// Classified on this machine instead; nothing is sent.
+    Offline(Vec<FileProposal>),
+}
+
+// One image as it may leave the machine, or the text it was withheld with
+enum ScreenedImage {
+    Clean(AnthropicContent),
+    Redacted(AnthropicContent, Vec<String>),
+    Withheld { text: String, reasoning: String },
+}
+
+// OCR and redaction are slow and blocking; call this off the async threads.
+fn screen_image(path: &Path, settings: &PrivacySettings) -> Result<ScreenedImage, String> {
+    if settings.mode == PrivacyMode::Off {
+        return Ok(ScreenedImage::Clean(image_content(path)?));
+    }
+    let inspection = match privacy::inspect(path, settings) {
+        Ok(inspection) => inspection,
+        // An image that could not be checked is not sent.
+        Err(e) => {
+            eprintln!(
+                "[RUST] ⚠️ Privacy check failed for {}: {}",
+                file_name(path),
+                e
+            );
+            return Ok(ScreenedImage::Withheld {
+                text: String::new(),
+                reasoning: "privacy check failed".to_string(),
+            });
+        }
+    };
+    if inspection.findings.is_empty() {
+        return Ok(ScreenedImage::Clean(image_content(path)?));
+    }
+    let found = privacy::labels(&inspection.findings);
+    eprintln!("[RUST] ⚠️ {} shows {}", file_name(path), found.join(", "));
+    match settings.mode {
+        PrivacyMode::Redact => Ok(ScreenedImage::Redacted(
+            base64_image("image/png", &privacy::redact(path, &inspection.findings)?),
+            found,
+        )),
+        _ => Ok(ScreenedImage::Withheld {
+            text: inspection.text,
+            reasoning: format!("kept local: {}", found.join(", ")),
+        }),
+    }
+}
+
+/// The image at `path` as it may be sent on its own, e.g. to refine its
+/// category. Never-upload rules and the privacy mode apply as in a scan;
+/// a file a scan would keep local is refused. Blocking, like OCR.
+#[cfg(feature = "gui")]
+pub(crate) fn upload_image(
+    path: &Path,
+    settings: &PrivacySettings,
+) -> Result<AnthropicContent, String> {
+    if let Some(protected) = privacy::protected(path, settings) {
+        return Err(format!("{} is {}", file_name(path), protected.reason));
+    }
+    match screen_image(path, settings)? {
+        ScreenedImage::Clean(image) | ScreenedImage::Redacted(image, _) => Ok(image),
+        ScreenedImage::Withheld { reasoning, .. } => {
+            Err(format!("{} is {}", file_name(path), reasoning))
+        }
+    }
+}
+
+/// Split off the files a never-upload rule keeps from the API.
+pub fn split_protected(
+    files: Vec<PathBuf>,
+    settings: &PrivacySettings,
+) -> (Vec<PathBuf>, Vec<(PathBuf, privacy::Protected)>) {
+    let mut protected = Vec::new();
+    let files = files
+        .into_iter()
+        .filter(|path| match privacy::protected(path, settings) {
+            Some(rule) => {
+                protected.push((path.clone(), rule));
+                false
+            }
+            None => true,
+        })
+        .collect();
+    (files, protected)
+}
+
+pub(crate) fn screen_file(path: &Path, run: &RunContext) -> Result<Screened, String> {
+    let (image, redactions) = match screen_image(path, &run.privacy)? {
+        ScreenedImage::Clean(image) => (image, Vec::new()),
+        ScreenedImage::Redacted(image, found) => (image, found),
+        ScreenedImage::Withheld { text, reasoning } => {
+            return Ok(Screened::Offline(vec![offline::file_proposal(
+                path, run, &text, None, &reasoning,
+            )]))
+        }
+    };
+    Ok(Screened::Upload {
+        content: vec![
+            image,
+            AnthropicContent::Text {
+                text: FILE_PROMPT.to_string(),
+            },
+        ],
+        redactions,
+    })
+}
+
+// Turn `model`'s answer for one screenshot into a proposal
+pub(crate) fn file_proposal(
+    path: &Path,
+    run: &RunContext,
+    model: &str,
+    text: &str,
+) -> Result<FileProposal, String> {
+    let filename = file_name(path);
+
+    #[derive(Deserialize)]
+    struct ClaudeResp {
+        new_filename: String,
+        category: String,
+        subcategory: Option<String>,
+        reasoning: Option<String>,
+        visible_text: Option<String>,
+        confidence: Option<f32>,
+        description: Option<RichDescription>,
+    }
+
+    let parsed = serde_json::from_str::<ClaudeResp>(&clean_model_json(text))
+        .map_err(|e| format!("JSON parse error: {}", e))?;
+    eprintln!(
+        "[RUST] ✅ {} -> {} ({})",
+        filename, parsed.new_filename, parsed.category
+    );
+
+    let proposed_category = match parsed.subcategory.as_deref().map(str::trim) {
+        Some(subcategory) if !subcategory.is_empty() => {
+            format!("{}/{}", parsed.category, subcategory)
+        }
+        _ => parsed.category.clone(),
+    };
+    let captured_at = capture_date::capture_date(path).map(|c| c.captured_at);
+
+    Ok(FileProposal {
+        id: filename.clone(),
+        original_path: path.to_string_lossy().to_string(),
+        original_name: filename,
+        proposed_name: with_image_extension(&parsed.new_filename, path),
+        proposed_category: run.layout.apply(&proposed_category, captured_at),
+        reasoning: parsed.reasoning.unwrap_or_default(),
+        session_id: None,
+        model: model.to_string(),
+        visible_text: parsed.visible_text.unwrap_or_default(),
+        confidence: parsed.confidence,
+        description: parsed.description,
+        redactions: Vec::new(),
+    })
+}
+
+// A burst stays local as a whole when any of its sampled shots must
+pub(crate) fn screen_session(
+    session: &bursts::Session,
+    run: &RunContext,
+) -> Result<Screened, String> {
+    let mut content = Vec::new();
+    let mut redactions = Vec::new();
+    for path in session.sample_paths() {
+        match screen_image(&path, &run.privacy)? {
+            ScreenedImage::Clean(image) => content.push(image),
+            ScreenedImage::Redacted(image, found) => {
+                content.push(image);
+                redactions.extend(found);
+            }
+            ScreenedImage::Withheld { text, reasoning } => {
+                return Ok(Screened::Offline(offline::session_proposals(
+                    session, run, &text, &reasoning,
+                )))
+            }
+        }
+    }
+    redactions.sort();
+    redactions.dedup();
+    content.push(AnthropicContent::Text {
+        text: SESSION_PROMPT.to_string(),
+    });
+    Ok(Screened::Upload {
+        content,
+        redactions,
+    })
+}
+
+// Turn `model`'s answer for a burst into one proposal per member, each with
+// a shared subfolder and a sequential name
+pub(crate) fn session_proposals(
+    session: &bursts::Session,
+    run: &RunContext,
+    model: &str,
+    text: &str,
+) -> Result<Vec<FileProposal>, String> {
+    #[derive(Deserialize)]
+    struct SessionResp {
+        session_name: String,
+        category: String,
+        reasoning: Option<String>,
+        confidence: Option<f32>,
+        description: Option<RichDescription>,
+    }
+
+    let parsed =
+        serde_json::from_str::<SessionResp>(&clean_model_json(text)).map_err(|e| e.to_string())?;
+
+    let session_name = bursts::slugify(&parsed.session_name);
+    let proposed_category = run.layout.apply(
+        &format!("{}/{}", parsed.category.trim(), session_name),
+        Some(session.started_at),
+    );
+    eprintln!("[RUST] ✅ Session {} -> {}", session.id, proposed_category);
+
+    let total = session.paths.len();
+    Ok(session
+        .paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let filename = file_name(path);
+            FileProposal {
+                id: filename.clone(),
+                original_path: path.to_string_lossy().to_string(),
+                original_name: filename,
+                proposed_name: with_image_extension(
+                    &bursts::sequence_name(&session_name, index, total),
+                    path,
+                ),
+                proposed_category: proposed_category.clone(),
+                reasoning: parsed.reasoning.clone().unwrap_or_default(),
+                session_id: Some(session.id.clone()),
+                model: model.to_string(),
+                visible_text: String::new(),
+                confidence: parsed.confidence,
+                description: parsed.description.clone(),
+                redactions: Vec::new(),
+            }
+        })
+        .collect())
+}
+
+// Records proposals, failures and token usage in the run journal before the
+// sink sees them
+#[derive(Clone)]
+pub(crate) struct Reporter {
+    run_id: String,
+    pub(crate) store: Arc<SessionStore>,
+    pub(crate) sink: Arc<dyn EventSink>,
+    total: Arc<Mutex<RunCost>>,
+    budget: Option<Arc<Budget>>,
+}
+
+// Spend cap of a run. A request may start only if the spend so far plus the
+// estimates of requests in flight leaves room for its own estimate; the first
+// request that does not fit closes the budget for the rest of the run.
+struct Budget {
+    limit_usd: f64,
+    state: Mutex<BudgetState>,
+}
+
+#[derive(Default)]
+struct BudgetState {
+    reserved_usd: f64,
+    closed: bool,
+}
+
+impl Reporter {
+    // `total` carries over the spend of a resumed run
+    pub(crate) fn new(
+        run_id: &str,
+        store: Arc<SessionStore>,
+        sink: Arc<dyn EventSink>,
+        total: RunCost,
+        budget_usd: Option<f64>,
+    ) -> Reporter {
+        Reporter {
+            run_id: run_id.to_string(),
+            store,
+            sink,
+            total: Arc::new(Mutex::new(total)),
+            budget: budget_usd.map(|limit_usd| {
+                Arc::new(Budget {
+                    limit_usd,
+                    state: Mutex::new(BudgetState::default()),
+                })
+            }),
+        }
+    }
+
+    pub(crate) fn record(&self, event: SessionEvent) {
+        if let Err(e) = self.store.append(&self.run_id, event) {
+            eprintln!("[RUST] ⚠️ Failed to record session event: {}", e);
+        }
+    }
+
+    pub(crate) fn proposed(&self, proposal: FileProposal) {
+        self.record(SessionEvent::Proposed {
+            proposal: Box::new(proposal.clone()),
+        });
+        self.sink.emit(PipelineEvent::Proposed {
+            proposal: Box::new(proposal),
+        });
+    }
+
+    pub(crate) fn failed(&self, name: String, error: String) {
+        self.record(SessionEvent::Failed {
+            name: name.clone(),
+            error: Some(error.clone()),
+        });
+        self.sink.emit(PipelineEvent::Failed { name, error });
+    }
+
+    pub(crate) fn skipped(&self, file: SkippedFile) {
+        self.record(SessionEvent::Skipped { file: file.clone() });
+        self.sink.emit(PipelineEvent::Skipped { file });
+    }
+
+    // Requests that never reached the model used nothing and are not recorded.
+    // `price_factor` scales list prices, e.g. for the batch discount.
+    pub(crate) fn usage(
+        &self,
+        files: Vec<String>,
+        run: &RunContext,
+        model: &str,
+        usage: TokenUsage,
+        price_factor: f64,
+    ) {
+        if usage.is_empty() {
+            return;
+        }
+        let request = RequestUsage {
+            files,
+            model: model.to_string(),
+            cost_usd: run
+                .prices
+                .cost(model, &usage)
+                .map(|cost| cost * price_factor),
+            usage,
+        };
+        self.record(SessionEvent::Usage {
+            request: request.clone(),
+        });
+        let total = {
+            let mut total = self.total.lock().unwrap();
+            total.add(&request);
+            *total
+        };
+        self.sink.emit(PipelineEvent::Usage { request, total });
+    }
+
+    // Reserve the estimated cost of a request in the budget. None once the
+    // budget is used up; the estimate is only computed when there is one.
+    pub(crate) fn reserve(
+        &self,
+        run: &RunContext,
+        model: &str,
+        price_factor: f64,
+        estimate: impl FnOnce() -> TokenUsage,
+    ) -> Option<f64> {
+        let Some(budget) = &self.budget else {
+            return Some(0.0);
+        };
+        let cost = run
+            .prices
+            .cost(model, &estimate())
+            .map_or(0.0, |cost| cost * price_factor);
+        let spent = self.total.lock().unwrap().cost_usd;
+        let mut state = budget.state.lock().unwrap();
+        if state.closed || spent + state.reserved_usd + cost > budget.limit_usd {
+            state.closed = true;
+            return None;
+        }
+        state.reserved_usd += cost;
+        Some(cost)
+    }
+
+    // Called after the request's actual usage was reported
+    pub(crate) fn release(&self, reserved_usd: f64) {
+        if let Some(budget) = &self.budget {
+            budget.state.lock().unwrap().reserved_usd -= reserved_usd;
+        }
+    }
+
+    pub(crate) fn deferred(&self, name: String) {
+        let limit_usd = self.budget.as_ref().map_or(0.0, |budget| budget.limit_usd);
+        self.record(SessionEvent::Deferred { name: name.clone() });
+        self.sink.emit(PipelineEvent::Deferred {
+            name,
+            reason: format!("budget of {} reached", usage::format_cost(limit_usd)),
+        });
+    }
+}
+
+/// Prepare and classify the files of a started run, reporting every outcome
+/// to `sink`. `jobs` bounds concurrent API requests; `None` sends them all
+/// at once. With `budget_usd` set, files that no longer fit are deferred;
+/// with `batch` set, requests go out as Message Batches instead.
+pub async fn run_scan(
+    scan: ScanResult,
+    options: ScanOptions,
+    mut run: RunContext,
+    store: Arc<SessionStore>,
+    sink: Arc<dyn EventSink>,
+    jobs: Option<usize>,
+) -> Result<(), String> {
+    let reporter = Reporter::new(
+        &run.run_id,
+        store,
+        sink,
+        RunCost::default(),
+        options.budget_usd,
+    );
+    if let Some(mode) = options.privacy {
+        run.privacy.mode = mode;
+    }
+    let batch_mode = options.batch.unwrap_or(false);
+    let first_model = if batch_mode {
+        run.models.classify.clone()
+    } else {
+        run.models.first_classify_model().to_string()
+    };
+    if reporter.budget.is_some() && run.prices.price(&first_model).is_none() {
+        eprintln!(
+            "[RUST] ⚠️ No price for {}, the budget cannot be enforced",
+            first_model
+        );
+    }
+    for name in scan.failed {
+        reporter.failed(name, "could not read file metadata".to_string());
+    }
+    for file in scan.skipped {
+        reporter.skipped(file);
+    }
+
+    // Never-upload rules come first so protected files are not grouped with
+    // others. App rules OCR the files, so this runs off the async threads.
+    let (files, protected) = {
+        let (files, privacy) = (scan.files, run.privacy.clone());
+        tokio::task::spawn_blocking(move || split_protected(files, &privacy))
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    for (path, protected) in &protected {
+        eprintln!(
+            "[RUST] Keeping {} local: {}",
+            file_name(path),
+            protected.reason
+        );
+        reporter.skipped(SkippedFile {
+            name: file_name(path),
+            size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            reason: protected.reason.clone(),
+            never_upload: true,
+        });
+    }
+
+    // Fingerprinting decodes every image, so it runs off the async threads.
+    let prepared = tokio::task::spawn_blocking(move || prepare(files, &options))
+        .await
+        .map_err(|e| e.to_string())?;
+    let actionable = prepared.actionable() + protected.len();
+    for file in prepared.skipped {
+        reporter.skipped(file);
+    }
+    eprintln!(
+        "[RUST] Found {} actionable files, {} burst session(s)",
+        actionable,
+        prepared.sessions.len()
+    );
+    reporter.sink.emit(PipelineEvent::Summary {
+        actionable,
+        duplicate_groups: prepared.duplicate_groups,
+    });
+    for (path, protected) in protected {
+        reporter.proposed(offline::file_proposal(
+            &path,
+            &run,
+            &protected.text,
+            protected.category.as_deref(),
+            &protected.reason,
+        ));
+    }
+
+    if batch_mode {
+        batch::run(prepared.files, prepared.sessions, &run, &reporter).await?;
+        reporter.record(SessionEvent::Finished {
+            finished_at: chrono::Local::now().to_rfc3339(),
+        });
+        return Ok(());
+    }
+
+    let limit = Arc::new(Semaphore::new(
+        jobs.unwrap_or(Semaphore::MAX_PERMITS).max(1),
+    ));
+    let mut tasks = JoinSet::new();
+    for session in prepared.sessions {
+        let (limit, run, reporter) = (limit.clone(), run.clone(), reporter.clone());
+        tasks.spawn(async move {
+            let _permit = limit.acquire_owned().await;
+            match reporter.reserve(&run, run.models.first_classify_model(), 1.0, || {
+                estimate_session(&session, run.descriptions)
+            }) {
+                Some(reserved) => {
+                    process_session(&session, &run, &reporter).await;
+                    reporter.release(reserved);
+                }
+                None => {
+                    for path in &session.paths {
+                        reporter.deferred(file_name(path));
+                    }
+                }
+            }
+        });
+    }
+    for path in prepared.files {
+        let (limit, run, reporter) = (limit.clone(), run.clone(), reporter.clone());
+        tasks.spawn(async move {
+            let _permit = limit.acquire_owned().await;
+            match reporter.reserve(&run, run.models.first_classify_model(), 1.0, || {
+                estimate_file(&path, run.descriptions)
+            }) {
+                Some(reserved) => {
+                    process_file(&path, &run, &reporter).await;
+                    reporter.release(reserved);
+                }
+                None => reporter.deferred(file_name(&path)),
+            }
+        });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        if let Err(e) = joined {
+            eprintln!("[RUST] ❌ Classification task failed: {}", e);
+        }
+    }
+    reporter.record(SessionEvent::Finished {
+        finished_at: chrono::Local::now().to_rfc3339(),
+    });
+    Ok(())
+}
+
+async fn process_file(path: &Path, run: &RunContext, reporter: &Reporter) {
+    let filename = file_name(path);
+    reporter.sink.emit(PipelineEvent::Processing {
+        name: filename.clone(),
+    });
+    let screened = {
+        let (path, run) = (path.to_path_buf(), run.clone());
+        tokio::task::spawn_blocking(move || screen_file(&path, &run))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|screened| screened)
+    };
+    let result = match screened {
+        Ok(Screened::Upload {
+            content,
+            redactions,
+        }) => classify(
+            run,
+            reporter,
+            vec![filename.clone()],
+            content,
+            max_tokens(FILE_MAX_TOKENS, run.descriptions),
+            || estimate_file(path, run.descriptions),
+            |model, text| {
+                let mut proposal = file_proposal(path, run, model, text)?;
+                proposal.redactions = redactions.clone();
+                let confidence = proposal.confidence;
+                Ok((proposal, confidence))
+            },
+        )
+        .await
+        .map(|proposal| proposal.map(|proposal| vec![proposal])),
+        Ok(Screened::Offline(proposals)) => Ok(Some(proposals)),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(Some(proposals)) => {
+            for proposal in proposals {
+                reporter.proposed(proposal);
+            }
+        }
+        Ok(None) => reporter.deferred(filename),
+        Err(e) => {
+            eprintln!("[RUST] ❌ Classification failed for {}: {}", filename, e);
+            reporter.failed(filename, e);
+        }
+    }
+}
+
+async fn process_session(session: &bursts::Session, run: &RunContext, reporter: &Reporter) {
+    let names = session
+        .paths
+        .iter()
+        .map(|path| file_name(path))
+        .collect::<Vec<String>>();
+    for name in &names {
+        reporter
+            .sink
+            .emit(PipelineEvent::Processing { name: name.clone() });
+    }
+    let screened = {
+        let (session, run) = (session.clone(), run.clone());
+        tokio::task::spawn_blocking(move || screen_session(&session, &run))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|screened| screened)
+    };
+    let result = match screened {
+        Ok(Screened::Upload {
+            content,
+            redactions,
+        }) => {
+            classify(
+                run,
+                reporter,
+                names,
+                content,
+                max_tokens(SESSION_MAX_TOKENS, run.descriptions),
+                || estimate_session(session, run.descriptions),
+                |model, text| {
+                    let mut proposals = session_proposals(session, run, model, text)?;
+                    for proposal in &mut proposals {
+                        proposal.redactions = redactions.clone();
+                    }
+                    let confidence = proposals.first().and_then(|proposal| proposal.confidence);
+                    Ok((proposals, confidence))
+                },
+            )
+            .await
+        }
+        Ok(Screened::Offline(proposals)) => Ok(Some(proposals)),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(Some(proposals)) => {
+            for proposal in proposals {
+                reporter.proposed(proposal);
+            }
+        }
+        Ok(None) => {
+            for path in &session.paths {
+                reporter.deferred(file_name(path));
+            }
+        }
+        Err(e) => {
+            eprintln!("[RUST] ❌ Session {} failed: {}", session.id, e);
+            for path in &session.paths {
+                reporter.failed(file_name(path), e.clone());
+            }
+        }
+    }
+}
+
+// Send a classification request to the run's first model and, when its
+// answer does not parse or is unsure, once more to the `classify` model.
+// `parse` turns a reply into the result and its confidence. The second
+// request is reserved in the budget like the first, with `estimate`; when it
+// no longer fits, an unsure answer is kept and an unparsed one is deferred
+// (None).
+async fn classify<T>(
+    run: &RunContext,
+    reporter: &Reporter,
+    files: Vec<String>,
+    content: Vec<AnthropicContent>,
+    max_tokens: u32,
+    estimate: impl FnOnce() -> TokenUsage,
+    parse: impl Fn(&str, &str) -> Result<(T, Option<f32>), String>,
+) -> Result<Option<T>, String> {
+    let first = run.models.first_classify_model();
+    eprintln!("[RUST] Sending {} to {}...", files.join(", "), first);
+    // Kept for a second request only when one can happen
+    let retry_content = (first != run.models.classify).then(|| content.clone());
+    let mut usage = TokenUsage::default();
+    let reply = send_message(run, first, content, max_tokens, &mut usage).await;
+    reporter.usage(files.clone(), run, first, usage, 1.0);
+    let parsed = parse(first, &reply?);
+
+    let (unparsed, confidence) = match &parsed {
+        Ok((_, confidence)) => (false, *confidence),
+        Err(_) => (true, None),
+    };
+    let content = match retry_content {
+        Some(content) if run.models.should_escalate(first, unparsed, confidence) => content,
+        _ => return parsed.map(|(result, _)| Some(result)),
+    };
+    let model = &run.models.classify;
+    // The first request's reservation is still held, so this errs on the
+    // safe side.
+    let Some(reserved) = reporter.reserve(run, model, 1.0, estimate) else {
+        eprintln!(
+            "[RUST] ⚠️ Not escalating {} to {}: budget reached",
+            files.join(", "),
+            model
+        );
+        return match parsed {
+            Ok((result, _)) => Ok(Some(result)),
+            Err(_) => Ok(None),
+        };
+    };
+    eprintln!(
+        "[RUST] ⚠️ Escalating {} to {} ({})",
+        files.join(", "),
+        model,
+        match &parsed {
+            Err(e) => e.clone(),
+            Ok(_) => format!("confidence {:.2}", confidence.unwrap_or_default()),
+        }
+    );
+    let mut usage = TokenUsage::default();
+    let reply = send_message(run, model, content, max_tokens, &mut usage).await;
+    reporter.usage(files, run, model, usage, 1.0);
+    reporter.release(reserved);
+    parse(model, &reply?).map(|(result, _)| Some(result))
+}
+
+fn sanitize_segment(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !matches!(c, '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Where a proposal would be moved: `<source folder>/<category>/<name>`,
+/// keeping the image's extension and sanitized the same way as in the GUI.
+pub fn destination_path(proposal: &FileProposal) -> PathBuf {
+    let original = Path::new(&proposal.original_path);
+    let parent = original.parent().unwrap_or(Path::new(""));
+
+    let category = proposal
+        .proposed_category
+        .split('/')
+        .map(sanitize_segment)
+        .filter(|part| !part.is_empty() && part != "." && part != "..")
+        .collect::<Vec<String>>()
+        .join("/");
+    let category = if category.is_empty() {
+        "Other".to_string()
+    } else {
+        category
+    };
+
+    let mut name = sanitize_segment(&proposal.proposed_name);
+    if name.is_empty() || name == "." || name == ".." {
+        name = "untitled".to_string();
+    }
+
+    parent
+        .join(category)
+        .join(with_image_extension(&name, original))
+}
+
+pub fn move_file(
+    original_path: String,
+    new_path: String,
+    root_path: Option<String>,
+    overwrite: Option<bool>,
+) -> Result<MoveResult, String> {
+    let src = Path::new(&original_path);
+    let dst = Path::new(&new_path);
+    let allow_overwrite = overwrite.unwrap_or(false);
+
+    if !src.exists() {
+        return Err("Source file no longer exists".to_string());
+    }
+
+    if contains_parent_dir(dst) {
+        return Err("Destination contains invalid path segments".to_string());
+    }
+
+    if let Some(root) = root_path.as_deref().map(Path::new) {
+        if !src.starts_with(root) || !dst.starts_with(root) {
+            return Err("Destination must stay within the scan folder".to_string());
+        }
+    } else if let Some(parent) = src.parent() {
+        if !dst.starts_with(parent) {
+            return Err("Destination must stay within the source folder".to_string());
+        }
+    } else {
+        return Err("Source path has no parent folder".to_string());
+    }
+
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    if dst.exists() {
+        if allow_overwrite {
+            if dst.is_file() {
+                std::fs::remove_file(dst).map_err(|e| e.to_string())?;
+            } else {
+                return Err("Destination exists and is not a file".to_string());
+            }
+        } else {
+            return Err("Destination already exists".to_string());
+        }
+    }
+
+    std::fs::rename(src, dst).map_err(|e| e.to_string())?;
+    Ok(MoveResult {
+        original_path,
+        final_path: new_path,
+        renamed: false,
+    })
+}
+
+/// Stores shared by every run: session journals, move history, descriptions
+/// and the search index. Embeddings are left to the caller, since updating
+/// them may load a model.
+pub struct Library {
+    pub sessions: Arc<SessionStore>,
+    pub history: HistoryLog,
+    pub descriptions: DescriptionStore,
+    pub prices: Arc<PriceTable>,
+    models_path: PathBuf,
+    network_path: PathBuf,
+    privacy_path: PathBuf,
+    client: RwLock<ApiClient>,
+    search: Option<SearchIndex>,
+}
+
+impl Library {
+    pub fn open(data_dir: &Path) -> Library {
+        // Another process may hold the index writer; work without search then.
+        let search = match SearchIndex::open(&data_dir.join("search_index")) {
+            Ok(index) => Some(index),
+            Err(e) => {
+                eprintln!("[RUST] ⚠️ Search index unavailable: {}", e);
+                None
+            }
+        };
+        let library = Library {
+            search,
+            ..Library::without_search(data_dir)
+        };
+        library.reindex_if_empty();
+        library
+    }
+
+    // A new or rebuilt search index is filled from the applied proposals of
+    // every stored run.
+    fn reindex_if_empty(&self) {
+        let Some(index) = self.search.as_ref().filter(|index| index.is_empty()) else {
+            return;
+        };
+        let runs = match self.sessions.list() {
+            Ok(runs) => runs,
+            Err(e) => {
+                eprintln!("[RUST] ⚠️ Could not list runs to reindex: {}", e);
+                return;
+            }
+        };
+        let mut indexed = 0;
+        for summary in runs.iter().filter(|summary| summary.applied > 0) {
+            let Ok(run) = self.sessions.load(&summary.run_id) else {
+                continue;
+            };
+            for entry in run.proposals {
+                let Some(final_path) = entry.final_path.filter(|path| {
+                    entry.status == ApplyStatus::Applied && Path::new(path).exists()
+                }) else {
+                    continue;
+                };
+                let document =
+                    search_document(&entry.proposal, &final_path, Some(run.run_id.clone()));
+                match index.upsert(&document) {
+                    Ok(()) => indexed += 1,
+                    Err(e) => eprintln!("[RUST] ⚠️ Failed to reindex {}: {}", final_path, e),
+                }
+            }
+        }
+        if indexed > 0 {
+            eprintln!("[RUST] Reindexed {} organized screenshot(s)", indexed);
+            self.commit_search();
+        }
+    }
+
+    /// Make moves recorded so far searchable right away, e.g. at the end
+    /// of a batch of moves.
+    pub fn commit_search(&self) {
+        if let Some(Err(e)) = self.search.as_ref().map(SearchIndex::commit) {
+            eprintln!("[RUST] ⚠️ Failed to commit search index: {}", e);
+        }
+    }
+
+    /// For readers running next to the app: search never takes the index
+    /// writer, so moves made through this library are not indexed.
+    pub fn open_read_only(data_dir: &Path) -> Library {
+        let search = match SearchIndex::open_read_only(&data_dir.join("search_index")) {
+            Ok(index) => Some(index),
+            Err(e) => {
+                eprintln!("[RUST] ⚠️ Search index unavailable: {}", e);
+                None
+            }
+        };
+        Library {
+            search,
+            ..Library::without_search(data_dir)
+        }
+    }
+
+    fn without_search(data_dir: &Path) -> Library {
+        Library {
+            sessions: Arc::new(SessionStore::new(data_dir.join("sessions"))),
+            history: HistoryLog::new(data_dir.join("history.jsonl")),
+            descriptions: DescriptionStore::new(data_dir.join("descriptions.jsonl")),
+            prices: Arc::new(PriceTable::load(&data_dir.join(PRICES_FILE))),
+            models_path: data_dir.join(MODELS_FILE),
+            network_path: data_dir.join(NETWORK_FILE),
+            privacy_path: data_dir.join(PRIVACY_FILE),
+            client: RwLock::new(ApiClient::load(&data_dir.join(NETWORK_FILE))),
+            search: None,
+        }
+    }
+
+    /// Current model settings; re-read so changes from another process
+    /// apply to the next run.
+    pub fn models(&self) -> ModelSettings {
+        ModelSettings::load(&self.models_path)
+    }
+
+    pub fn set_models(&self, models: &ModelSettings) -> Result<(), String> {
+        models.save(&self.models_path)
+    }
+
+    /// Shared client for API requests.
+    pub fn client(&self) -> ApiClient {
+        self.client.read().unwrap().clone()
+    }
+
+    pub fn network(&self) -> NetworkConfig {
+        NetworkConfig::load(&self.network_path)
+    }
+
+    /// Save network settings and rebuild the client. Settings that cannot be
+    /// applied, e.g. an unreadable certificate, are refused.
+    pub fn set_network(&self, config: &NetworkConfig) -> Result<(), String> {
+        let client = ApiClient::new(config)?;
+        config.save(&self.network_path)?;
+        *self.client.write().unwrap() = client;
+        Ok(())
+    }
+
+    /// Current privacy settings, re-read like the model settings.
+    pub fn privacy(&self) -> PrivacySettings {
+        PrivacySettings::load(&self.privacy_path)
+    }
+
+    pub fn set_privacy(&self, settings: &PrivacySettings) -> Result<(), String> {
+        settings.save(&self.privacy_path)
+    }
+
+    pub fn search_index(&self) -> Result<&SearchIndex, String> {
+        self.search
+            .as_ref()
+            .ok_or_else(|| "Search index unavailable".to_string())
+    }
+
+    /// Move a file and record it in the run journal, history, tags,
+    /// descriptions and search index. Moving a file back to its original
+    /// location is an undo.
+    pub fn execute_move(
+        &self,
+        request: MoveRequest,
+    ) -> (Result<MoveResult, String>, Option<EmbeddingUpdate>) {
+        let MoveRequest {
+            original_path,
+            new_path,
+            root_path,
+            overwrite,
+            run_id,
+            proposal_id,
+        } = request;
+        let result = move_file(original_path.clone(), new_path, root_path, Some(overwrite));
+
+        let proposal = match (&run_id, &proposal_id) {
+            (Some(run_id), Some(id)) => self.sessions.proposal(run_id, id),
+            _ => None,
+        };
+        let options = run_id
+            .as_deref()
+            .and_then(|run_id| self.sessions.options(run_id))
+            .unwrap_or_default();
+
+        if let (Some(run_id), Some(id)) = (&run_id, proposal_id) {
+            let event = match &result {
+                Ok(moved) => SessionEvent::Moved {
+                    id,
+                    from: original_path.clone(),
+                    to: moved.final_path.clone(),
+                },
+                Err(error) => SessionEvent::MoveFailed {
+                    id,
+                    error: error.clone(),
+                },
+            };
+            if let Err(e) = self.sessions.append(run_id, event) {
+                eprintln!("[RUST] ⚠️ Failed to record move for {}: {}", run_id, e);
+            }
+        }
+
+        let action = match &proposal {
+            Some(p)
+                if result
+                    .as_ref()
+                    .is_ok_and(|m| m.final_path == p.original_path) =>
+            {
+                HistoryAction::Undo
+            }
+            _ => HistoryAction::Apply,
+        };
+        let entry = HistoryEntry {
+            run_id,
+            timestamp: chrono::Local::now().to_rfc3339(),
+            action,
+            original_path,
+            final_path: result.as_ref().ok().map(|m| m.final_path.clone()),
+            category: proposal.as_ref().map(|p| p.proposed_category.clone()),
+            model: proposal
+                .as_ref()
+                .map(|p| p.model.clone())
+                .filter(|m| !m.is_empty()),
+            reasoning: proposal.as_ref().map(|p| p.reasoning.clone()),
+            error: result.as_ref().err().cloned(),
+        };
+        if let Err(e) = self.history.record(&entry) {
+            eprintln!("[RUST] ⚠️ Failed to write history entry: {}", e);
+        }
+
+        let (Ok(moved), Some(proposal)) = (&result, &proposal) else {
+            return (result, None);
+        };
+        let description_text = proposal
+            .description
+            .as_ref()
+            .map(|d| d.text())
+            .unwrap_or_default();
+
+        if options.write_tags {
+            let target = Path::new(&moved.final_path);
+            let tagged = match action {
+                HistoryAction::Apply => image_tags::write_tags(
+                    target,
+                    &image_tags::ImageTags::from_folder(
+                        &proposal.proposed_category,
+                        proposal.description.as_ref().map(|d| d.summary.clone()),
+                        proposal.original_name.clone(),
+                    ),
+                ),
+                HistoryAction::Undo => image_tags::remove_tags(target),
+            };
+            if let Err(e) = tagged {
+                eprintln!("[RUST] ⚠️ Failed to update image tags: {}", e);
+            }
+        }
+
+        if let (Some(storage), Some(description)) = (options.descriptions, &proposal.description) {
+            let written = match action {
+                HistoryAction::Apply => self.descriptions.write(
+                    storage,
+                    &DescriptionRecord {
+                        path: moved.final_path.clone(),
+                        original_name: proposal.original_name.clone(),
+                        category: proposal.proposed_category.clone(),
+                        reasoning: proposal.reasoning.clone(),
+                        model: proposal.model.clone(),
+                        run_id: entry.run_id.clone(),
+                        organized_at: entry.timestamp.clone(),
+                        description: description.clone(),
+                    },
+                ),
+                HistoryAction::Undo => self.descriptions.remove(storage, &moved.original_path),
+            };
+            if let Err(e) = written {
+                eprintln!("[RUST] ⚠️ Failed to update description: {}", e);
+            }
+        }
+
+        if let Some(index) = &self.search {
+            let indexed = match action {
+                HistoryAction::Apply => index.upsert(&search_document(
+                    proposal,
+                    &moved.final_path,
+                    entry.run_id.clone(),
+                )),
+                HistoryAction::Undo => index.remove(&moved.original_path),
+            };
+            if let Err(e) = indexed {
+                eprintln!("[RUST] ⚠️ Failed to update search index: {}", e);
+            }
+        }
+
+        let update = match action {
+            HistoryAction::Apply => EmbeddingUpdate::Upsert(EmbeddingInput {
+                path: moved.final_path.clone(),
+                original_name: proposal.original_name.clone(),
+                category: proposal.proposed_category.clone(),
+                text: embeddings::document_text(
+                    &proposal.proposed_category,
+                    &proposal.reasoning,
+                    &proposal.visible_text,
+                    &description_text,
+                ),
+            }),
+            HistoryAction::Undo => EmbeddingUpdate::Remove(moved.original_path.clone()),
+        };
+        (result, Some(update))
+    }
+
+    /// Move every applied file of a run back to where it started.
+    pub fn undo_run(&self, run_id: &str) -> Result<Vec<MoveOutcome>, String> {
+        let run = self.sessions.load(run_id)?;
+        let mut outcomes = Vec::new();
+        for entry in run.proposals {
+            let Some(final_path) = entry
+                .final_path
+                .filter(|_| entry.status == ApplyStatus::Applied)
+            else {
+                continue;
+            };
+            let id = entry.proposal.id.clone();
+            let (result, _) = self.execute_move(MoveRequest {
+                original_path: final_path.clone(),
+                new_path: entry.proposal.original_path,
+                root_path: Some(run.root_path.clone()),
+                overwrite: false,
+                run_id: Some(run_id.to_string()),
+                proposal_id: Some(id.clone()),
+            });
+            outcomes.push(MoveOutcome::new(id, final_path, result));
+        }
+        self.commit_search();
+        Ok(outcomes)
+    }
+}
+
+// What the search index holds for a proposal filed at `final_path`
+fn search_document(
+    proposal: &FileProposal,
+    final_path: &str,
+    run_id: Option<String>,
+) -> SearchDocument {
+    SearchDocument {
+        path: final_path.to_string(),
+        original_name: proposal.original_name.clone(),
+        category: proposal.proposed_category.clone(),
+        reasoning: proposal.reasoning.clone(),
+        visible_text: proposal.visible_text.clone(),
+        description: proposal
+            .description
+            .as_ref()
+            .map(|d| d.text())
+            .unwrap_or_default(),
+        captured_at: capture_date::parse_filename(&proposal.original_name)
+            .or_else(|| capture_date::capture_date(Path::new(final_path)))
+            .map(|c| c.captured_at),
+        run_id,
+    }
+}
+
+impl MoveOutcome {
+    pub fn new(id: String, original_path: String, result: Result<MoveResult, String>) -> Self {
+        match result {
+            Ok(moved) => MoveOutcome {
+                id,
+                original_path,
+                final_path: Some(moved.final_path),
+                error: None,
+            },
+            Err(error) => MoveOutcome {
+                id,
+                original_path,
+                final_path: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DEFAULT_MODEL;
+    use crate::search::SearchFilters;
+    use crate::session_store::RunOptions;
+
+    #[test]
+    fn descriptions_raise_the_reply_limit() {
+        assert_eq!(max_tokens(SESSION_MAX_TOKENS, None), SESSION_MAX_TOKENS);
+        assert_eq!(
+            max_tokens(SESSION_MAX_TOKENS, Some(DescriptionStorage::Sidecar)),
+            SESSION_MAX_TOKENS + DESCRIPTION_REPLY_TOKENS as u32
+        );
+        assert_eq!(
+            max_tokens(FILE_MAX_TOKENS, Some(DescriptionStorage::Central)),
+            FILE_MAX_TOKENS + DESCRIPTION_REPLY_TOKENS as u32
+        );
+    }
+
+    #[test]
+    fn jpeg_and_webp_screenshots_keep_their_extension() {
+        assert!(is_screenshot_image("Screenshot 2025-03-14 at 10.22.33.png"));
+        assert!(is_screenshot_image("Screenshot_20250314_102233.JPG"));
+        assert!(is_screenshot_image("chrome_2025-03-14_10-22-33.webp"));
+        assert!(!is_screenshot_image("Screenshot notes.txt"));
+        assert!(!is_screenshot_image("holiday.jpg"));
+
+        let jpeg = Path::new("/shots/Screenshot 1.jpeg");
+        assert_eq!(media_type(jpeg), "image/jpeg");
+        assert_eq!(media_type(Path::new("/shots/a.WEBP")), "image/webp");
+        assert_eq!(
+            with_image_extension("stripe_invoice.png", jpeg),
+            "stripe_invoice.jpeg"
+        );
+        assert_eq!(
+            with_image_extension("stripe_invoice", jpeg),
+            "stripe_invoice.jpeg"
+        );
+        assert_eq!(with_image_extension("invoice.JPEG", jpeg), "invoice.JPEG");
+        assert_eq!(with_image_extension("v1.2", jpeg), "v1.2.jpeg");
+
+        let proposal = FileProposal {
+            id: "a".to_string(),
+            original_path: "/shots/Screenshot 1.webp".to_string(),
+            original_name: "Screenshot 1.webp".to_string(),
+            proposed_name: "login_page.png".to_string(),
+            proposed_category: "Code".to_string(),
+            reasoning: String::new(),
+            session_id: None,
+            model: String::new(),
+            visible_text: String::new(),
+            confidence: None,
+            description: None,
+            redactions: Vec::new(),
+        };
+        assert_eq!(
+            destination_path(&proposal),
+            Path::new("/shots/Code/login_page.webp")
+        );
+    }
+
+    #[test]
+    fn only_cacheable_prompts_get_a_cache_breakpoint() {
+        // About four characters per token
+        let tokens = |n: usize| "word".repeat(n);
+        let has_breakpoint = |model: &str, system: &str| {
+            !message_params(model, system.to_string(), Vec::new(), FILE_MAX_TOKENS)["system"][0]
+                ["cache_control"]
+                .is_null()
+        };
+
+        assert!(!has_breakpoint("claude-sonnet-4-5", &tokens(1000)));
+        assert!(has_breakpoint("claude-sonnet-4-5", &tokens(1024)));
+        assert!(!has_breakpoint("claude-3-5-haiku", &tokens(2000)));
+        assert!(has_breakpoint("claude-3-5-haiku", &tokens(2048)));
+        assert!(!has_breakpoint("claude-opus-4-5-20251101", &tokens(4000)));
+        assert!(has_breakpoint("claude-opus-4-5-20251101", &tokens(4096)));
+        assert!(!has_breakpoint("claude-haiku-4-5", &tokens(4000)));
+
+        // The filing guide makes the shared prompt long enough for every
+        // built-in model, with or without the description rule
+        let models = ModelSettings::default();
+        for model in [
+            models.classify.as_str(),
+            models.refine.as_str(),
+            models.summarize.as_str(),
+            "claude-sonnet-4-5",
+            "claude-3-5-haiku",
+        ] {
+            for descriptions in [None, Some(DescriptionStorage::Sidecar)] {
+                assert!(
+                    has_breakpoint(model, &system_prompt(descriptions)),
+                    "{}",
+                    model
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn the_estimate_reads_the_cached_prompt_after_the_first_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = ["Screenshot 1.png", "Screenshot 2.png", "Screenshot 3.png"]
+            .map(|name| dir.path().join(name));
+        for (seed, path) in files.iter().enumerate() {
+            // Different sizes so no two are duplicates or a burst
+            image::RgbImage::new(64 + seed as u32 * 40, 64)
+                .save(path)
+                .unwrap();
+        }
+        let prepared = PreparedScan {
+            files: files.to_vec(),
+            sessions: Vec::new(),
+            duplicate_groups: Vec::new(),
+            skipped: Vec::new(),
+        };
+        let prices = PriceTable::default();
+        let model = "claude-haiku-4-5";
+        let estimate = estimate_cost(&prepared, None, model, None, &prices);
+
+        let prompt_tokens = usage::text_tokens(&system_prompt(None));
+        let mut tokens = TokenUsage::default();
+        for path in &files {
+            tokens.add(estimate_file(path, None));
+        }
+        assert_eq!(tokens.cache_creation_input_tokens, 3 * prompt_tokens);
+        let expected = TokenUsage {
+            cache_creation_input_tokens: prompt_tokens,
+            cache_read_input_tokens: 2 * prompt_tokens,
+            ..tokens
+        };
+        assert!(estimate.prompt_cached);
+        assert_eq!(estimate.requests, 3);
+        assert_eq!(
+            estimate.input_tokens,
+            tokens.input_tokens + 3 * prompt_tokens
+        );
+        assert_eq!(estimate.cost_usd, prices.cost(model, &expected));
+        // Far less than sending the prompt in full three times
+        let uncached = TokenUsage {
+            input_tokens: tokens.input_tokens + 3 * prompt_tokens,
+            cache_creation_input_tokens: 0,
+            ..tokens
+        };
+        assert!(estimate.cost_usd.unwrap() < prices.cost(model, &uncached).unwrap());
+    }
+
+    #[test]
+    fn protected_files_are_left_out_of_the_estimate() {
+        let dir = tempfile::tempdir().unwrap();
+        let statement = dir.path().join("chase-statement.png");
+        image::GrayImage::from_fn(90, 80, |x, y| image::Luma([((x * 2 + y) % 256) as u8]))
+            .save(&statement)
+            .unwrap();
+        // Unlike the statement, so the two are not grouped as duplicates
+        let diagram = dir.path().join("diagram.png");
+        image::GrayImage::from_fn(90, 80, |x, _| {
+            image::Luma([if x % 20 < 10 { 0 } else { 255 }])
+        })
+        .save(&diagram)
+        .unwrap();
+        let files = [statement, diagram];
+        let mut privacy = PrivacySettings::default();
+        let estimate = |privacy: &PrivacySettings| {
+            estimate_scan(
+                files.to_vec(),
+                &ScanOptions::default(),
+                privacy,
+                &ModelSettings::default(),
+                &PriceTable::default(),
+            )
+        };
+
+        assert_eq!(estimate(&privacy).files, 2);
+        privacy.never_upload.push(privacy::NeverUpload {
+            kind: privacy::RuleKind::Pattern,
+            value: "*statement*".to_string(),
+            category: None,
+        });
+        let estimate = estimate(&privacy);
+        assert_eq!(estimate.files, 1);
+        assert_eq!(estimate.requests, 1);
+    }
+
+    #[test]
+    fn the_estimate_bounds_what_escalation_could_add() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Screenshot 1.png");
+        image::RgbImage::new(64, 64).save(&path).unwrap();
+        let prices = PriceTable::default();
+        let models = ModelSettings::default();
+        let estimate = |options: &ScanOptions, models: &ModelSettings| {
+            estimate_scan(
+                vec![path.clone()],
+                options,
+                &PrivacySettings::default(),
+                models,
+                &prices,
+            )
+        };
+
+        let escalating = estimate(&ScanOptions::default(), &models);
+        // One request, which writes the system prompt to the cache
+        let prompt_tokens = usage::text_tokens(&system_prompt(None));
+        let tokens = TokenUsage {
+            input_tokens: escalating.input_tokens - prompt_tokens,
+            output_tokens: escalating.output_tokens,
+            cache_creation_input_tokens: prompt_tokens,
+            ..TokenUsage::default()
+        };
+        assert_eq!(escalating.model, models.first_classify_model());
+        assert_eq!(escalating.escalation_model.as_deref(), Some(DEFAULT_MODEL));
+        let first_cost = prices.cost(models.first_classify_model(), &tokens).unwrap();
+        let escalated_cost = prices.cost(DEFAULT_MODEL, &tokens).unwrap();
+        assert_eq!(escalating.cost_usd, Some(first_cost));
+        assert_eq!(escalating.max_cost_usd, Some(first_cost + escalated_cost));
+
+        // Batch runs and runs without a first model never escalate
+        let batch = ScanOptions {
+            batch: Some(true),
+            ..ScanOptions::default()
+        };
+        let direct = ModelSettings {
+            escalate_from: None,
+            ..ModelSettings::default()
+        };
+        for estimate in [
+            estimate(&batch, &models),
+            estimate(&ScanOptions::default(), &direct),
+        ] {
+            assert_eq!(estimate.model, DEFAULT_MODEL);
+            assert_eq!(estimate.cost_usd, Some(escalated_cost));
+            assert_eq!(estimate.escalation_model, None);
+            assert_eq!(estimate.max_cost_usd, None);
+        }
+
+        // Without a price for the escalation model the bound is unknown
+        let unpriced = ModelSettings {
+            classify: "claude-unreleased".to_string(),
+            ..ModelSettings::default()
+        };
+        let estimate = estimate(&ScanOptions::default(), &unpriced);
+        assert_eq!(estimate.cost_usd, Some(first_cost));
+        assert_eq!(estimate.max_cost_usd, None);
+    }
+
+    fn reporter(dir: &Path, budget_usd: Option<f64>) -> (Reporter, RunContext) {
+        let store = Arc::new(SessionStore::new(dir.join("sessions")));
+        let run_id = store.start("/shots", &[], RunOptions::default()).unwrap();
+        let (sender, _) = tokio::sync::mpsc::unbounded_channel();
+        let reporter = Reporter::new(
+            &run_id,
+            store,
+            Arc::new(crate::events::ChannelSink(sender)),
+            RunCost::default(),
+            budget_usd,
+        );
+        let run = RunContext {
+            run_id,
+            api_key: String::new(),
+            layout: Default::default(),
+            descriptions: None,
+            prices: Arc::new(PriceTable::default()),
+            models: ModelSettings::default(),
+            client: ApiClient::new(&NetworkConfig::default()).unwrap(),
+            privacy: PrivacySettings::default(),
+        };
+        (reporter, run)
+    }
+
+    // Input worth `amount` on claude-haiku-4-5, at $1 per million tokens
+    fn dollars(amount: f64) -> TokenUsage {
+        TokenUsage {
+            input_tokens: (amount * 1_000_000.0).round() as u64,
+            ..TokenUsage::default()
+        }
+    }
+
+    #[test]
+    fn reservations_hold_room_in_the_budget_until_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let (reporter, run) = reporter(dir.path(), Some(1.0));
+        let model = "claude-haiku-4-5";
+
+        let first = reporter.reserve(&run, model, 1.0, || dollars(0.4)).unwrap();
+        let second = reporter.reserve(&run, model, 1.0, || dollars(0.4)).unwrap();
+        assert!((first + second - 0.8).abs() < 1e-9);
+
+        // What was spent counts once the first request reports and releases
+        reporter.usage(vec!["a.png".to_string()], &run, model, dollars(0.5), 1.0);
+        reporter.release(first);
+        reporter.release(second);
+        let third = reporter.reserve(&run, model, 1.0, || dollars(0.2)).unwrap();
+        // The batch discount scales what is held
+        let fourth = reporter.reserve(&run, model, 0.5, || dollars(0.4)).unwrap();
+        assert!((fourth - 0.2).abs() < 1e-9);
+        reporter.release(third);
+        reporter.release(fourth);
+    }
+
+    #[test]
+    fn a_request_that_does_not_fit_closes_the_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let (reporter, run) = reporter(dir.path(), Some(1.0));
+        let model = "claude-haiku-4-5";
+
+        let held = reporter.reserve(&run, model, 1.0, || dollars(0.7)).unwrap();
+        assert_eq!(reporter.reserve(&run, model, 1.0, || dollars(0.4)), None);
+        // Closed for the rest of the run, even for what would fit again
+        reporter.release(held);
+        assert_eq!(reporter.reserve(&run, model, 1.0, || dollars(0.1)), None);
+    }
+
+    #[test]
+    fn without_a_budget_or_a_price_nothing_is_held() {
+        let dir = tempfile::tempdir().unwrap();
+        let (unlimited, run) = reporter(dir.path(), None);
+        let estimate = || -> TokenUsage { panic!("no estimate without a budget") };
+        assert_eq!(
+            unlimited.reserve(&run, "claude-haiku-4-5", 1.0, estimate),
+            Some(0.0)
+        );
+
+        let (limited, run) = reporter(dir.path(), Some(0.01));
+        assert_eq!(
+            limited.reserve(&run, "claude-unreleased", 1.0, || dollars(5.0)),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn a_rebuilt_search_index_is_filled_from_applied_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path().join("data");
+        let shot = dir.path().join("Screenshot 2025-03-14 at 10.22.33.png");
+        std::fs::write(&shot, b"png").unwrap();
+        let target = dir.path().join("2025/03/Finance/stripe_invoice.png");
+
+        let library = Library::open(&data);
+        let run_id = library
+            .sessions
+            .start(
+                &dir.path().to_string_lossy(),
+                std::slice::from_ref(&shot),
+                RunOptions::default(),
+            )
+            .unwrap();
+        let proposal = FileProposal {
+            id: "p1".to_string(),
+            original_path: shot.to_string_lossy().to_string(),
+            original_name: file_name(&shot),
+            proposed_name: "stripe_invoice.png".to_string(),
+            proposed_category: "2025/03/Finance".to_string(),
+            reasoning: "payment receipt".to_string(),
+            session_id: None,
+            model: String::new(),
+            visible_text: "Stripe invoice".to_string(),
+            confidence: Some(0.9),
+            description: None,
+            redactions: Vec::new(),
+        };
+        library
+            .sessions
+            .append(
+                &run_id,
+                SessionEvent::Proposed {
+                    proposal: Box::new(proposal),
+                },
+            )
+            .unwrap();
+        let (moved, _) = library.execute_move(MoveRequest {
+            original_path: shot.to_string_lossy().to_string(),
+            new_path: target.to_string_lossy().to_string(),
+            root_path: Some(dir.path().to_string_lossy().to_string()),
+            overwrite: false,
+            run_id: Some(run_id),
+            proposal_id: Some("p1".to_string()),
+        });
+        moved.unwrap();
+        drop(library);
+
+        std::fs::remove_dir_all(data.join("search_index")).unwrap();
+        let library = Library::open(&data);
+        let filters = SearchFilters {
+            category: Some("finance".to_string()),
+            ..SearchFilters::default()
+        };
+        let hits = library
+            .search_index()
+            .unwrap()
+            .search("stripe", &filters)
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].path, target.to_string_lossy());
+        assert_eq!(hits[0].category, "Finance");
+    }
+}
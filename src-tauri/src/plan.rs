@@ -0,0 +1,555 @@
+//! A reviewable move plan for one run: every pending proposal with its
+//! source, destination and any conflict. Plans are exported as JSON or CSV
+//! (`snapsort export`, `snapsort plan --json`, Export Plan in the app), can
+//! be edited by hand or in a spreadsheet, and are validated before
+//! `snapsort apply` or the app's Apply Plan moves anything.
+//!
+//! `proposed_path` decides where a file goes; `category` and `conflict` are
+//! informational and ignored on import.
+
+use crate::pipeline::{self, Library, MoveOutcome, MoveRequest};
+use crate::session_store::{ApplyStatus, RunSnapshot, SessionEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Plan {
+    pub run_id: String,
+    pub root_path: String,
+    pub created_at: String,
+    pub moves: Vec<PlannedMove>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlannedMove {
+    pub id: String,
+    pub original_path: String,
+    /// Edit this to change where the file goes.
+    pub proposed_path: String,
+    pub category: String,
+    pub reasoning: String,
+    #[serde(default = "default_selected")]
+    pub selected: bool,
+    /// Replace a file already at `proposed_path`.
+    #[serde(default)]
+    pub overwrite: bool,
+    /// Why the move would fail as planned.
+    #[serde(default)]
+    pub conflict: Option<String>,
+}
+
+fn default_selected() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanFormat {
+    Json,
+    Csv,
+}
+
+impl PlanFormat {
+    /// CSV for `.csv` files, JSON otherwise.
+    pub fn from_path(path: &Path) -> PlanFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => PlanFormat::Csv,
+            _ => PlanFormat::Json,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            PlanFormat::Json => "json",
+            PlanFormat::Csv => "csv",
+        }
+    }
+}
+
+// One CSV row; the run id repeats so a sheet stays self-describing.
+#[derive(Serialize, Deserialize)]
+struct CsvRow {
+    run_id: String,
+    id: String,
+    selected: bool,
+    original_path: String,
+    proposed_path: String,
+    category: String,
+    reasoning: String,
+    #[serde(default)]
+    overwrite: bool,
+    #[serde(default)]
+    conflict: Option<String>,
+}
+
+impl Plan {
+    /// Pending proposals of a run; applied and failed ones are left out.
+    pub fn from_run(run: &RunSnapshot) -> Plan {
+        let mut plan = Plan {
+            run_id: run.run_id.clone(),
+            root_path: run.root_path.clone(),
+            created_at: chrono::Local::now().to_rfc3339(),
+            moves: run
+                .proposals
+                .iter()
+                .filter(|entry| entry.status == ApplyStatus::Pending)
+                .map(|entry| PlannedMove {
+                    id: entry.proposal.id.clone(),
+                    original_path: entry.proposal.original_path.clone(),
+                    proposed_path: pipeline::destination_path(&entry.proposal)
+                        .to_string_lossy()
+                        .to_string(),
+                    category: entry.proposal.proposed_category.clone(),
+                    reasoning: entry.proposal.reasoning.clone(),
+                    selected: entry.selected,
+                    overwrite: false,
+                    conflict: None,
+                })
+                .collect(),
+        };
+        plan.detect_conflicts();
+        plan
+    }
+
+    /// Mark selected moves that would fail: missing source, an existing file
+    /// at the destination, or two moves to the same destination.
+    pub fn detect_conflicts(&mut self) {
+        let mut targets: HashMap<String, Vec<String>> = HashMap::new();
+        for planned in self.moves.iter().filter(|planned| planned.selected) {
+            targets
+                .entry(planned.proposed_path.clone())
+                .or_default()
+                .push(planned.id.clone());
+        }
+
+        for planned in &mut self.moves {
+            planned.conflict = None;
+            if !planned.selected {
+                continue;
+            }
+            let destination = Path::new(&planned.proposed_path);
+            planned.conflict = if !Path::new(&planned.original_path).exists() {
+                Some("source file no longer exists".to_string())
+            } else if let Some(other) = targets[&planned.proposed_path]
+                .iter()
+                .find(|id| **id != planned.id)
+            {
+                Some(format!("same destination as {}", other))
+            } else if destination.exists() && !planned.overwrite {
+                Some("destination already exists".to_string())
+            } else {
+                None
+            };
+        }
+    }
+
+    pub fn conflicts(&self) -> usize {
+        self.moves
+            .iter()
+            .filter(|planned| planned.conflict.is_some())
+            .count()
+    }
+
+    pub fn export(&self, format: PlanFormat) -> Result<String, String> {
+        match format {
+            PlanFormat::Json => serde_json::to_string_pretty(self).map_err(|e| e.to_string()),
+            PlanFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                for planned in &self.moves {
+                    writer
+                        .serialize(CsvRow {
+                            run_id: self.run_id.clone(),
+                            id: planned.id.clone(),
+                            selected: planned.selected,
+                            original_path: planned.original_path.clone(),
+                            proposed_path: planned.proposed_path.clone(),
+                            category: planned.category.clone(),
+                            reasoning: planned.reasoning.clone(),
+                            overwrite: planned.overwrite,
+                            conflict: planned.conflict.clone(),
+                        })
+                        .map_err(|e| e.to_string())?;
+                }
+                let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+                String::from_utf8(bytes).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Parse an exported plan. CSV carries no root folder, so it is taken
+    /// from the run.
+    pub fn import(text: &str, format: PlanFormat, library: &Library) -> Result<Plan, String> {
+        let mut plan = match format {
+            PlanFormat::Json => {
+                serde_json::from_str::<Plan>(text).map_err(|e| format!("Invalid plan: {}", e))?
+            }
+            PlanFormat::Csv => {
+                let mut reader = csv::Reader::from_reader(text.as_bytes());
+                let mut rows = Vec::new();
+                for (index, row) in reader.deserialize::<CsvRow>().enumerate() {
+                    // Line 1 is the header
+                    rows.push(row.map_err(|e| format!("Invalid plan row {}: {}", index + 2, e))?);
+                }
+                let run_id = rows
+                    .first()
+                    .map(|row| row.run_id.clone())
+                    .ok_or_else(|| "Plan has no moves".to_string())?;
+                if rows.iter().any(|row| row.run_id != run_id) {
+                    return Err("Plan mixes moves from several runs".to_string());
+                }
+                Plan {
+                    root_path: library.sessions.load(&run_id)?.root_path,
+                    run_id,
+                    created_at: chrono::Local::now().to_rfc3339(),
+                    moves: rows
+                        .into_iter()
+                        .map(|row| PlannedMove {
+                            id: row.id,
+                            original_path: row.original_path,
+                            proposed_path: row.proposed_path,
+                            category: row.category,
+                            reasoning: row.reasoning,
+                            selected: row.selected,
+                            overwrite: row.overwrite,
+                            conflict: row.conflict,
+                        })
+                        .collect(),
+                }
+            }
+        };
+        // Conflicts in the file may be stale; judge the disk as it is now.
+        plan.detect_conflicts();
+        Ok(plan)
+    }
+
+    /// Problems that would make the plan fail or do something else than
+    /// written, one line per move. Empty when the plan can be applied.
+    pub fn validate(&self, library: &Library) -> Result<Vec<String>, String> {
+        let run = library.sessions.load(&self.run_id)?;
+        let proposals = run
+            .proposals
+            .iter()
+            .map(|entry| (entry.proposal.id.as_str(), entry))
+            .collect::<HashMap<&str, _>>();
+
+        let mut problems = Vec::new();
+        if self.root_path != run.root_path {
+            problems.push(format!("root path does not match run {}", self.run_id));
+        }
+        let mut seen = HashSet::new();
+        for planned in &self.moves {
+            if !seen.insert(planned.id.as_str()) {
+                problems.push(format!("{}: listed more than once", planned.id));
+                continue;
+            }
+            if !planned.selected {
+                continue;
+            }
+            let Some(entry) = proposals.get(planned.id.as_str()) else {
+                problems.push(format!("{}: not part of run {}", planned.id, self.run_id));
+                continue;
+            };
+            if entry.proposal.original_path != planned.original_path {
+                problems.push(format!(
+                    "{}: original path does not match the run",
+                    planned.id
+                ));
+            } else if entry.status != ApplyStatus::Pending {
+                problems.push(format!("{}: already applied", planned.id));
+            } else if let Err(e) = destination_category(&entry.proposal.original_path, planned) {
+                problems.push(format!("{}: {}", planned.id, e));
+            } else if let Some(conflict) = &planned.conflict {
+                problems.push(format!("{}: {}", planned.id, conflict));
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Apply the selected moves. Hand edits to `proposed_path` are recorded
+    /// in the run first, so history and search see the final folder.
+    pub fn apply(&self, library: &Library) -> Vec<MoveOutcome> {
+        let mut outcomes = Vec::new();
+        for planned in self.moves.iter().filter(|planned| planned.selected) {
+            let outcome = match self.record_edit(library, planned) {
+                Ok(()) => {
+                    let (result, _) = library.execute_move(MoveRequest {
+                        original_path: planned.original_path.clone(),
+                        new_path: planned.proposed_path.clone(),
+                        root_path: Some(self.root_path.clone()),
+                        overwrite: planned.overwrite,
+                        run_id: Some(self.run_id.clone()),
+                        proposal_id: Some(planned.id.clone()),
+                    });
+                    result
+                }
+                Err(e) => Err(e),
+            };
+            outcomes.push(MoveOutcome::new(
+                planned.id.clone(),
+                planned.original_path.clone(),
+                outcome,
+            ));
+        }
+        library.commit_search();
+        outcomes
+    }
+
+    fn record_edit(&self, library: &Library, planned: &PlannedMove) -> Result<(), String> {
+        let proposal = library
+            .sessions
+            .proposal(&self.run_id, &planned.id)
+            .ok_or_else(|| format!("Proposal {} not found in run {}", planned.id, self.run_id))?;
+        if proposal.original_path != planned.original_path {
+            return Err("Original path does not match the run".to_string());
+        }
+
+        let (category, name) = destination_category(&proposal.original_path, planned)?;
+        if category == proposal.proposed_category && name == proposal.proposed_name {
+            return Ok(());
+        }
+        library.sessions.append(
+            &self.run_id,
+            SessionEvent::Edited {
+                id: planned.id.clone(),
+                proposed_name: name,
+                proposed_category: category,
+                selected: true,
+            },
+        )
+    }
+}
+
+// Category folder and file name of a planned destination, relative to the
+// folder the file is in now
+fn destination_category(
+    original_path: &str,
+    planned: &PlannedMove,
+) -> Result<(String, String), String> {
+    let destination = Path::new(&planned.proposed_path);
+    if pipeline::contains_parent_dir(destination) {
+        return Err("Proposed path contains invalid path segments".to_string());
+    }
+    let source_dir = Path::new(original_path).parent().unwrap_or(Path::new(""));
+    let category = destination
+        .parent()
+        .and_then(|parent| parent.strip_prefix(source_dir).ok())
+        .map(|folder| folder.to_string_lossy().replace('\\', "/"))
+        .ok_or_else(|| "Proposed path must stay within the source folder".to_string())?;
+    let name = destination
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.trim().is_empty())
+        .ok_or_else(|| "Proposed path has no file name".to_string())?;
+    Ok((category, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::FileProposal;
+    use crate::session_store::RunOptions;
+    use std::path::PathBuf;
+
+    // A run over `names` in `dir`, each proposed into Code/ as renamed_<name>
+    fn run_with(library: &Library, dir: &Path, names: &[&str]) -> String {
+        let files = names
+            .iter()
+            .map(|name| dir.join(name))
+            .collect::<Vec<PathBuf>>();
+        for file in &files {
+            std::fs::write(file, b"png").unwrap();
+        }
+        let run_id = library
+            .sessions
+            .start(&dir.to_string_lossy(), &files, RunOptions::default())
+            .unwrap();
+        for (name, file) in names.iter().zip(&files) {
+            let proposal = FileProposal {
+                id: name.to_string(),
+                original_path: file.to_string_lossy().to_string(),
+                original_name: name.to_string(),
+                proposed_name: format!("renamed_{}", name),
+                proposed_category: "Code".to_string(),
+                reasoning: "editor window".to_string(),
+                session_id: None,
+                model: String::new(),
+                visible_text: String::new(),
+                confidence: Some(0.9),
+                description: None,
+                redactions: Vec::new(),
+            };
+            library
+                .sessions
+                .append(
+                    &run_id,
+                    SessionEvent::Proposed {
+                        proposal: Box::new(proposal),
+                    },
+                )
+                .unwrap();
+        }
+        run_id
+    }
+
+    fn plan_of(library: &Library, run_id: &str) -> Plan {
+        Plan::from_run(&library.sessions.load(run_id).unwrap())
+    }
+
+    #[test]
+    fn exported_plans_import_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::open(&dir.path().join("data"));
+        let run_id = run_with(&library, dir.path(), &["a.png", "b, \"quoted\".png"]);
+        let mut plan = plan_of(&library, &run_id);
+        plan.moves[1].selected = false;
+
+        for format in [PlanFormat::Json, PlanFormat::Csv] {
+            let text = plan.export(format).unwrap();
+            let imported = Plan::import(&text, format, &library).unwrap();
+            assert_eq!(imported.run_id, run_id);
+            assert_eq!(imported.root_path, plan.root_path);
+            assert_eq!(imported.moves.len(), 2);
+            for (before, after) in plan.moves.iter().zip(&imported.moves) {
+                assert_eq!(after.id, before.id);
+                assert_eq!(after.original_path, before.original_path);
+                assert_eq!(after.proposed_path, before.proposed_path);
+                assert_eq!(after.selected, before.selected);
+                assert_eq!(after.reasoning, "editor window");
+            }
+            assert!(imported.validate(&library).unwrap().is_empty());
+        }
+        assert_eq!(
+            PlanFormat::from_path(Path::new("plan.CSV")),
+            PlanFormat::Csv
+        );
+        assert_eq!(PlanFormat::from_path(Path::new("plan")), PlanFormat::Json);
+    }
+
+    #[test]
+    fn stale_plans_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::open(&dir.path().join("data"));
+        let run_id = run_with(&library, dir.path(), &["a.png", "b.png"]);
+        let mut plan = plan_of(&library, &run_id);
+        std::fs::remove_file(dir.path().join("a.png")).unwrap();
+        plan.moves[1].original_path = dir.path().join("c.png").to_string_lossy().to_string();
+        std::fs::write(&plan.moves[1].original_path, b"png").unwrap();
+
+        let text = plan.export(PlanFormat::Json).unwrap();
+        let imported = Plan::import(&text, PlanFormat::Json, &library).unwrap();
+        assert_eq!(
+            imported.moves[0].conflict.as_deref(),
+            Some("source file no longer exists")
+        );
+        assert_eq!(
+            imported.validate(&library).unwrap(),
+            vec![
+                "a.png: source file no longer exists".to_string(),
+                "b.png: original path does not match the run".to_string(),
+            ]
+        );
+
+        let outcomes = imported.apply(&library);
+        assert!(outcomes.iter().all(|outcome| outcome.error.is_some()));
+        assert!(dir.path().join("c.png").exists());
+    }
+
+    #[test]
+    fn moves_to_one_destination_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::open(&dir.path().join("data"));
+        let run_id = run_with(&library, dir.path(), &["a.png", "b.png", "c.png"]);
+        let mut plan = plan_of(&library, &run_id);
+        plan.moves[1].proposed_path = plan.moves[0].proposed_path.clone();
+        std::fs::create_dir_all(dir.path().join("Code")).unwrap();
+        std::fs::write(&plan.moves[2].proposed_path, b"taken").unwrap();
+        plan.detect_conflicts();
+
+        assert_eq!(
+            plan.moves[0].conflict.as_deref(),
+            Some("same destination as b.png")
+        );
+        assert_eq!(
+            plan.moves[1].conflict.as_deref(),
+            Some("same destination as a.png")
+        );
+        assert_eq!(
+            plan.moves[2].conflict.as_deref(),
+            Some("destination already exists")
+        );
+        assert_eq!(plan.conflicts(), 3);
+
+        // Deselecting one side and allowing the overwrite clears them all
+        plan.moves[1].selected = false;
+        plan.moves[2].overwrite = true;
+        plan.detect_conflicts();
+        assert_eq!(plan.conflicts(), 0);
+        assert!(plan.validate(&library).unwrap().is_empty());
+    }
+
+    #[test]
+    fn an_edited_plan_moves_files_where_it_says() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::open(&dir.path().join("data"));
+        let run_id = run_with(&library, dir.path(), &["a.png", "b.png", "c.png"]);
+        let plan = plan_of(&library, &run_id);
+
+        // Edit the CSV the way a spreadsheet would: new folder and name for
+        // a.png, b.png left out, c.png as proposed
+        let edited = dir.path().join("Web").join("login_form.png");
+        let text = plan
+            .export(PlanFormat::Csv)
+            .unwrap()
+            .replace(&plan.moves[0].proposed_path, &edited.to_string_lossy())
+            .replace(
+                &format!("{},true,", plan.moves[1].id),
+                &format!("{},false,", plan.moves[1].id),
+            );
+        let imported = Plan::import(&text, PlanFormat::Csv, &library).unwrap();
+        assert!(imported.validate(&library).unwrap().is_empty());
+
+        let outcomes = imported.apply(&library);
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|outcome| outcome.error.is_none()));
+        assert!(edited.exists());
+        assert!(dir.path().join("Code/renamed_c.png").exists());
+        assert!(dir.path().join("b.png").exists());
+
+        let run = library.sessions.load(&run_id).unwrap();
+        let a = &run.proposals[0];
+        assert_eq!(a.proposal.proposed_category, "Web");
+        assert_eq!(a.proposal.proposed_name, "login_form.png");
+        assert_eq!(a.status, ApplyStatus::Applied);
+        assert_eq!(run.proposals[1].status, ApplyStatus::Pending);
+
+        // Applying the same plan again is refused
+        let problems = imported.validate(&library).unwrap();
+        assert!(problems.contains(&"a.png: already applied".to_string()));
+    }
+
+    #[test]
+    fn destinations_outside_the_source_folder_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::open(&dir.path().join("data"));
+        let run_id = run_with(&library, dir.path(), &["a.png", "b.png"]);
+        let mut plan = plan_of(&library, &run_id);
+        let outside = tempfile::tempdir().unwrap();
+        plan.moves[0].proposed_path = outside.path().join("a.png").to_string_lossy().to_string();
+        plan.moves[1].proposed_path = dir
+            .path()
+            .join("Code/../../b.png")
+            .to_string_lossy()
+            .to_string();
+
+        assert_eq!(
+            plan.validate(&library).unwrap(),
+            vec![
+                "a.png: Proposed path must stay within the source folder".to_string(),
+                "b.png: Proposed path contains invalid path segments".to_string(),
+            ]
+        );
+        assert!(plan.apply(&library).iter().all(|o| o.error.is_some()));
+        assert!(!outside.path().join("a.png").exists());
+    }
+}
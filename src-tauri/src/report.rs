@@ -0,0 +1,536 @@
+//! Post-run reports written next to the screenshots: a self-contained HTML
+//! gallery (thumbnails grouped by category, old and new names) and a Markdown
+//! summary with counts, skipped files, failures and duration.
+//!
+//! Everything comes from the run journal and the files on disk, so reports
+//! can be generated offline and regenerated at any time.
+
+use crate::pipeline::MAX_FILE_SIZE;
+use crate::session_store::{ApplyStatus, RunSnapshot, SessionProposal};
+use crate::{summary, usage};
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+// Longest edge of a gallery thumbnail, in pixels
+const THUMBNAIL_SIZE: u32 = 240;
+
+#[derive(Serialize, Clone)]
+pub struct ReportFiles {
+    pub html: PathBuf,
+    pub markdown: PathBuf,
+}
+
+/// Write `snapsort-report-<run_id>.html` and `.md` into the scan root.
+pub fn write_reports(run: &RunSnapshot) -> Result<ReportFiles, String> {
+    let root = Path::new(&run.root_path);
+    if !root.is_dir() {
+        return Err(format!("Scan folder not found: {}", run.root_path));
+    }
+    let files = ReportFiles {
+        html: root.join(format!("snapsort-report-{}.html", run.run_id)),
+        markdown: root.join(format!("snapsort-report-{}.md", run.run_id)),
+    };
+    std::fs::write(&files.html, html_report(run)).map_err(|e| e.to_string())?;
+    std::fs::write(&files.markdown, markdown_report(run)).map_err(|e| e.to_string())?;
+    Ok(files)
+}
+
+pub fn markdown_report(run: &RunSnapshot) -> String {
+    let counts = Counts::of(run);
+    let mut out = String::new();
+    let _ = writeln!(out, "# SnapSort report\n");
+    let _ = writeln!(out, "- Folder: `{}`", run.root_path);
+    let _ = writeln!(out, "- Run: `{}`", run.run_id);
+    let _ = writeln!(out, "- Started: {}", run.started_at);
+    let _ = writeln!(out, "- Duration: {}", duration(run));
+    let _ = writeln!(out, "- API usage: {}\n", cost(run));
+
+    let _ = writeln!(out, "## Counts\n");
+    let _ = writeln!(out, "| | Files |\n|---|---:|");
+    for (label, count) in counts.rows() {
+        let _ = writeln!(out, "| {} | {} |", label, count);
+    }
+
+    let groups = by_category(run);
+    if !groups.is_empty() {
+        let _ = writeln!(out, "\n## Categories\n");
+        let _ = writeln!(out, "| Category | Files | Moved |\n|---|---:|---:|");
+        for (category, entries) in &groups {
+            let moved = entries
+                .iter()
+                .filter(|entry| entry.status == ApplyStatus::Applied)
+                .count();
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} |",
+                markdown_cell(category),
+                entries.len(),
+                moved
+            );
+        }
+    }
+
+    if !run.skipped.is_empty() {
+        let _ = writeln!(out, "\n## Skipped\n");
+        for file in &run.skipped {
+            let _ = writeln!(out, "- `{}`: {}", file.name, file.reason);
+        }
+    }
+
+    if !run.deferred.is_empty() {
+        let _ = writeln!(out, "\n## Deferred (budget reached)\n");
+        for name in &run.deferred {
+            let _ = writeln!(out, "- `{}`", name);
+        }
+    }
+
+    let failures = failures(run);
+    if !failures.is_empty() {
+        let _ = writeln!(out, "\n## Failures\n");
+        for (name, error) in failures {
+            let _ = writeln!(out, "- `{}`: {}", name, error);
+        }
+    }
+    out
+}
+
+pub fn html_report(run: &RunSnapshot) -> String {
+    let counts = Counts::of(run);
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>SnapSort report – {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&folder_name(run)),
+        STYLE
+    );
+    let _ = writeln!(out, "<h1>SnapSort report</h1>");
+    let _ = writeln!(
+        out,
+        "<p class=\"meta\">{} · started {} · {} · {}</p>",
+        escape(&run.root_path),
+        escape(&run.started_at),
+        escape(&duration(run)),
+        escape(&cost(run))
+    );
+
+    let _ = writeln!(out, "<ul class=\"counts\">");
+    for (label, count) in counts.rows() {
+        let _ = writeln!(out, "<li><b>{}</b> {}</li>", count, escape(label));
+    }
+    let _ = writeln!(out, "</ul>");
+
+    for (category, entries) in by_category(run) {
+        let _ = writeln!(
+            out,
+            "<h2>{} <span>{}</span></h2>\n<div class=\"grid\">",
+            escape(category),
+            entries.len()
+        );
+        for entry in entries {
+            card(&mut out, entry);
+        }
+        let _ = writeln!(out, "</div>");
+    }
+
+    if !run.skipped.is_empty() {
+        let _ = writeln!(
+            out,
+            "<h2>Skipped <span>{}</span></h2>\n<ul>",
+            run.skipped.len()
+        );
+        for file in &run.skipped {
+            let _ = writeln!(
+                out,
+                "<li><code>{}</code> {}</li>",
+                escape(&file.name),
+                escape(&file.reason)
+            );
+        }
+        let _ = writeln!(out, "</ul>");
+    }
+
+    if !run.deferred.is_empty() {
+        let _ = writeln!(
+            out,
+            "<h2>Deferred <span>{}</span></h2>\n<p class=\"meta\">Budget reached before these were sent</p>\n<ul>",
+            run.deferred.len()
+        );
+        for name in &run.deferred {
+            let _ = writeln!(out, "<li><code>{}</code></li>", escape(name));
+        }
+        let _ = writeln!(out, "</ul>");
+    }
+
+    let failures = failures(run);
+    if !failures.is_empty() {
+        let _ = writeln!(
+            out,
+            "<h2>Failures <span>{}</span></h2>\n<ul>",
+            failures.len()
+        );
+        for (name, error) in failures {
+            let _ = writeln!(
+                out,
+                "<li><code>{}</code> {}</li>",
+                escape(&name),
+                escape(&error)
+            );
+        }
+        let _ = writeln!(out, "</ul>");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn card(out: &mut String, entry: &SessionProposal) {
+    let proposal = &entry.proposal;
+    // The file is wherever the last move left it
+    let current = entry
+        .final_path
+        .as_deref()
+        .unwrap_or(&proposal.original_path);
+    let image = match thumbnail(Path::new(current)) {
+        Some(data) => format!("<img src=\"data:image/jpeg;base64,{}\" alt=\"\">", data),
+        None => "<div class=\"missing\">No preview</div>".to_string(),
+    };
+    let status = match entry.status {
+        ApplyStatus::Applied => "moved",
+        ApplyStatus::Pending => "not moved",
+        ApplyStatus::Failed => "move failed",
+    };
+    let _ = writeln!(
+        out,
+        "<figure class=\"{}\">{}<figcaption><div class=\"before\">{}</div>\
+         <div class=\"after\">→ {}</div><div class=\"status\">{}</div>\
+         <p>{}</p></figcaption></figure>",
+        status.replace(' ', "-"),
+        image,
+        escape(&proposal.original_name),
+        escape(&proposal.proposed_name),
+        status,
+        escape(&proposal.reasoning)
+    );
+}
+
+// Base64 JPEG thumbnail, or None when the file is gone or unreadable
+fn thumbnail(path: &Path) -> Option<String> {
+    let size = std::fs::metadata(path).ok()?.len();
+    if size > MAX_FILE_SIZE {
+        return None;
+    }
+    let image = image::open(path)
+        .map_err(|e| eprintln!("[RUST] ⚠️ No thumbnail for {}: {}", path.display(), e))
+        .ok()?;
+    let small = image
+        .resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle)
+        .into_rgb8();
+    let mut data = Vec::new();
+    JpegEncoder::new_with_quality(&mut data, 75)
+        .encode_image(&small)
+        .ok()?;
+    Some(base64::engine::general_purpose::STANDARD.encode(&data))
+}
+
+struct Counts {
+    files: usize,
+    proposed: usize,
+    moved: usize,
+    pending: usize,
+    skipped: usize,
+    deferred: usize,
+    failed: usize,
+}
+
+impl Counts {
+    fn of(run: &RunSnapshot) -> Counts {
+        let count = |status: ApplyStatus| {
+            run.proposals
+                .iter()
+                .filter(|entry| entry.status == status)
+                .count()
+        };
+        Counts {
+            files: run.files.len(),
+            proposed: run.proposals.len(),
+            moved: count(ApplyStatus::Applied),
+            pending: count(ApplyStatus::Pending),
+            skipped: run.skipped.len(),
+            deferred: run.deferred.len(),
+            failed: run.failed.len() + count(ApplyStatus::Failed),
+        }
+    }
+
+    fn rows(&self) -> [(&'static str, usize); 7] {
+        [
+            ("Scanned", self.files),
+            ("Proposed", self.proposed),
+            ("Moved", self.moved),
+            ("Not moved", self.pending),
+            ("Skipped", self.skipped),
+            ("Deferred", self.deferred),
+            ("Failed", self.failed),
+        ]
+    }
+}
+
+fn by_category(run: &RunSnapshot) -> BTreeMap<&str, Vec<&SessionProposal>> {
+    let mut groups: BTreeMap<&str, Vec<&SessionProposal>> = BTreeMap::new();
+    for entry in &run.proposals {
+        groups
+            .entry(entry.proposal.proposed_category.as_str())
+            .or_default()
+            .push(entry);
+    }
+    groups
+}
+
+// Classification failures followed by failed moves
+fn failures(run: &RunSnapshot) -> Vec<(String, String)> {
+    let classification = run.failed.iter().map(|file| {
+        (
+            file.name.clone(),
+            file.error
+                .clone()
+                .unwrap_or_else(|| "classification failed".to_string()),
+        )
+    });
+    let moves = run
+        .proposals
+        .iter()
+        .filter(|entry| entry.status == ApplyStatus::Failed)
+        .map(|entry| {
+            (
+                entry.proposal.original_name.clone(),
+                entry
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| "move failed".to_string()),
+            )
+        });
+    classification.chain(moves).collect()
+}
+
+fn duration(run: &RunSnapshot) -> String {
+    let Some(finished_at) = &run.finished_at else {
+        return "not finished".to_string();
+    };
+    let (Ok(start), Ok(end)) = (
+        chrono::DateTime::parse_from_rfc3339(&run.started_at),
+        chrono::DateTime::parse_from_rfc3339(finished_at),
+    ) else {
+        return "unknown".to_string();
+    };
+    summary::format_duration((end - start).num_seconds().max(0) as u64)
+}
+
+fn cost(run: &RunSnapshot) -> String {
+    let cost = run.cost();
+    if cost.requests == 0 {
+        return "no API requests".to_string();
+    }
+    let mut text = format!(
+        "{} request(s), {} input and {} output tokens, {}",
+        cost.requests,
+        summary::format_tokens(cost.input_tokens),
+        summary::format_tokens(cost.output_tokens),
+        usage::format_cost(cost.cost_usd)
+    );
+    if cost.cache_read_input_tokens + cost.cache_creation_input_tokens > 0 {
+        text.push_str(&format!(
+            "; {} cached input tokens read, {} written",
+            summary::format_tokens(cost.cache_read_input_tokens),
+            summary::format_tokens(cost.cache_creation_input_tokens)
+        ));
+    }
+    if cost.unpriced_requests > 0 {
+        text.push_str(&format!(
+            " ({} without a known price)",
+            cost.unpriced_requests
+        ));
+    }
+    text
+}
+
+fn folder_name(run: &RunSnapshot) -> String {
+    Path::new(&run.root_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| run.root_path.clone())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+const STYLE: &str = "\
+body{font-family:-apple-system,system-ui,sans-serif;background:#0b0b0c;color:#e4e4e7;margin:2rem}\
+h1{font-size:1.5rem;margin:0}h2{font-size:1.1rem;margin-top:2rem}h2 span{color:#71717a;font-weight:400}\
+.meta{color:#a1a1aa;font-size:.85rem}\
+.counts{display:flex;gap:1.5rem;list-style:none;padding:0}.counts b{display:block;font-size:1.4rem}\
+.grid{display:grid;grid-template-columns:repeat(auto-fill,minmax(220px,1fr));gap:1rem}\
+figure{margin:0;background:#18181b;border:1px solid #27272a;border-radius:8px;overflow:hidden}\
+figure img,.missing{display:block;width:100%;height:160px;object-fit:cover;background:#09090b}\
+.missing{display:flex;align-items:center;justify-content:center;color:#52525b}\
+figcaption{padding:.6rem;font-size:.8rem;word-break:break-all}\
+.before{color:#a1a1aa;text-decoration:line-through}.after{color:#fafafa}\
+.status{color:#22c55e;margin-top:.3rem}.not-moved .status{color:#a1a1aa}.move-failed .status{color:#ef4444}\
+figcaption p{color:#71717a;margin:.3rem 0 0}code{color:#fafafa}li{margin:.2rem 0;color:#a1a1aa}";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{FileProposal, SkippedFile};
+    use crate::session_store::{RunOptions, SessionEvent, SessionStore};
+
+    fn proposed(id: &str, name: &str, category: &str) -> SessionEvent {
+        SessionEvent::Proposed {
+            proposal: Box::new(FileProposal {
+                id: id.to_string(),
+                original_path: format!("/shots/{}", name),
+                original_name: name.to_string(),
+                proposed_name: format!("renamed_{}", id),
+                proposed_category: category.to_string(),
+                reasoning: "a \"quoted\" <reason>".to_string(),
+                session_id: None,
+                model: String::new(),
+                visible_text: String::new(),
+                confidence: Some(0.9),
+                description: None,
+                redactions: Vec::new(),
+            }),
+        }
+    }
+
+    // Six files: one moved, one move failed, one pending, one skipped, one
+    // deferred and one that failed classification
+    fn run(dir: &Path) -> RunSnapshot {
+        let store = SessionStore::new(dir.to_path_buf());
+        let files = ["a", "b", "c", "d", "e", "f"].map(|name| PathBuf::from("/shots").join(name));
+        let run_id = store
+            .start("/shots", &files, RunOptions::default())
+            .unwrap();
+        for event in [
+            proposed("a", "<script>.png", "Code"),
+            proposed("b", "Tom & Jerry.png", "Fun | Games"),
+            proposed("c", "say \"hi\".png", "Code"),
+            SessionEvent::Moved {
+                id: "a".to_string(),
+                from: "/shots/<script>.png".to_string(),
+                to: "/shots/Code/renamed_a".to_string(),
+            },
+            SessionEvent::MoveFailed {
+                id: "b".to_string(),
+                error: "Permission denied".to_string(),
+            },
+            SessionEvent::Skipped {
+                file: SkippedFile {
+                    name: "huge.png".to_string(),
+                    size: 0,
+                    reason: "too large".to_string(),
+                    never_upload: false,
+                },
+            },
+            SessionEvent::Deferred {
+                name: "later.png".to_string(),
+            },
+            SessionEvent::Failed {
+                name: "broken.png".to_string(),
+                error: None,
+            },
+        ] {
+            store.append(&run_id, event).unwrap();
+        }
+        store.load(&run_id).unwrap()
+    }
+
+    #[test]
+    fn counts_cover_every_outcome() {
+        let dir = tempfile::tempdir().unwrap();
+        let run = run(dir.path());
+        let counts = Counts::of(&run);
+
+        assert_eq!(
+            counts.rows(),
+            [
+                ("Scanned", 6),
+                ("Proposed", 3),
+                ("Moved", 1),
+                ("Not moved", 1),
+                ("Skipped", 1),
+                ("Deferred", 1),
+                ("Failed", 2),
+            ]
+        );
+        assert_eq!(
+            failures(&run),
+            vec![
+                (
+                    "broken.png".to_string(),
+                    "classification failed".to_string()
+                ),
+                (
+                    "Tom & Jerry.png".to_string(),
+                    "Permission denied".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_markdown_report_lists_every_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = markdown_report(&run(dir.path()));
+
+        assert!(report.contains("| Moved | 1 |"), "{}", report);
+        assert!(report.contains("| Failed | 2 |"), "{}", report);
+        assert!(report.contains("| Code | 2 | 1 |"), "{}", report);
+        assert!(report.contains("| Fun \\| Games | 1 | 0 |"), "{}", report);
+        assert!(report.contains("- `huge.png`: too large"), "{}", report);
+        assert!(report.contains("## Deferred (budget reached)\n\n- `later.png`"));
+        assert!(report.contains("- `broken.png`: classification failed"));
+        assert!(report.contains("- `Tom & Jerry.png`: Permission denied"));
+        assert!(report.contains("- Duration: not finished"));
+        assert!(report.contains("- API usage: no API requests"));
+    }
+
+    #[test]
+    fn names_are_escaped_in_the_html_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = html_report(&run(dir.path()));
+
+        assert!(report.contains("&lt;script&gt;.png"), "{}", report);
+        assert!(report.contains("Tom &amp; Jerry.png"));
+        assert!(report.contains("say &quot;hi&quot;.png"));
+        assert!(report.contains("a &quot;quoted&quot; &lt;reason&gt;"));
+        assert!(!report.contains("<script>"));
+        assert!(!report.contains("Tom & Jerry"));
+        assert!(report.contains("<figure class=\"moved\">"));
+        assert!(report.contains("<figure class=\"move-failed\">"));
+        assert!(report.contains("<figure class=\"not-moved\">"));
+        assert!(report.contains("<b>2</b> Failed"));
+    }
+
+    #[test]
+    fn escaping_covers_html_and_markdown_specials() {
+        assert_eq!(
+            escape("<a href=\"x\">Tom & Jerry</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&lt;/a&gt;"
+        );
+        assert_eq!(escape("&lt;"), "&amp;lt;");
+        assert_eq!(markdown_cell("Fun | Games"), "Fun \\| Games");
+        assert_eq!(markdown_cell("Code"), "Code");
+    }
+}
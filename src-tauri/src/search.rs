@@ -0,0 +1,591 @@
+//! Local full-text search over organized screenshots.
+//!
+//! A tantivy index keyed by the file's current path, covering filename,
+//! category, reasoning, the text the model read off the screenshot, rich
+//! description and capture date. Accepted proposals are upserted as they are
+//! moved; undone moves are removed again. Changes are committed together, by
+//! `commit` after a batch of moves or at the latest every couple of seconds.
+
+use crate::capture_date;
+use chrono::{DateTime as ChronoDateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tantivy::collector::{Count, TopDocs};
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, RangeQuery, TermQuery};
+use tantivy::schema::{
+    DateOptions, Facet, FacetOptions, Field, IndexRecordOption, Schema, Value, FAST, INDEXED,
+    STORED, STRING, TEXT,
+};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{DateTime, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+
+const WRITER_MEMORY_BYTES: usize = 50_000_000;
+const DEFAULT_LIMIT: usize = 20;
+const COMMIT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Everything indexed about one organized screenshot.
+pub struct SearchDocument {
+    pub path: String,
+    pub original_name: String,
+    pub category: String,
+    pub reasoning: String,
+    /// Text the model read off the screenshot.
+    pub visible_text: String,
+    pub description: String,
+    pub captured_at: Option<NaiveDateTime>,
+    pub run_id: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct SearchFilters {
+    /// Category or category prefix, in any case, e.g. `finance` matches
+    /// `Finance/Invoices`.
+    pub category: Option<String>,
+    /// Capture date lower bound, `YYYY-MM-DD` or RFC 3339.
+    pub from: Option<String>,
+    /// Capture date upper bound, `YYYY-MM-DD` or RFC 3339.
+    pub to: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SearchHit {
+    pub path: String,
+    pub filename: String,
+    pub category: String,
+    pub captured_at: Option<String>,
+    pub score: f32,
+    /// HTML fragment with matches wrapped in `<b>`.
+    pub snippet: String,
+}
+
+struct Fields {
+    path: Field,
+    filename: Field,
+    original_name: Field,
+    category: Field,
+    category_facet: Field,
+    reasoning: Field,
+    visible_text: Field,
+    description: Field,
+    captured_at: Field,
+    run_id: Field,
+}
+
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    // None when opened read-only.
+    writer: Option<Arc<StagedWriter>>,
+    fields: Fields,
+}
+
+// Writer whose changes wait for the next commit. The last one is made when
+// the index is dropped.
+struct StagedWriter {
+    writer: Mutex<IndexWriter>,
+    reader: IndexReader,
+    dirty: AtomicBool,
+}
+
+impl StagedWriter {
+    fn commit(&self) -> Result<(), String> {
+        let mut writer = self.writer.lock().unwrap();
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        if let Err(e) = writer.commit() {
+            self.dirty.store(true, Ordering::SeqCst);
+            return Err(e.to_string());
+        }
+        self.reader.reload().map_err(|e| e.to_string())
+    }
+}
+
+impl Drop for StagedWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.commit() {
+            eprintln!("[RUST] ⚠️ Failed to commit search index: {}", e);
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct CategoryCount {
+    pub category: String,
+    pub count: usize,
+}
+
+impl SearchIndex {
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        let (schema, fields) = Self::schema();
+        let index = match Self::open_index(dir, schema.clone()) {
+            Ok(index) => index,
+            // The index only mirrors data kept elsewhere; an older schema is
+            // rebuilt from scratch rather than blocking startup.
+            Err(tantivy::TantivyError::SchemaError(e)) => {
+                eprintln!("[RUST] ⚠️ Recreating search index: {}", e);
+                std::fs::remove_dir_all(dir).map_err(|e| e.to_string())?;
+                Self::open_index(dir, schema).map_err(|e| e.to_string())?
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .map_err(|e: tantivy::TantivyError| e.to_string())?;
+        let writer = Arc::new(StagedWriter {
+            writer: Mutex::new(
+                index
+                    .writer(WRITER_MEMORY_BYTES)
+                    .map_err(|e| e.to_string())?,
+            ),
+            reader: reader.clone(),
+            dirty: AtomicBool::new(false),
+        });
+
+        // Moves made one at a time from the app are committed in bulk here.
+        let staged = Arc::downgrade(&writer);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(COMMIT_INTERVAL);
+            let Some(writer) = staged.upgrade() else {
+                break;
+            };
+            if let Err(e) = writer.commit() {
+                eprintln!("[RUST] ⚠️ Failed to commit search index: {}", e);
+            }
+        });
+
+        Ok(SearchIndex {
+            index,
+            reader,
+            writer: Some(writer),
+            fields,
+        })
+    }
+
+    /// Search only, without taking the writer lock, so another process (the
+    /// desktop app) can keep indexing. Its commits show up automatically.
+    pub fn open_read_only(dir: &Path) -> Result<Self, String> {
+        let (schema, fields) = Self::schema();
+        let index = Index::open(MmapDirectory::open(dir).map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+        if index.schema() != schema {
+            return Err("Search index is from another version, open the app to rebuild it".into());
+        }
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommitWithDelay)
+            .try_into()
+            .map_err(|e: tantivy::TantivyError| e.to_string())?;
+
+        Ok(SearchIndex {
+            index,
+            reader,
+            writer: None,
+            fields,
+        })
+    }
+
+    fn schema() -> (Schema, Fields) {
+        let mut builder = Schema::builder();
+        let fields = Fields {
+            path: builder.add_text_field("path", STRING | STORED),
+            filename: builder.add_text_field("filename", TEXT | STORED),
+            original_name: builder.add_text_field("original_name", TEXT | STORED),
+            category: builder.add_text_field("category", TEXT | STORED),
+            category_facet: builder.add_facet_field("category_facet", FacetOptions::default()),
+            reasoning: builder.add_text_field("reasoning", TEXT | STORED),
+            visible_text: builder.add_text_field("visible_text", TEXT | STORED),
+            description: builder.add_text_field("description", TEXT | STORED),
+            captured_at: builder
+                .add_date_field("captured_at", DateOptions::from(INDEXED) | STORED | FAST),
+            run_id: builder.add_text_field("run_id", STRING | STORED),
+        };
+        (builder.build(), fields)
+    }
+
+    fn staged(&self) -> Result<&StagedWriter, String> {
+        self.writer
+            .as_deref()
+            .ok_or_else(|| "Search index is open read-only".to_string())
+    }
+
+    fn open_index(dir: &Path, schema: Schema) -> tantivy::Result<Index> {
+        std::fs::create_dir_all(dir)?;
+        Index::open_or_create(MmapDirectory::open(dir)?, schema)
+    }
+
+    /// Add a document, replacing any previous one at the same path. The
+    /// category is indexed without the date folders of the run's layout.
+    pub fn upsert(&self, document: &SearchDocument) -> Result<(), String> {
+        let f = &self.fields;
+        let filename = Path::new(&document.path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let category = capture_date::category_of(&document.category);
+
+        let mut doc = TantivyDocument::default();
+        doc.add_text(f.path, &document.path);
+        doc.add_text(f.filename, &filename);
+        doc.add_text(f.original_name, &document.original_name);
+        doc.add_text(f.category, &category);
+        doc.add_facet(f.category_facet, category_facet(&category));
+        doc.add_text(f.reasoning, &document.reasoning);
+        doc.add_text(f.visible_text, &document.visible_text);
+        doc.add_text(f.description, &document.description);
+        if let Some(captured_at) = document.captured_at {
+            doc.add_date(f.captured_at, to_tantivy_date(captured_at));
+        }
+        if let Some(run_id) = &document.run_id {
+            doc.add_text(f.run_id, run_id);
+        }
+
+        let staged = self.staged()?;
+        let writer = staged.writer.lock().unwrap();
+        writer.delete_term(Term::from_field_text(f.path, &document.path));
+        writer.add_document(doc).map_err(|e| e.to_string())?;
+        staged.dirty.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn remove(&self, path: &str) -> Result<(), String> {
+        let staged = self.staged()?;
+        let writer = staged.writer.lock().unwrap();
+        writer.delete_term(Term::from_field_text(self.fields.path, path));
+        staged.dirty.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Make staged changes searchable now instead of at the next periodic
+    /// commit.
+    pub fn commit(&self) -> Result<(), String> {
+        self.staged()?.commit()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reader.searcher().num_docs() == 0
+    }
+
+    /// Whether a committed document is indexed for `path`.
+    pub fn contains(&self, path: &str) -> Result<bool, String> {
+        let query = TermQuery::new(
+            Term::from_field_text(self.fields.path, path),
+            IndexRecordOption::Basic,
+        );
+        let count = self
+            .reader
+            .searcher()
+            .search(&query, &Count)
+            .map_err(|e| e.to_string())?;
+        Ok(count > 0)
+    }
+
+    /// Ranked hits for a free-text query. An empty query lists everything
+    /// matching the filters.
+    pub fn search(&self, query: &str, filters: &SearchFilters) -> Result<Vec<SearchHit>, String> {
+        let f = &self.fields;
+        let searcher = self.reader.searcher();
+
+        let mut parser = QueryParser::for_index(
+            &self.index,
+            vec![
+                f.filename,
+                f.original_name,
+                f.category,
+                f.reasoning,
+                f.visible_text,
+                f.description,
+            ],
+        );
+        parser.set_conjunction_by_default();
+        parser.set_field_boost(f.filename, 2.0);
+        parser.set_field_boost(f.category, 1.5);
+
+        let text_query: Box<dyn Query> = if query.trim().is_empty() {
+            Box::new(AllQuery)
+        } else {
+            // Lenient parsing: stray quotes or colons in user input still search.
+            parser.parse_query_lenient(query).0
+        };
+
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, text_query.box_clone())];
+        if let Some(category) = filters.category.as_deref().filter(|c| !c.trim().is_empty()) {
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_facet(f.category_facet, &category_facet(category)),
+                    IndexRecordOption::Basic,
+                )),
+            ));
+        }
+        if filters.from.is_some() || filters.to.is_some() {
+            let bound = |value: &Option<String>, time: NaiveTime| -> Result<Bound<Term>, String> {
+                Ok(match value.as_deref() {
+                    Some(value) => Bound::Included(Term::from_field_date_for_search(
+                        f.captured_at,
+                        to_tantivy_date(parse_date_bound(value, time)?),
+                    )),
+                    None => Bound::Unbounded,
+                })
+            };
+            let end_of_day = NaiveTime::from_hms_opt(23, 59, 59).unwrap_or(NaiveTime::MIN);
+            clauses.push((
+                Occur::Must,
+                Box::new(RangeQuery::new(
+                    bound(&filters.from, NaiveTime::MIN)?,
+                    bound(&filters.to, end_of_day)?,
+                )),
+            ));
+        }
+
+        let combined = BooleanQuery::new(clauses);
+        let limit = filters.limit.unwrap_or(DEFAULT_LIMIT).max(1);
+        let top_docs = searcher
+            .search(&combined, &TopDocs::with_limit(limit))
+            .map_err(|e| e.to_string())?;
+
+        let snippet_fields = [
+            f.visible_text,
+            f.description,
+            f.reasoning,
+            f.filename,
+            f.original_name,
+        ];
+        let generators = snippet_fields
+            .iter()
+            .filter_map(|&field| SnippetGenerator::create(&searcher, &*text_query, field).ok())
+            .collect::<Vec<SnippetGenerator>>();
+
+        let mut hits = Vec::new();
+        for (score, address) in top_docs {
+            let doc: TantivyDocument = searcher.doc(address).map_err(|e| e.to_string())?;
+            let text = |field: Field| {
+                doc.get_first(field)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+
+            let snippet = generators
+                .iter()
+                .map(|generator| generator.snippet_from_doc(&doc))
+                .find(|snippet| !snippet.highlighted().is_empty())
+                .map(|snippet| snippet.to_html())
+                .unwrap_or_else(|| text(f.reasoning));
+
+            hits.push(SearchHit {
+                path: text(f.path),
+                filename: text(f.filename),
+                category: text(f.category),
+                captured_at: doc
+                    .get_first(f.captured_at)
+                    .and_then(|value| value.as_datetime())
+                    .and_then(|date| ChronoDateTime::from_timestamp(date.into_timestamp_secs(), 0))
+                    .map(|date| date.naive_utc().format("%Y-%m-%dT%H:%M:%S").to_string()),
+                score,
+                snippet,
+            });
+        }
+        Ok(hits)
+    }
+
+    /// Every category with the number of screenshots filed under it, most
+    /// used first.
+    pub fn categories(&self) -> Result<Vec<CategoryCount>, String> {
+        let searcher = self.reader.searcher();
+        let total = searcher.num_docs() as usize;
+        if total == 0 {
+            return Ok(Vec::new());
+        }
+        let top_docs = searcher
+            .search(&AllQuery, &TopDocs::with_limit(total))
+            .map_err(|e| e.to_string())?;
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for (_, address) in top_docs {
+            let doc: TantivyDocument = searcher.doc(address).map_err(|e| e.to_string())?;
+            let category = doc
+                .get_first(self.fields.category)
+                .and_then(|value| value.as_str())
+                .unwrap_or_default();
+            *counts.entry(category.to_string()).or_default() += 1;
+        }
+        let mut categories = counts
+            .into_iter()
+            .map(|(category, count)| CategoryCount { category, count })
+            .collect::<Vec<CategoryCount>>();
+        categories.sort_by(|a, b| b.count.cmp(&a.count).then(a.category.cmp(&b.category)));
+        Ok(categories)
+    }
+}
+
+// Lowercased, so filters match whatever case the model or the user chose
+fn category_facet(category: &str) -> Facet {
+    let parts = category
+        .split('/')
+        .map(|part| part.trim().to_lowercase())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<String>>();
+    Facet::from_path(parts)
+}
+
+// Capture times are local wall-clock times; they are stored as if UTC so the
+// same conversion works for indexing and filtering.
+fn to_tantivy_date(value: NaiveDateTime) -> DateTime {
+    DateTime::from_timestamp_secs(value.and_utc().timestamp())
+}
+
+fn parse_date_bound(value: &str, time_of_day: NaiveTime) -> Result<NaiveDateTime, String> {
+    if let Ok(timestamp) = ChronoDateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.naive_local());
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(time_of_day))
+        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(path: &str, category: &str, visible_text: &str) -> SearchDocument {
+        SearchDocument {
+            path: path.to_string(),
+            original_name: "Screenshot 2025-03-14 at 10.22.33.png".to_string(),
+            category: category.to_string(),
+            reasoning: "payment receipt".to_string(),
+            visible_text: visible_text.to_string(),
+            description: String::new(),
+            captured_at: capture_date::parse_filename("Screenshot 2025-03-14 at 10.22.33.png")
+                .map(|date| date.captured_at),
+            run_id: None,
+        }
+    }
+
+    fn in_category(category: &str) -> SearchFilters {
+        SearchFilters {
+            category: Some(category.to_string()),
+            ..SearchFilters::default()
+        }
+    }
+
+    fn paths(hits: Vec<SearchHit>) -> Vec<String> {
+        let mut paths = hits
+            .into_iter()
+            .map(|hit| hit.path)
+            .collect::<Vec<String>>();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn changes_show_up_once_committed() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        index
+            .upsert(&document(
+                "/shots/Finance/receipt.png",
+                "Finance",
+                "Stripe invoice",
+            ))
+            .unwrap();
+        assert!(index.is_empty());
+        assert!(!index.contains("/shots/Finance/receipt.png").unwrap());
+
+        index.commit().unwrap();
+        assert!(index.contains("/shots/Finance/receipt.png").unwrap());
+        let hits = index.search("stripe", &SearchFilters::default()).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].snippet.contains("<b>Stripe</b>"));
+
+        // Replaced in place, then removed
+        index
+            .upsert(&document(
+                "/shots/Finance/receipt.png",
+                "Finance",
+                "Paddle receipt",
+            ))
+            .unwrap();
+        index.commit().unwrap();
+        assert!(index
+            .search("stripe", &SearchFilters::default())
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            index
+                .search("paddle", &SearchFilters::default())
+                .unwrap()
+                .len(),
+            1
+        );
+        index.remove("/shots/Finance/receipt.png").unwrap();
+        index.commit().unwrap();
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn staged_changes_are_committed_when_the_index_closes() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        index
+            .upsert(&document("/shots/Code/build.png", "Code", "cargo build"))
+            .unwrap();
+        drop(index);
+
+        let reopened = SearchIndex::open_read_only(dir.path()).unwrap();
+        assert!(reopened.contains("/shots/Code/build.png").unwrap());
+    }
+
+    #[test]
+    fn category_filter_ignores_date_folders_and_case() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        for (path, category) in [
+            ("/a.png", "2025/03/Finance/Invoices"),
+            ("/b.png", "Finance/2025/03"),
+            ("/c.png", "finance/Receipts"),
+            ("/d.png", "Code"),
+        ] {
+            index.upsert(&document(path, category, "")).unwrap();
+        }
+        index.commit().unwrap();
+
+        assert_eq!(
+            paths(index.search("", &in_category("Finance")).unwrap()),
+            ["/a.png", "/b.png", "/c.png"]
+        );
+        assert_eq!(
+            paths(index.search("", &in_category("FINANCE/invoices")).unwrap()),
+            ["/a.png"]
+        );
+        assert!(index
+            .search("", &in_category("Invoices"))
+            .unwrap()
+            .is_empty());
+        assert!(index.search("", &in_category("2025")).unwrap().is_empty());
+
+        let hits = index.search("", &in_category("finance/invoices")).unwrap();
+        assert_eq!(hits[0].category, "Finance/Invoices");
+        assert_eq!(hits[0].captured_at.as_deref(), Some("2025-03-14T10:22:33"));
+    }
+
+    #[test]
+    fn read_only_indexes_refuse_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        drop(SearchIndex::open(dir.path()).unwrap());
+        let index = SearchIndex::open_read_only(dir.path()).unwrap();
+        assert!(index.upsert(&document("/a.png", "Code", "")).is_err());
+        assert!(index.commit().is_err());
+    }
+}
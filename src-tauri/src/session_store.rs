@@ -0,0 +1,621 @@
+//! Rust-side record of every run: scan root, file list, proposals, user edits
+//! and apply status.
+//!
+//! Each run is an append-only journal at `<dir>/<run_id>.jsonl`. Replaying it
+//! rebuilds the run, so an app restart between scanning and applying loses
+//! nothing, and a crash loses at most the line being written.
+
+use crate::batch::BatchItem;
+use crate::capture_date::FolderLayout;
+use crate::description::DescriptionStorage;
+use crate::pipeline::{FileProposal, SkippedFile};
+use crate::usage::{RequestUsage, RunCost};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SessionEvent {
+    Started {
+        root_path: String,
+        files: Vec<String>,
+        started_at: String,
+        #[serde(flatten)]
+        options: RunOptions,
+    },
+    Proposed {
+        proposal: Box<FileProposal>,
+    },
+    Failed {
+        name: String,
+        // Missing in journals written before errors were recorded
+        #[serde(default)]
+        error: Option<String>,
+    },
+    Skipped {
+        #[serde(flatten)]
+        file: SkippedFile,
+    },
+    /// Left for a later run because the budget was used up.
+    Deferred {
+        name: String,
+    },
+    /// Tokens used by one API request.
+    Usage {
+        #[serde(flatten)]
+        request: RequestUsage,
+    },
+    /// Requests handed to the API as one Message Batch.
+    BatchSubmitted {
+        batch_id: String,
+        items: Vec<BatchItem>,
+    },
+    /// Every result of the batch was recorded.
+    BatchEnded {
+        batch_id: String,
+    },
+    /// Classification of every file is done.
+    Finished {
+        finished_at: String,
+    },
+    Edited {
+        id: String,
+        proposed_name: String,
+        proposed_category: String,
+        selected: bool,
+    },
+    Moved {
+        id: String,
+        from: String,
+        to: String,
+    },
+    MoveFailed {
+        id: String,
+        error: String,
+    },
+}
+
+/// Run settings that still matter when its proposals are applied.
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+pub struct RunOptions {
+    /// Where rich descriptions go; None when they are not generated.
+    #[serde(default)]
+    pub descriptions: Option<DescriptionStorage>,
+    /// Embed SnapSort tags into moved images.
+    #[serde(default)]
+    pub write_tags: bool,
+    /// Folder layout of proposals, needed when batch results arrive later.
+    #[serde(default)]
+    pub layout: FolderLayout,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplyStatus {
+    Pending,
+    Applied,
+    Failed,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SessionProposal {
+    #[serde(flatten)]
+    pub proposal: FileProposal,
+    pub selected: bool,
+    pub status: ApplyStatus,
+    pub final_path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct FailedFile {
+    pub name: String,
+    pub error: Option<String>,
+}
+
+/// A Message Batch of the run and the files behind its requests.
+#[derive(Serialize, Clone)]
+pub struct RunBatch {
+    pub batch_id: String,
+    pub items: Vec<BatchItem>,
+    pub ended: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RunSnapshot {
+    pub run_id: String,
+    pub root_path: String,
+    pub started_at: String,
+    pub files: Vec<String>,
+    pub options: RunOptions,
+    pub proposals: Vec<SessionProposal>,
+    pub failed: Vec<FailedFile>,
+    pub skipped: Vec<SkippedFile>,
+    pub deferred: Vec<String>,
+    pub usage: Vec<RequestUsage>,
+    pub batches: Vec<RunBatch>,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SessionSummary {
+    pub run_id: String,
+    pub root_path: String,
+    pub started_at: String,
+    pub total_files: usize,
+    pub proposed: usize,
+    pub applied: usize,
+    pub failed: usize,
+    pub cost: RunCost,
+    /// Batches still waiting for results.
+    pub pending_batches: usize,
+}
+
+impl RunSnapshot {
+    fn new(run_id: &str) -> Self {
+        RunSnapshot {
+            run_id: run_id.to_string(),
+            root_path: String::new(),
+            started_at: String::new(),
+            files: Vec::new(),
+            options: RunOptions::default(),
+            proposals: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+            deferred: Vec::new(),
+            usage: Vec::new(),
+            batches: Vec::new(),
+            finished_at: None,
+        }
+    }
+
+    fn apply(&mut self, event: &SessionEvent) {
+        match event {
+            SessionEvent::Started {
+                root_path,
+                files,
+                started_at,
+                options,
+            } => {
+                self.root_path = root_path.clone();
+                self.files = files.clone();
+                self.started_at = started_at.clone();
+                self.options = *options;
+            }
+            SessionEvent::Proposed { proposal } => {
+                self.proposals.retain(|p| p.proposal.id != proposal.id);
+                self.proposals.push(SessionProposal {
+                    proposal: (**proposal).clone(),
+                    selected: true,
+                    status: ApplyStatus::Pending,
+                    final_path: None,
+                    error: None,
+                });
+            }
+            SessionEvent::Failed { name, error } => self.failed.push(FailedFile {
+                name: name.clone(),
+                error: error.clone(),
+            }),
+            SessionEvent::Skipped { file } => self.skipped.push(file.clone()),
+            SessionEvent::Deferred { name } => self.deferred.push(name.clone()),
+            SessionEvent::Usage { request } => self.usage.push(request.clone()),
+            SessionEvent::BatchSubmitted { batch_id, items } => self.batches.push(RunBatch {
+                batch_id: batch_id.clone(),
+                items: items.clone(),
+                ended: false,
+            }),
+            SessionEvent::BatchEnded { batch_id } => {
+                if let Some(batch) = self.batches.iter_mut().find(|b| b.batch_id == *batch_id) {
+                    batch.ended = true;
+                }
+            }
+            SessionEvent::Finished { finished_at } => self.finished_at = Some(finished_at.clone()),
+            SessionEvent::Edited {
+                id,
+                proposed_name,
+                proposed_category,
+                selected,
+            } => {
+                if let Some(entry) = self.proposal_mut(id) {
+                    entry.proposal.proposed_name = proposed_name.clone();
+                    entry.proposal.proposed_category = proposed_category.clone();
+                    entry.selected = *selected;
+                }
+            }
+            SessionEvent::Moved { id, to, .. } => {
+                if let Some(entry) = self.proposal_mut(id) {
+                    // Moving a file back to where it started is an undo.
+                    if *to == entry.proposal.original_path {
+                        entry.status = ApplyStatus::Pending;
+                        entry.final_path = None;
+                    } else {
+                        entry.status = ApplyStatus::Applied;
+                        entry.final_path = Some(to.clone());
+                    }
+                    entry.error = None;
+                }
+            }
+            SessionEvent::MoveFailed { id, error } => {
+                if let Some(entry) = self.proposal_mut(id) {
+                    entry.status = ApplyStatus::Failed;
+                    entry.error = Some(error.clone());
+                }
+            }
+        }
+    }
+
+    fn proposal_mut(&mut self, id: &str) -> Option<&mut SessionProposal> {
+        self.proposals.iter_mut().find(|p| p.proposal.id == id)
+    }
+
+    fn summary(&self) -> SessionSummary {
+        let count =
+            |status: ApplyStatus| self.proposals.iter().filter(|p| p.status == status).count();
+        SessionSummary {
+            run_id: self.run_id.clone(),
+            root_path: self.root_path.clone(),
+            started_at: self.started_at.clone(),
+            total_files: self.files.len(),
+            proposed: self.proposals.len(),
+            applied: count(ApplyStatus::Applied),
+            failed: self.failed.len() + count(ApplyStatus::Failed),
+            cost: self.cost(),
+            pending_batches: self.batches.iter().filter(|b| !b.ended).count(),
+        }
+    }
+
+    /// Tokens and cost of every request in the run.
+    pub fn cost(&self) -> RunCost {
+        RunCost::of(&self.usage)
+    }
+}
+
+// Whether a non-empty journal lacks the newline after its last line
+fn ends_torn(file: &mut std::fs::File) -> bool {
+    let mut last = [0u8];
+    file.seek(SeekFrom::End(-1)).is_ok() && file.read_exact(&mut last).is_ok() && last[0] != b'\n'
+}
+
+pub struct SessionStore {
+    dir: PathBuf,
+    // Runs touched since startup, kept in sync with their journals.
+    runs: Mutex<HashMap<String, RunSnapshot>>,
+}
+
+impl SessionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        SessionStore {
+            dir,
+            runs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn new_run_id() -> String {
+        chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string()
+    }
+
+    /// Start a new run and return its ID.
+    pub fn start(
+        &self,
+        root_path: &str,
+        files: &[PathBuf],
+        options: RunOptions,
+    ) -> Result<String, String> {
+        let run_id = Self::new_run_id();
+        self.append(
+            &run_id,
+            SessionEvent::Started {
+                root_path: root_path.to_string(),
+                files: files
+                    .iter()
+                    .map(|path| path.to_string_lossy().to_string())
+                    .collect(),
+                started_at: chrono::Local::now().to_rfc3339(),
+                options,
+            },
+        )?;
+        Ok(run_id)
+    }
+
+    pub fn append(&self, run_id: &str, event: SessionEvent) -> Result<(), String> {
+        let path = self.journal_path(run_id)?;
+        let mut runs = self.runs.lock().unwrap();
+        if !matches!(event, SessionEvent::Started { .. }) && !path.exists() {
+            return Err(format!("Session {} not found", run_id));
+        }
+
+        std::fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let mut line = serde_json::to_string(&event).map_err(|e| e.to_string())?;
+        line.push('\n');
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| e.to_string())?;
+        // A crash mid-write leaves the last line without its newline. End it
+        // first, so the torn line stays on its own and this event is kept.
+        if ends_torn(&mut file) {
+            line.insert(0, '\n');
+        }
+        file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+
+        if let Some(run) = runs.get_mut(run_id) {
+            run.apply(&event);
+        } else {
+            let run = Self::replay(run_id, &path)?;
+            runs.insert(run_id.to_string(), run);
+        }
+        Ok(())
+    }
+
+    pub fn load(&self, run_id: &str) -> Result<RunSnapshot, String> {
+        let path = self.journal_path(run_id)?;
+        let mut runs = self.runs.lock().unwrap();
+        if let Some(run) = runs.get(run_id) {
+            return Ok(run.clone());
+        }
+        if !path.exists() {
+            return Err(format!("Session {} not found", run_id));
+        }
+        let run = Self::replay(run_id, &path)?;
+        runs.insert(run_id.to_string(), run.clone());
+        Ok(run)
+    }
+
+    /// All stored runs, newest first.
+    pub fn list(&self) -> Result<Vec<SessionSummary>, String> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Ok(Vec::new());
+        };
+
+        let mut summaries = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Some(run_id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            match self.load(&run_id) {
+                Ok(run) => summaries.push(run.summary()),
+                Err(e) => eprintln!("[RUST] ⚠️ Skipping unreadable session {}: {}", run_id, e),
+            }
+        }
+
+        summaries.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        Ok(summaries)
+    }
+
+    /// Current state of one proposal, loading the run if needed.
+    pub fn proposal(&self, run_id: &str, id: &str) -> Option<FileProposal> {
+        self.with_run(run_id, |run| {
+            run.proposals
+                .iter()
+                .find(|p| p.proposal.id == id)
+                .map(|p| p.proposal.clone())
+        })?
+    }
+
+    pub fn options(&self, run_id: &str) -> Option<RunOptions> {
+        self.with_run(run_id, |run| run.options)
+    }
+
+    fn with_run<T>(&self, run_id: &str, f: impl FnOnce(&RunSnapshot) -> T) -> Option<T> {
+        if !self.runs.lock().unwrap().contains_key(run_id) {
+            self.load(run_id).ok()?;
+        }
+        let runs = self.runs.lock().unwrap();
+        runs.get(run_id).map(f)
+    }
+
+    pub fn discard(&self, run_id: &str) -> Result<(), String> {
+        let path = self.journal_path(run_id)?;
+        self.runs.lock().unwrap().remove(run_id);
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn journal_path(&self, run_id: &str) -> Result<PathBuf, String> {
+        let valid = !run_id.is_empty()
+            && run_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err("Invalid session ID".to_string());
+        }
+        Ok(self.dir.join(format!("{}.jsonl", run_id)))
+    }
+
+    fn replay(run_id: &str, path: &PathBuf) -> Result<RunSnapshot, String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let mut run = RunSnapshot::new(run_id);
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // A torn last line from a crash is skipped, not fatal.
+            match serde_json::from_str::<SessionEvent>(&line) {
+                Ok(event) => run.apply(&event),
+                Err(e) => eprintln!("[RUST] ⚠️ Bad line in session {}: {}", run_id, e),
+            }
+        }
+        Ok(run)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposal(id: &str) -> FileProposal {
+        FileProposal {
+            id: id.to_string(),
+            original_path: format!("/shots/{}", id),
+            original_name: id.to_string(),
+            proposed_name: format!("renamed_{}", id),
+            proposed_category: "Code".to_string(),
+            reasoning: String::new(),
+            session_id: None,
+            model: "claude-haiku-4-5".to_string(),
+            visible_text: String::new(),
+            confidence: Some(0.9),
+            description: None,
+            redactions: Vec::new(),
+        }
+    }
+
+    fn proposed(id: &str) -> SessionEvent {
+        SessionEvent::Proposed {
+            proposal: Box::new(proposal(id)),
+        }
+    }
+
+    fn ids(run: &RunSnapshot) -> Vec<&str> {
+        run.proposals
+            .iter()
+            .map(|p| p.proposal.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn a_reopened_store_replays_the_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().to_path_buf());
+        let files = [PathBuf::from("/shots/a.png"), PathBuf::from("/shots/b.png")];
+        let run_id = store
+            .start("/shots", &files, RunOptions::default())
+            .unwrap();
+        store.append(&run_id, proposed("a.png")).unwrap();
+        store.append(&run_id, proposed("b.png")).unwrap();
+        store
+            .append(
+                &run_id,
+                SessionEvent::Edited {
+                    id: "b.png".to_string(),
+                    proposed_name: "login.png".to_string(),
+                    proposed_category: "Web".to_string(),
+                    selected: false,
+                },
+            )
+            .unwrap();
+        store
+            .append(
+                &run_id,
+                SessionEvent::Moved {
+                    id: "a.png".to_string(),
+                    from: "/shots/a.png".to_string(),
+                    to: "/shots/Code/renamed_a.png".to_string(),
+                },
+            )
+            .unwrap();
+        store
+            .append(
+                &run_id,
+                SessionEvent::Deferred {
+                    name: "c.png".to_string(),
+                },
+            )
+            .unwrap();
+
+        let reopened = SessionStore::new(dir.path().to_path_buf());
+        let run = reopened.load(&run_id).unwrap();
+        assert_eq!(run.root_path, "/shots");
+        assert_eq!(run.files, ["/shots/a.png", "/shots/b.png"]);
+        assert_eq!(ids(&run), ["a.png", "b.png"]);
+        assert_eq!(run.proposals[0].status, ApplyStatus::Applied);
+        assert_eq!(
+            run.proposals[0].final_path.as_deref(),
+            Some("/shots/Code/renamed_a.png")
+        );
+        assert_eq!(run.proposals[1].proposal.proposed_name, "login.png");
+        assert_eq!(run.proposals[1].proposal.proposed_category, "Web");
+        assert!(!run.proposals[1].selected);
+        assert_eq!(run.deferred, ["c.png"]);
+
+        let summaries = reopened.list().unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].proposed, 2);
+        assert_eq!(summaries[0].applied, 1);
+    }
+
+    #[test]
+    fn moving_a_file_back_undoes_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().to_path_buf());
+        let run_id = store.start("/shots", &[], RunOptions::default()).unwrap();
+        store.append(&run_id, proposed("a.png")).unwrap();
+        for (from, to) in [
+            ("/shots/a.png", "/shots/Code/renamed_a.png"),
+            ("/shots/Code/renamed_a.png", "/shots/a.png"),
+        ] {
+            store
+                .append(
+                    &run_id,
+                    SessionEvent::Moved {
+                        id: "a.png".to_string(),
+                        from: from.to_string(),
+                        to: to.to_string(),
+                    },
+                )
+                .unwrap();
+        }
+
+        let run = SessionStore::new(dir.path().to_path_buf())
+            .load(&run_id)
+            .unwrap();
+        assert_eq!(run.proposals[0].status, ApplyStatus::Pending);
+        assert_eq!(run.proposals[0].final_path, None);
+    }
+
+    #[test]
+    fn a_torn_last_line_does_not_swallow_the_next_event() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().to_path_buf());
+        let run_id = store.start("/shots", &[], RunOptions::default()).unwrap();
+        store.append(&run_id, proposed("a.png")).unwrap();
+
+        // The app dies halfway through writing the next event
+        let journal = dir.path().join(format!("{}.jsonl", run_id));
+        let torn = serde_json::to_string(&proposed("b.png")).unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&journal)
+            .unwrap();
+        file.write_all(&torn.as_bytes()[..torn.len() / 2]).unwrap();
+        drop(file);
+
+        // Resumed after a restart, the run keeps going
+        let resumed = SessionStore::new(dir.path().to_path_buf());
+        assert_eq!(ids(&resumed.load(&run_id).unwrap()), ["a.png"]);
+        resumed.append(&run_id, proposed("c.png")).unwrap();
+        resumed.append(&run_id, proposed("d.png")).unwrap();
+
+        let run = SessionStore::new(dir.path().to_path_buf())
+            .load(&run_id)
+            .unwrap();
+        assert_eq!(ids(&run), ["a.png", "c.png", "d.png"]);
+        let text = std::fs::read_to_string(&journal).unwrap();
+        assert_eq!(text.lines().count(), 5);
+        assert!(text.ends_with('\n'));
+    }
+
+    #[test]
+    fn unknown_and_malformed_run_ids_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().to_path_buf());
+        assert!(store.load("20250314-102233-000").is_err());
+        assert!(store
+            .append("20250314-102233-000", proposed("a.png"))
+            .is_err());
+        assert!(store.load("../../etc/passwd").is_err());
+        assert!(store.list().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+//! The few sentences shown at the end of a run. The local template covers
+//! every field and needs no network; the model only writes the summary when
+//! the user asks for it, and the template stands in if that call fails.
+
+use crate::http_client::ApiClient;
+use crate::usage;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct SummaryRequest {
+    pub total: u32,
+    pub processed: u32,
+    pub skipped: u32,
+    pub conflicts: u32,
+    pub folders: u32,
+    pub duration_seconds: u32,
+    pub top_categories: Vec<(String, u32)>,
+    /// Left out by callers that do not track usage.
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+}
+
+/// Templated summary: what was organized, where it went, and what is left.
+pub fn local_summary(request: &SummaryRequest) -> String {
+    if request.total == 0 {
+        return "No screenshots were found to organize.".to_string();
+    }
+    let mut sentences = Vec::new();
+
+    let organized = if request.processed == request.total {
+        format!("Organized {}", plural(request.total, "screenshot"))
+    } else {
+        format!(
+            "Organized {} of {}",
+            request.processed,
+            plural(request.total, "screenshot")
+        )
+    };
+    sentences.push(format!(
+        "{} into {} in {}.",
+        organized,
+        plural(request.folders, "folder"),
+        format_duration(u64::from(request.duration_seconds))
+    ));
+
+    if !request.top_categories.is_empty() {
+        let categories = request
+            .top_categories
+            .iter()
+            .map(|(name, count)| format!("{} ({})", name, count))
+            .collect::<Vec<String>>();
+        sentences.push(format!("Most went to {}.", join_list(&categories)));
+    }
+
+    if request.input_tokens + request.output_tokens > 0 {
+        sentences.push(format!(
+            "Claude read {} and wrote {} tokens{}.",
+            format_tokens(request.input_tokens),
+            format_tokens(request.output_tokens),
+            request
+                .cost_usd
+                .map(|cost| format!(", about {}", usage::format_cost(cost)))
+                .unwrap_or_default()
+        ));
+    }
+
+    sentences.push(match (request.skipped, request.conflicts) {
+        (0, 0) => "Nothing was skipped and no names clashed.".to_string(),
+        (skipped, 0) => format!("{} skipped.", was(skipped)),
+        (0, conflicts) => format!(
+            "{} still {} a decision because the name is taken.",
+            plural(conflicts, "file"),
+            if conflicts == 1 { "needs" } else { "need" }
+        ),
+        (skipped, conflicts) => format!(
+            "{} skipped and {} waiting on a name conflict.",
+            was(skipped),
+            plural(conflicts, "file")
+        ),
+    });
+    sentences.join(" ")
+}
+
+/// Ask `model` for a friendlier 2-3 sentence summary.
+pub async fn model_summary(
+    client: &ApiClient,
+    request: &SummaryRequest,
+    api_key: &str,
+    model: &str,
+) -> Result<String, String> {
+    let categories = request
+        .top_categories
+        .iter()
+        .map(|(name, count)| format!("{} ({})", name, count))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let prompt = format!(
+        "Write a 2-3 sentence, friendly summary of this screenshot organization run. \
+         Keep it concise, helpful, and warm (no hype). \
+         Stats: processed {processed} of {total}, skipped {skipped}, conflicts {conflicts}, \
+         folders {folders}, duration {duration}s, \
+         tokens {input_tokens} in / {output_tokens} out, cost {cost}. \
+         Top categories: {categories}.",
+        processed = request.processed,
+        total = request.total,
+        skipped = request.skipped,
+        conflicts = request.conflicts,
+        folders = request.folders,
+        duration = request.duration_seconds,
+        input_tokens = request.input_tokens,
+        output_tokens = request.output_tokens,
+        cost = request
+            .cost_usd
+            .map(usage::format_cost)
+            .unwrap_or_else(|| "unknown".to_string()),
+        categories = categories
+    );
+
+    let request_body = serde_json::json!({
+        "model": model,
+        "max_tokens": 200,
+        "messages": [{
+            "role": "user",
+            "content": [{ "type": "text", "text": prompt }]
+        }]
+    });
+
+    let res = client
+        .post("/v1/messages", api_key)
+        .json(&request_body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let json: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+    if let Some(content) = json["content"][0]["text"].as_str() {
+        return Ok(content.trim().to_string());
+    }
+    if let Some(message) = json["error"]["message"].as_str() {
+        return Err(message.to_string());
+    }
+
+    Err("Failed to generate summary".to_string())
+}
+
+/// "45s", "3m 5s" or "1h 2m".
+pub fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// "950", "12.4k" or "1.2M".
+pub fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+fn plural(count: u32, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+fn was(count: u32) -> String {
+    format!(
+        "{} {}",
+        plural(count, "file"),
+        if count == 1 { "was" } else { "were" }
+    )
+}
+
+// "a", "a and b", "a, b and c"
+fn join_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
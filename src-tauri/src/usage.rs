@@ -0,0 +1,388 @@
+//! Token usage of API requests and what it costs.
+//!
+//! Every request's `usage` block is recorded in the run journal with the
+//! files it covered. Prices are per million tokens; the built-in table can
+//! be overridden per model in `prices.json` in the data directory. Prompt
+//! cache writes and reads are billed at a multiple of the input price
+//! unless a model lists its own cache prices.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The `usage` block of a Messages API response. `input_tokens` leaves out
+/// the tokens written to or read from the prompt cache.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn add(&mut self, other: TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input_tokens == 0
+            && self.output_tokens == 0
+            && self.cache_creation_input_tokens == 0
+            && self.cache_read_input_tokens == 0
+    }
+}
+
+// Cache prices relative to input, for the default five-minute cache
+const CACHE_WRITE_FACTOR: f64 = 1.25;
+const CACHE_READ_FACTOR: f64 = 0.1;
+
+/// Message Batches are billed at half the list price.
+pub const BATCH_PRICE_FACTOR: f64 = 0.5;
+
+/// USD per million tokens.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+    /// Defaults to 1.25 times the input price.
+    #[serde(default)]
+    pub cache_write_per_mtok: Option<f64>,
+    /// Defaults to a tenth of the input price.
+    #[serde(default)]
+    pub cache_read_per_mtok: Option<f64>,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        let cache_write = self
+            .cache_write_per_mtok
+            .unwrap_or(self.input_per_mtok * CACHE_WRITE_FACTOR);
+        let cache_read = self
+            .cache_read_per_mtok
+            .unwrap_or(self.input_per_mtok * CACHE_READ_FACTOR);
+        (usage.input_tokens as f64 * self.input_per_mtok
+            + usage.cache_creation_input_tokens as f64 * cache_write
+            + usage.cache_read_input_tokens as f64 * cache_read
+            + usage.output_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Prices keyed by model name or prefix, e.g. `claude-opus-4-5` covers every
+/// dated snapshot of that model.
+#[derive(Clone, Debug)]
+pub struct PriceTable {
+    models: HashMap<String, ModelPrice>,
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        let price = |input_per_mtok, output_per_mtok| ModelPrice {
+            input_per_mtok,
+            output_per_mtok,
+            cache_write_per_mtok: None,
+            cache_read_per_mtok: None,
+        };
+        PriceTable {
+            models: HashMap::from([
+                ("claude-opus-4-5".to_string(), price(5.0, 25.0)),
+                ("claude-opus-4-1".to_string(), price(15.0, 75.0)),
+                ("claude-opus-4".to_string(), price(15.0, 75.0)),
+                ("claude-sonnet-4-5".to_string(), price(3.0, 15.0)),
+                ("claude-sonnet-4".to_string(), price(3.0, 15.0)),
+                ("claude-haiku-4-5".to_string(), price(1.0, 5.0)),
+                ("claude-3-5-haiku".to_string(), price(0.8, 4.0)),
+            ]),
+        }
+    }
+}
+
+impl PriceTable {
+    /// Built-in prices with the entries of `path` on top. A missing file
+    /// is fine; a broken one is reported and ignored.
+    pub fn load(path: &Path) -> PriceTable {
+        let mut table = PriceTable::default();
+        let overrides = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str::<HashMap<String, ModelPrice>>(&text)
+                .map_err(|e| eprintln!("[RUST] ⚠️ Ignoring {}: {}", path.display(), e))
+                .unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        };
+        table.models.extend(overrides);
+        table
+    }
+
+    /// Exact match first, then the longest prefix of `model`.
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        self.models.get(model).copied().or_else(|| {
+            self.models
+                .iter()
+                .filter(|(name, _)| model.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, price)| *price)
+        })
+    }
+
+    /// `None` when the model has no price.
+    pub fn cost(&self, model: &str, usage: &TokenUsage) -> Option<f64> {
+        self.price(model).map(|price| price.cost(usage))
+    }
+}
+
+/// One API request: the files it classified and what it used.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RequestUsage {
+    pub files: Vec<String>,
+    pub model: String,
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+    pub cost_usd: Option<f64>,
+}
+
+/// Totals of a run so far.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct RunCost {
+    pub requests: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+    pub cost_usd: f64,
+    /// Requests to models missing from the price table, left out of `cost_usd`.
+    pub unpriced_requests: u32,
+}
+
+impl RunCost {
+    pub fn add(&mut self, request: &RequestUsage) {
+        self.requests += 1;
+        self.input_tokens += request.usage.input_tokens;
+        self.output_tokens += request.usage.output_tokens;
+        self.cache_creation_input_tokens += request.usage.cache_creation_input_tokens;
+        self.cache_read_input_tokens += request.usage.cache_read_input_tokens;
+        match request.cost_usd {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+
+    pub fn of<'a>(requests: impl IntoIterator<Item = &'a RequestUsage>) -> RunCost {
+        let mut total = RunCost::default();
+        for request in requests {
+            total.add(request);
+        }
+        total
+    }
+}
+
+// The API scales images down to fit these before counting tokens
+const MAX_IMAGE_EDGE: f64 = 1568.0;
+const MAX_IMAGE_PIXELS: f64 = 1_150_000.0;
+
+/// Input tokens of an image of this size, after the API resizes it.
+pub fn image_tokens(width: u32, height: u32) -> u64 {
+    if width == 0 || height == 0 {
+        return 0;
+    }
+    let (width, height) = (f64::from(width), f64::from(height));
+    let scale = (MAX_IMAGE_EDGE / width.max(height))
+        .min((MAX_IMAGE_PIXELS / (width * height)).sqrt())
+        .min(1.0);
+    ((width * scale).floor() * (height * scale).floor() / 750.0).ceil() as u64
+}
+
+/// Rough input tokens of English prompt text, about four characters each.
+pub fn text_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// Shortest prompt prefix `model` will cache. A cache breakpoint on a
+/// shorter prefix is ignored and the prefix is billed as plain input.
+pub fn min_cacheable_tokens(model: &str) -> u64 {
+    if model.contains("opus-4-5") || model.contains("haiku-4-5") {
+        4096
+    } else if model.contains("haiku") {
+        2048
+    } else {
+        1024
+    }
+}
+
+/// Predicted tokens and cost of a run before it starts.
+#[derive(Serialize, Clone, Debug)]
+pub struct CostEstimate {
+    pub files: usize,
+    pub requests: usize,
+    /// Every input token, including the system prompt read from the cache.
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub model: String,
+    /// `None` when the model has no price.
+    pub cost_usd: Option<f64>,
+    /// Model that unsure answers go to next, when the run escalates.
+    pub escalation_model: Option<String>,
+    /// Cost if every request were escalated: the same requests once more on
+    /// `escalation_model`, on top of `cost_usd`. `None` without escalation
+    /// or a price for both models.
+    pub max_cost_usd: Option<f64>,
+    /// Whether the shared system prompt is long enough for the model to
+    /// cache. When it is not, every request pays for it in full.
+    pub prompt_cached: bool,
+}
+
+/// "$0.42", or "<$0.01" for fractions of a cent.
+pub fn format_cost(cost_usd: f64) -> String {
+    if cost_usd > 0.0 && cost_usd < 0.01 {
+        "<$0.01".to_string()
+    } else {
+        format!("${:.2}", cost_usd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: u64, output: u64, cache_write: u64, cache_read: u64) -> TokenUsage {
+        TokenUsage {
+            input_tokens: input,
+            output_tokens: output,
+            cache_creation_input_tokens: cache_write,
+            cache_read_input_tokens: cache_read,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn prices_match_the_longest_model_prefix() {
+        let table = PriceTable::default();
+        let input = |model: &str| table.price(model).map(|price| price.input_per_mtok);
+
+        assert_eq!(input("claude-haiku-4-5"), Some(1.0));
+        assert_eq!(input("claude-haiku-4-5-20251001"), Some(1.0));
+        assert_eq!(input("claude-opus-4-5-20251101"), Some(5.0));
+        assert_eq!(input("claude-opus-4-1-20250805"), Some(15.0));
+        assert_eq!(input("claude-opus-4-20250514"), Some(15.0));
+        assert_eq!(input("gpt-4o"), None);
+        assert_eq!(table.cost("gpt-4o", &usage(1000, 0, 0, 0)), None);
+    }
+
+    #[test]
+    fn cache_tokens_are_billed_at_their_own_price() {
+        let table = PriceTable::default();
+        // 1M each of input, output, cache write and cache read on Sonnet
+        let cost = table
+            .cost(
+                "claude-sonnet-4-5",
+                &usage(1_000_000, 1_000_000, 1_000_000, 1_000_000),
+            )
+            .unwrap();
+        assert_close(cost, 3.0 + 15.0 + 3.75 + 0.3);
+
+        let listed = ModelPrice {
+            input_per_mtok: 3.0,
+            output_per_mtok: 15.0,
+            cache_write_per_mtok: Some(6.0),
+            cache_read_per_mtok: Some(1.0),
+        };
+        assert_close(listed.cost(&usage(0, 0, 500_000, 2_000_000)), 3.0 + 2.0);
+        assert_close(listed.cost(&TokenUsage::default()), 0.0);
+    }
+
+    #[test]
+    fn price_overrides_replace_and_extend_the_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prices.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "claude-haiku-4-5": { "input_per_mtok": 2.0, "output_per_mtok": 10.0 },
+                "local-model": {
+                    "input_per_mtok": 0.5, "output_per_mtok": 1.0,
+                    "cache_read_per_mtok": 0.0
+                }
+            }"#,
+        )
+        .unwrap();
+        let table = PriceTable::load(&path);
+
+        assert_close(
+            table
+                .cost("claude-haiku-4-5", &usage(1_000_000, 0, 0, 0))
+                .unwrap(),
+            2.0,
+        );
+        assert_close(
+            table
+                .cost("local-model", &usage(0, 0, 0, 1_000_000))
+                .unwrap(),
+            0.0,
+        );
+        assert!(table.price("claude-sonnet-4-5").is_some());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(
+            PriceTable::load(&path)
+                .price("claude-haiku-4-5")
+                .map(|price| price.input_per_mtok),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn run_totals_count_unpriced_requests_apart() {
+        let request = |model: &str, cost_usd: Option<f64>| RequestUsage {
+            files: vec!["a.png".to_string()],
+            model: model.to_string(),
+            usage: usage(1000, 50, 200, 4000),
+            cost_usd,
+        };
+        let total = RunCost::of(&[
+            request("claude-haiku-4-5", Some(0.25)),
+            request("claude-haiku-4-5", Some(0.5)),
+            request("local-model", None),
+        ]);
+
+        assert_eq!(total.requests, 3);
+        assert_eq!(total.input_tokens, 3000);
+        assert_eq!(total.output_tokens, 150);
+        assert_eq!(total.cache_creation_input_tokens, 600);
+        assert_eq!(total.cache_read_input_tokens, 12000);
+        assert_close(total.cost_usd, 0.75);
+        assert_eq!(total.unpriced_requests, 1);
+    }
+
+    #[test]
+    fn images_are_counted_after_the_api_resizes_them() {
+        assert_eq!(image_tokens(0, 100), 0);
+        assert_eq!(image_tokens(200, 200), 54);
+        // 1000x1000 is under both limits and kept as is
+        assert_eq!(image_tokens(1000, 1000), 1000 * 1000 / 750 + 1);
+        // Long edge capped at 1568
+        assert_eq!(image_tokens(3136, 200), (1568 * 100u64).div_ceil(750));
+    }
+
+    #[test]
+    fn costs_under_a_cent_are_not_shown_as_zero() {
+        assert_eq!(format_cost(0.0), "$0.00");
+        assert_eq!(format_cost(0.004), "<$0.01");
+        assert_eq!(format_cost(1.234), "$1.23");
+    }
+}
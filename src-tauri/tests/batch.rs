@@ -0,0 +1,327 @@
+//! Batch mode against a stand-in for the Message Batches API: submission,
+//! the journal, picking up again after a restart and collecting results.
+
+mod common;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri_app_lib::batch;
+use tauri_app_lib::events::{ChannelSink, PipelineEvent};
+use tauri_app_lib::http_client::NetworkConfig;
+use tauri_app_lib::pipeline::{self, Library, RunContext, ScanOptions};
+use tauri_app_lib::privacy::PrivacySettings;
+use tauri_app_lib::session_store::RunOptions;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::Notify;
+
+const BATCH_ID: &str = "msgbatch_test";
+
+#[derive(Default)]
+struct FakeBatches {
+    // Refuse submissions with this message
+    refuse: Option<String>,
+    ended: AtomicBool,
+    polled: Notify,
+    custom_ids: Mutex<Vec<String>>,
+}
+
+async fn create(
+    State(fake): State<Arc<FakeBatches>>,
+    Json(body): Json<Value>,
+) -> (StatusCode, Json<Value>) {
+    if let Some(message) = &fake.refuse {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(
+                json!({ "type": "error", "error": { "type": "invalid_request_error", "message": message } }),
+            ),
+        );
+    }
+    let custom_ids = body["requests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|request| request["custom_id"].as_str().unwrap().to_string())
+        .collect();
+    *fake.custom_ids.lock().unwrap() = custom_ids;
+    (
+        StatusCode::OK,
+        Json(json!({ "id": BATCH_ID, "processing_status": "in_progress" })),
+    )
+}
+
+async fn status(State(fake): State<Arc<FakeBatches>>, Path(id): Path<String>) -> Json<Value> {
+    assert_eq!(id, BATCH_ID);
+    let total = fake.custom_ids.lock().unwrap().len();
+    if !fake.ended.load(Ordering::SeqCst) {
+        fake.polled.notify_one();
+        return Json(json!({
+            "id": id,
+            "processing_status": "in_progress",
+            "request_counts": { "processing": total },
+        }));
+    }
+    Json(json!({
+        "id": id,
+        "processing_status": "ended",
+        "request_counts": { "succeeded": 1, "errored": 1, "expired": total - 2 },
+        "results_url": null,
+    }))
+}
+
+// First request succeeds, the second errored and the rest expired
+async fn results(State(fake): State<Arc<FakeBatches>>) -> String {
+    let custom_ids = fake.custom_ids.lock().unwrap().clone();
+    custom_ids
+        .iter()
+        .enumerate()
+        .map(|(index, custom_id)| {
+            let result = match index {
+                0 => {
+                    let mut message = common::message(&common::classification(
+                        "terminal_output.png",
+                        "Code",
+                    ));
+                    message["model"] = json!("claude-haiku-4-5");
+                    json!({ "type": "succeeded", "message": message })
+                }
+                1 => json!({
+                    "type": "errored",
+                    "error": { "type": "error", "error": { "type": "overloaded_error", "message": "Overloaded" } },
+                }),
+                _ => json!({ "type": "expired" }),
+            };
+            json!({ "custom_id": custom_id, "result": result }).to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+async fn stand_in(fake: Arc<FakeBatches>) -> String {
+    common::serve(
+        Router::new()
+            .route("/v1/messages/batches", post(create))
+            .route("/v1/messages/batches/{id}", get(status))
+            .route("/v1/messages/batches/{id}/results", get(results))
+            .with_state(fake),
+    )
+    .await
+}
+
+// A library in `dir` whose network settings point at `base_url`
+fn library(dir: &std::path::Path, base_url: &str) -> Library {
+    // The environment variable would win over the saved base URL
+    std::env::remove_var("ANTHROPIC_BASE_URL");
+    let library = Library::open(&dir.join("data"));
+    library
+        .set_network(&NetworkConfig {
+            base_url: Some(base_url.to_string()),
+            ..NetworkConfig::default()
+        })
+        .unwrap();
+    library
+}
+
+// Start a batch run over three screenshots and return its ID
+async fn start(
+    library: &Arc<Library>,
+    dir: &std::path::Path,
+) -> (
+    String,
+    tokio::task::JoinHandle<Result<(), String>>,
+    UnboundedReceiver<PipelineEvent>,
+) {
+    let folder = dir.join("shots");
+    std::fs::create_dir(&folder).unwrap();
+    for (seed, name) in ["Screenshot a.png", "Screenshot b.png", "Screenshot c.png"]
+        .into_iter()
+        .enumerate()
+    {
+        common::screenshot(&folder, name, seed as u32 + 1);
+    }
+    let scan = pipeline::collect_files(&folder, None);
+    let run_id = library
+        .sessions
+        .start(
+            &folder.to_string_lossy(),
+            &scan.files,
+            RunOptions::default(),
+        )
+        .unwrap();
+    let run = RunContext {
+        run_id: run_id.clone(),
+        api_key: "test-key".to_string(),
+        layout: Default::default(),
+        descriptions: None,
+        prices: library.prices.clone(),
+        models: library.models(),
+        client: library.client(),
+        privacy: PrivacySettings::default(),
+    };
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    let store = library.sessions.clone();
+    let task = tokio::spawn(pipeline::run_scan(
+        scan,
+        ScanOptions {
+            group_bursts: Some(false),
+            batch: Some(true),
+            ..ScanOptions::default()
+        },
+        run,
+        store,
+        Arc::new(ChannelSink(sender)),
+        None,
+    ));
+    (run_id, task, receiver)
+}
+
+fn drain(receiver: &mut UnboundedReceiver<PipelineEvent>) -> Vec<PipelineEvent> {
+    let mut events = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        events.push(event);
+    }
+    events
+}
+
+#[tokio::test]
+async fn batch_results_are_collected_after_a_restart() {
+    let fake = Arc::new(FakeBatches::default());
+    let base_url = stand_in(fake.clone()).await;
+    let dir = tempfile::tempdir().unwrap();
+    let library = Arc::new(library(dir.path(), &base_url));
+
+    // The app quits while the batch is still in progress
+    let (run_id, task, mut receiver) = start(&library, dir.path()).await;
+    tokio::time::timeout(Duration::from_secs(30), fake.polled.notified())
+        .await
+        .expect("batch status was never checked");
+    task.abort();
+    let _ = task.await;
+    let before = drain(&mut receiver);
+    assert_eq!(
+        before
+            .iter()
+            .filter(|event| matches!(event, PipelineEvent::Processing { .. }))
+            .count(),
+        3
+    );
+
+    let journal = library.sessions.load(&run_id).unwrap();
+    assert_eq!(journal.batches.len(), 1);
+    assert_eq!(journal.batches[0].batch_id, BATCH_ID);
+    assert_eq!(journal.batches[0].items.len(), 3);
+    assert!(!journal.batches[0].ended);
+    assert!(journal.proposals.is_empty());
+    assert_eq!(
+        batch::pending_runs(&library.sessions).unwrap(),
+        std::slice::from_ref(&run_id)
+    );
+
+    // After the restart the batch has ended
+    fake.ended.store(true, Ordering::SeqCst);
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    batch::resume(
+        &run_id,
+        &library,
+        "test-key".to_string(),
+        Arc::new(ChannelSink(sender)),
+    )
+    .await
+    .unwrap();
+    let events = drain(&mut receiver);
+
+    let item_name = |index: usize| {
+        let path = &journal.batches[0].items[index].paths[0];
+        std::path::Path::new(path)
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    };
+    let proposed = events
+        .iter()
+        .filter_map(|event| match event {
+            PipelineEvent::Proposed { proposal } => Some(proposal),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(proposed.len(), 1);
+    assert_eq!(proposed[0].original_name, item_name(0));
+    assert_eq!(proposed[0].proposed_name, "terminal_output.png");
+    assert_eq!(proposed[0].model, "claude-haiku-4-5");
+
+    let mut failed = events
+        .iter()
+        .filter_map(|event| match event {
+            PipelineEvent::Failed { name, error } => Some((name.clone(), error.clone())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    failed.sort();
+    assert_eq!(
+        failed,
+        [
+            (item_name(1), "Overloaded".to_string()),
+            (
+                item_name(2),
+                "batch expired before this request ran".to_string()
+            ),
+        ]
+    );
+    assert!(events.iter().any(|event| matches!(
+        event,
+        PipelineEvent::Usage { total, .. } if total.input_tokens == 1200
+    )));
+
+    let journal = library.sessions.load(&run_id).unwrap();
+    assert!(journal.batches[0].ended);
+    assert_eq!(journal.proposals.len(), 1);
+    assert_eq!(journal.failed.len(), 2);
+    assert!(journal.finished_at.is_some());
+    assert!(batch::pending_runs(&library.sessions).unwrap().is_empty());
+
+    // Collecting again reports nothing twice
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    batch::resume(
+        &run_id,
+        &library,
+        "test-key".to_string(),
+        Arc::new(ChannelSink(sender)),
+    )
+    .await
+    .unwrap();
+    assert!(drain(&mut receiver).is_empty());
+}
+
+#[tokio::test]
+async fn refused_batch_fails_its_files() {
+    let fake = Arc::new(FakeBatches {
+        refuse: Some("Batch too large".to_string()),
+        ..FakeBatches::default()
+    });
+    let base_url = stand_in(fake).await;
+    let dir = tempfile::tempdir().unwrap();
+    let library = Arc::new(library(dir.path(), &base_url));
+
+    let (run_id, task, mut receiver) = start(&library, dir.path()).await;
+    task.await.unwrap().unwrap();
+    let failed = drain(&mut receiver)
+        .into_iter()
+        .filter_map(|event| match event {
+            PipelineEvent::Failed { error, .. } => Some(error),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(failed.len(), 3);
+    assert!(failed[0].starts_with("Batch too large"), "{}", failed[0]);
+
+    let journal = library.sessions.load(&run_id).unwrap();
+    assert!(journal.batches.is_empty());
+    assert_eq!(journal.failed.len(), 3);
+}
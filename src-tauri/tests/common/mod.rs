@@ -0,0 +1,63 @@
+//! Helpers shared by the integration tests: screenshots on disk and a
+//! stand-in for the Anthropic API.
+
+#![allow(dead_code)]
+
+use axum::Router;
+use std::path::{Path, PathBuf};
+use tauri_app_lib::http_client::{ApiClient, NetworkConfig};
+
+/// Serve `router` on a free local port and return its base URL.
+pub async fn serve(router: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{}", address)
+}
+
+/// Client that sends every request to `base_url`, even when
+/// `ANTHROPIC_BASE_URL` is set.
+pub fn client(base_url: &str) -> ApiClient {
+    ApiClient::new(&NetworkConfig::default())
+        .unwrap()
+        .with_base_url(base_url)
+}
+
+/// Write a small PNG named `name` into `dir`. Different `seed`s give images
+/// that are not duplicates of each other.
+pub fn screenshot(dir: &Path, name: &str, seed: u32) -> PathBuf {
+    let image = image::RgbImage::from_fn(64, 64, |x, y| {
+        let cell = 2 + seed * 3;
+        let on = ((x / cell) + (y / (cell + seed))).is_multiple_of(2);
+        if on {
+            image::Rgb([240, 240, 240])
+        } else {
+            image::Rgb([(seed * 50 % 200) as u8, 20, 20])
+        }
+    });
+    let path = dir.join(name);
+    image.save(&path).unwrap();
+    path
+}
+
+/// A Messages API reply whose only text block is `text`.
+pub fn message(text: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": "message",
+        "role": "assistant",
+        "content": [{ "type": "text", "text": text }],
+        "usage": { "input_tokens": 1200, "output_tokens": 60 }
+    })
+}
+
+/// A classification answer for one screenshot.
+pub fn classification(new_filename: &str, category: &str) -> String {
+    serde_json::json!({
+        "new_filename": new_filename,
+        "category": category,
+        "reasoning": "stand-in answer",
+        "visible_text": "cargo build",
+        "confidence": 0.95
+    })
+    .to_string()
+}
@@ -0,0 +1,285 @@
+//! `run_scan` end to end against a stand-in API, observed through the
+//! events it emits.
+
+mod common;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use std::sync::{Arc, Mutex};
+use tauri_app_lib::events::{ChannelSink, PipelineEvent};
+use tauri_app_lib::models::ModelSettings;
+use tauri_app_lib::pipeline::{self, RunContext, ScanOptions};
+use tauri_app_lib::privacy::{NeverUpload, PrivacySettings, RuleKind};
+use tauri_app_lib::session_store::{RunOptions, SessionStore};
+use tauri_app_lib::usage::PriceTable;
+
+#[tokio::test]
+async fn run_scan_reports_every_file_through_the_sink() {
+    let requests = Arc::new(Mutex::new(Vec::<serde_json::Value>::new()));
+    let received = requests.clone();
+    let base_url = common::serve(Router::new().route(
+        "/v1/messages",
+        post(move |Json(body): Json<serde_json::Value>| {
+            received.lock().unwrap().push(body);
+            async {
+                Json(common::message(&common::classification(
+                    "cargo_build_error.png",
+                    "Code",
+                )))
+            }
+        }),
+    ))
+    .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let folder = dir.path().join("shots");
+    std::fs::create_dir(&folder).unwrap();
+    common::screenshot(&folder, "Screenshot terminal.png", 1);
+    common::screenshot(&folder, "Screenshot bank statement.png", 2);
+    common::screenshot(&folder, "Screenshot card statement.png", 3);
+    std::fs::write(
+        folder.join("Screenshot huge.png"),
+        vec![0u8; pipeline::MAX_FILE_SIZE as usize + 1],
+    )
+    .unwrap();
+
+    let store = Arc::new(SessionStore::new(dir.path().join("sessions")));
+    let scan = pipeline::collect_files(&folder, None);
+    let run_id = store
+        .start(
+            &folder.to_string_lossy(),
+            &scan.files,
+            RunOptions::default(),
+        )
+        .unwrap();
+    let run = RunContext {
+        run_id: run_id.clone(),
+        api_key: "test-key".to_string(),
+        layout: Default::default(),
+        descriptions: None,
+        prices: Arc::new(PriceTable::default()),
+        models: ModelSettings::default(),
+        client: common::client(&base_url),
+        privacy: PrivacySettings {
+            never_upload: vec![NeverUpload {
+                kind: RuleKind::Pattern,
+                value: "*statement*".to_string(),
+                category: Some("Finance".to_string()),
+            }],
+            ..PrivacySettings::default()
+        },
+    };
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+    pipeline::run_scan(
+        scan,
+        ScanOptions {
+            group_bursts: Some(false),
+            ..ScanOptions::default()
+        },
+        run,
+        store.clone(),
+        Arc::new(ChannelSink(sender)),
+        Some(1),
+    )
+    .await
+    .unwrap();
+
+    let mut events = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        events.push(event);
+    }
+
+    let skipped = events
+        .iter()
+        .filter_map(|event| match event {
+            PipelineEvent::Skipped { file } => {
+                Some((file.name.as_str(), file.reason.as_str(), file.never_upload))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        skipped,
+        [
+            ("Screenshot huge.png", "exceeds 5MB limit", false),
+            (
+                "Screenshot bank statement.png",
+                "never uploaded: pattern *statement*",
+                true
+            ),
+            (
+                "Screenshot card statement.png",
+                "never uploaded: pattern *statement*",
+                true
+            ),
+        ]
+    );
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, PipelineEvent::Summary { actionable: 3, .. })));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        PipelineEvent::Processing { name } if name == "Screenshot terminal.png"
+    )));
+
+    let proposals = events
+        .iter()
+        .filter_map(|event| match event {
+            PipelineEvent::Proposed { proposal } => Some(proposal),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(proposals.len(), 3);
+    let kept = proposals
+        .iter()
+        .filter(|proposal| proposal.model == "offline")
+        .collect::<Vec<_>>();
+    assert_eq!(kept.len(), 2);
+    assert!(kept
+        .iter()
+        .all(|proposal| proposal.proposed_category == "Finance"));
+    // Nothing is read from protected files, yet each gets its own name
+    assert_ne!(
+        pipeline::destination_path(kept[0]),
+        pipeline::destination_path(kept[1])
+    );
+    let sent = proposals
+        .iter()
+        .find(|proposal| proposal.original_name == "Screenshot terminal.png")
+        .unwrap();
+    assert_eq!(sent.proposed_name, "cargo_build_error.png");
+    assert_eq!(sent.proposed_category, "Code");
+
+    let usage = events
+        .iter()
+        .filter_map(|event| match event {
+            PipelineEvent::Usage { total, .. } => Some(total),
+            _ => None,
+        })
+        .next_back()
+        .unwrap();
+    assert_eq!(usage.input_tokens, 1200);
+    assert_eq!(usage.output_tokens, 60);
+
+    // Only the unprotected file reached the API, and the journal has both
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    // Instructions and filing guide form one cached block
+    let system = &requests[0]["system"][0];
+    assert!(system["text"].as_str().unwrap().contains("Categories:"));
+    assert!(system["text"].as_str().unwrap().contains("# Filing guide"));
+    assert_eq!(system["cache_control"]["type"], "ephemeral");
+    assert_eq!(store.load(&run_id).unwrap().proposals.len(), 3);
+}
+
+// One screenshot classified against a stand-in that gives `reply` to every
+// request; the models asked, in order, and the events of the run.
+async fn escalation_run(
+    reply: String,
+    budget_usd: Option<f64>,
+) -> (Vec<String>, Vec<PipelineEvent>) {
+    let models = Arc::new(Mutex::new(Vec::<String>::new()));
+    let asked = models.clone();
+    let base_url = common::serve(Router::new().route(
+        "/v1/messages",
+        post(move |Json(body): Json<serde_json::Value>| {
+            asked
+                .lock()
+                .unwrap()
+                .push(body["model"].as_str().unwrap().to_string());
+            let reply = reply.clone();
+            async move { Json(common::message(&reply)) }
+        }),
+    ))
+    .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    common::screenshot(dir.path(), "Screenshot terminal.png", 1);
+    let store = Arc::new(SessionStore::new(dir.path().join("sessions")));
+    let scan = pipeline::collect_files(dir.path(), None);
+    let run_id = store
+        .start(
+            &dir.path().to_string_lossy(),
+            &scan.files,
+            RunOptions::default(),
+        )
+        .unwrap();
+    let run = RunContext {
+        run_id,
+        api_key: "test-key".to_string(),
+        layout: Default::default(),
+        descriptions: None,
+        prices: Arc::new(PriceTable::default()),
+        models: ModelSettings::default(),
+        client: common::client(&base_url),
+        privacy: PrivacySettings::default(),
+    };
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    pipeline::run_scan(
+        scan,
+        ScanOptions {
+            budget_usd,
+            ..ScanOptions::default()
+        },
+        run,
+        store,
+        Arc::new(ChannelSink(sender)),
+        Some(1),
+    )
+    .await
+    .unwrap();
+
+    let mut events = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        events.push(event);
+    }
+    let models = models.lock().unwrap().clone();
+    (models, events)
+}
+
+#[tokio::test]
+async fn escalation_is_only_sent_when_it_fits_the_budget() {
+    let defaults = ModelSettings::default();
+    let unsure = serde_json::json!({
+        "new_filename": "terminal_output.png",
+        "category": "Code",
+        "confidence": 0.3
+    })
+    .to_string();
+    let proposed_by = |events: &[PipelineEvent]| {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                PipelineEvent::Proposed { proposal } => Some(proposal.model.clone()),
+                _ => None,
+            })
+            .collect::<Vec<String>>()
+    };
+
+    // An unsure answer goes on to the classify model when nothing limits it
+    let (models, events) = escalation_run(unsure.clone(), None).await;
+    assert_eq!(
+        models,
+        [defaults.first_classify_model(), defaults.classify.as_str()]
+    );
+    assert_eq!(proposed_by(&events), [defaults.classify.as_str()]);
+
+    // The first request fits this budget; the escalation after it does not,
+    // so the unsure answer is kept
+    let (models, events) = escalation_run(unsure, Some(0.01)).await;
+    assert_eq!(models, [defaults.first_classify_model()]);
+    assert_eq!(proposed_by(&events), [defaults.first_classify_model()]);
+
+    // An answer that does not parse leaves nothing to keep
+    let (models, events) = escalation_run("not json".to_string(), Some(0.01)).await;
+    assert_eq!(models, [defaults.first_classify_model()]);
+    assert!(proposed_by(&events).is_empty());
+    assert!(events.iter().any(|event| matches!(
+        event,
+        PipelineEvent::Deferred { name, .. } if name == "Screenshot terminal.png"
+    )));
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, PipelineEvent::Failed { .. })));
+}
@@ -1,16 +1,17 @@
-import { useState, useEffect, useMemo } from "react";
+import { useState, useEffect, useMemo, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { motion, AnimatePresence } from "framer-motion";
-import { Settings, Loader2, Check, Layers, FolderOpen, AlertTriangle } from "lucide-react";
+import { Settings, Loader2, Check, Layers, FolderOpen, AlertTriangle, FileDown, FileUp } from "lucide-react";
 
 import { Button } from "@/components/ui/button";
 import { FolderTree, buildFolderTree } from "@/components/FolderTree";
 import { FileList } from "@/components/FileCard";
 import { PreviewPanel } from "@/components/PreviewPanel";
-import { SettingsSheet } from "@/components/SettingsSheet";
+import { SettingsSheet, type FolderLayout } from "@/components/SettingsSheet";
 import { EditModal } from "@/components/EditModal";
 import { ConflictDialog, type ConflictItem } from "@/components/ConflictDialog";
+import { DuplicatesDialog, type DuplicateGroup } from "@/components/DuplicatesDialog";
 import { FolderPicker } from "@/components/FolderPicker";
 import { PreviewGrid } from "@/components/PreviewGrid";
 import { optimizeFolderStructure, formatCategory, cleanupSubfolders } from "@/lib/categoryMerge";
@@ -29,6 +30,9 @@ interface FileProposal {
   proposed_name: string;
   proposed_category: string;
   reasoning: string;
+  model?: string;
+  // Kinds of what was blacked out before upload
+  redactions?: string[];
   selected: boolean;
 }
 
@@ -36,11 +40,84 @@ interface SkippedFile {
   name: string;
   size: number;
   reason: string;
+  never_upload?: boolean;
+}
+
+interface RunCost {
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_input_tokens: number;
+  cache_read_input_tokens: number;
+  cost_usd: number;
+  unpriced_requests: number;
+}
+
+interface SessionSummary {
+  run_id: string;
+  root_path: string;
+  started_at: string;
+  total_files: number;
+  proposed: number;
+  applied: number;
+  failed: number;
+  pending_batches: number;
+}
+
+interface RunSnapshot {
+  run_id: string;
+  root_path: string;
+  files: string[];
+  proposals: (FileProposal & { status: "pending" | "applied" | "failed" })[];
+  skipped: SkippedFile[];
+}
+
+interface TrashResult {
+  trashed: string[];
+  failed: [string, string][];
+}
+
+interface CostEstimate {
+  files: number;
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  model: string;
+  cost_usd: number | null;
+  escalation_model: string | null;
+  max_cost_usd: number | null;
+  prompt_cached: boolean;
+}
+
+interface BatchStatus {
+  batch_id: string;
+  status: string;
+  request_counts: {
+    processing: number;
+    succeeded: number;
+    errored: number;
+    canceled: number;
+    expired: number;
+  };
+}
+
+// "12/300" requests with a result
+// Image formats a scan picks up, as in the backend
+const IMAGE_EXTENSIONS = ["png", "jpg", "jpeg", "webp"];
+
+function batchProgress(batch: BatchStatus) {
+  const counts = batch.request_counts;
+  const done = counts.succeeded + counts.errored + counts.canceled + counts.expired;
+  return `${done}/${done + counts.processing}`;
+}
+
+function formatCost(cost: number) {
+  return cost > 0 && cost < 0.01 ? "<$0.01" : `$${cost.toFixed(2)}`;
 }
 
 interface ActivityEvent {
   id: string;
-  type: "proposed" | "skipped" | "failed" | "info";
+  type: "proposed" | "skipped" | "kept" | "failed" | "info";
   title: string;
   detail?: string;
   time: number;
@@ -60,7 +137,14 @@ interface MoveRecord {
 
 function App() {
   // Settings
-  const [apiKey, setApiKey] = useState("");
+  // The key stays in the backend's encrypted store; only its presence is known here
+  const [hasApiKey, setHasApiKey] = useState(false);
+  // Spend cap per scan in USD; empty means no cap
+  const [budget, setBudget] = useState("");
+  // Send scans as a Message Batch: half price, results within a day
+  const [batchMode, setBatchMode] = useState(false);
+  // Whether destination folders also get the capture year and month
+  const [layout, setLayout] = useState<FolderLayout>("category");
   const [path, setPath] = useState("/Users/pawan/Desktop");
   const [showSettings, setShowSettings] = useState(false);
   const [showFolderPicker, setShowFolderPicker] = useState(false);
@@ -69,10 +153,22 @@ function App() {
   const [isScanning, setIsScanning] = useState(false);
   const [totalFiles, setTotalFiles] = useState(0);
   const [processedFiles, setProcessedFiles] = useState(0);
+  const [runCost, setRunCost] = useState<RunCost | null>(null);
+  const [estimate, setEstimate] = useState<CostEstimate | null>(null);
+  const [isEstimating, setIsEstimating] = useState(false);
+  const [batchStatus, setBatchStatus] = useState<BatchStatus | null>(null);
+  const resumedBatches = useRef(false);
 
   // Skipped files (over 5MB)
   const [skippedFiles, setSkippedFiles] = useState<SkippedFile[]>([]);
+  // Files a never-upload rule kept from the API; they are classified locally
+  const [keptLocalFiles, setKeptLocalFiles] = useState<SkippedFile[]>([]);
   const [showSkippedNotice, setShowSkippedNotice] = useState(false);
+  // Duplicates the scan skipped, offered for the Trash
+  const [duplicateGroups, setDuplicateGroups] = useState<DuplicateGroup[]>([]);
+  const [showDuplicates, setShowDuplicates] = useState(false);
+  const [isTrashingDuplicates, setIsTrashingDuplicates] = useState(false);
+  const [duplicateError, setDuplicateError] = useState<string | null>(null);
 
   // Preview state (before scanning)
   const [previewFiles, setPreviewFiles] = useState<PreviewFile[]>([]);
@@ -98,7 +194,11 @@ function App() {
   const [isResizingSidebar, setIsResizingSidebar] = useState(false);
   const [activityEvents, setActivityEvents] = useState<ActivityEvent[]>([]);
   const [scanStartedAt, setScanStartedAt] = useState<number | null>(null);
+  const [runId, setRunId] = useState<string | null>(null);
+  const [planFile, setPlanFile] = useState<string | null>(null);
   const [showHome, setShowHome] = useState(true);
+  // Earlier runs whose proposals can be reviewed again
+  const [sessions, setSessions] = useState<SessionSummary[]>([]);
 
   // Animation state for file movement
   const [exitingFileIds, setExitingFileIds] = useState<Set<string>>(new Set());
@@ -159,6 +259,10 @@ function App() {
       });
     };
 
+    const u0 = listen("run-started", (e: any) => {
+      setRunId(e.payload.run_id as string);
+    });
+
     const u1 = listen("scan-summary", (e: any) => {
       setTotalFiles(e.payload as number);
       setProcessedFiles(0);
@@ -196,6 +300,17 @@ function App() {
 
     const u3 = listen("file-skipped", (e: any) => {
       const skipped = e.payload as SkippedFile;
+      if (skipped.never_upload) {
+        setKeptLocalFiles(prev => [...prev, skipped]);
+        pushActivity({
+          id: `kept-${skipped.name}-${Date.now()}`,
+          type: "kept",
+          title: skipped.name,
+          detail: `${skipped.reason}, classified on this device`,
+          time: Date.now(),
+        });
+        return;
+      }
       setSkippedFiles(prev => [...prev, skipped]);
       setShowSkippedNotice(true);
       pushActivity({
@@ -210,6 +325,7 @@ function App() {
     });
 
     return () => {
+      u0.then(f => f());
       u1.then(f => f());
       u2.then(f => f());
       u3.then(f => f());
@@ -260,6 +376,106 @@ function App() {
     };
   }, []);
 
+  useEffect(() => {
+    if (!invoke || !listen) return;
+    const u5 = listen("token-usage", (e: any) => {
+      setRunCost(e.payload.total as RunCost);
+    });
+    const u6 = listen("file-deferred", (e: any) => {
+      setProcessedFiles(prev => prev + 1);
+      setActivityEvents(prev => [
+        {
+          id: `deferred-${e.payload.name}-${Date.now()}`,
+          type: "skipped" as const,
+          title: e.payload.name as string,
+          detail: `Deferred: ${e.payload.reason}`,
+          time: Date.now(),
+        },
+        ...prev,
+      ].slice(0, 120));
+    });
+    const u7 = listen("batch-status", (e: any) => {
+      setBatchStatus(e.payload as BatchStatus);
+    });
+    const u8 = listen("duplicate-groups", (e: any) => {
+      setDuplicateGroups(e.payload as DuplicateGroup[]);
+    });
+    return () => {
+      u5.then(f => f());
+      u6.then(f => f());
+      u7.then(f => f());
+      u8.then(f => f());
+    };
+  }, []);
+
+  useEffect(() => {
+    invoke<boolean>("has_api_key")
+      .then(setHasApiKey)
+      .catch(err => console.error("Failed to check API key:", err));
+  }, []);
+
+  // Collect batches that were still open when the app last quit
+  useEffect(() => {
+    if (!invoke || !hasApiKey || resumedBatches.current) return;
+    resumedBatches.current = true;
+    invoke<string[]>("resume_batches")
+      .then(runIds => {
+        if (runIds.length === 0) return;
+        setShowHome(false);
+        setHasScanned(true);
+        setActivityEvents(prev => [
+          {
+            id: `batches-${Date.now()}`,
+            type: "info" as const,
+            title: `Collecting batch results of ${runIds.length} earlier scan${runIds.length === 1 ? "" : "s"}`,
+            time: Date.now(),
+          },
+          ...prev,
+        ].slice(0, 120));
+      })
+      .catch(err => console.error("Failed to resume batches:", err));
+  }, [hasApiKey]);
+
+  // Recent runs for the home screen
+  useEffect(() => {
+    if (!invoke || !showHome) return;
+    invoke<SessionSummary[]>("list_sessions")
+      .then(setSessions)
+      .catch(err => console.error("Failed to list sessions:", err));
+  }, [showHome]);
+
+  // Reopen the review of an earlier run; files already moved are left out
+  async function resumeSession(runIdToResume: string) {
+    try {
+      const snapshot = await invoke<RunSnapshot>("resume_session", { runId: runIdToResume });
+      const pending = snapshot.proposals
+        .filter(proposal => proposal.status !== "applied")
+        .map(proposal => ({
+          ...proposal,
+          proposed_category: formatCategory(proposal.proposed_category),
+        }));
+      setRunId(snapshot.run_id);
+      setPath(snapshot.root_path);
+      setProposals(pending);
+      setSkippedFiles(snapshot.skipped.filter(file => !file.never_upload));
+      setKeptLocalFiles(snapshot.skipped.filter(file => file.never_upload));
+      setSelectedCategory(null);
+      setSelectedFile(null);
+      setMoveErrors({});
+      setDuplicateGroups([]);
+      setActivityEvents([]);
+      setRunCost(null);
+      setBatchStatus(null);
+      setTotalFiles(snapshot.files.length);
+      setProcessedFiles(snapshot.proposals.length);
+      setHasOptimized(true); // Keep the categories as they were reviewed
+      setShowHome(false);
+      setHasScanned(true);
+    } catch (err) {
+      alert("Error: " + err);
+    }
+  }
+
   // Auto-load desktop thumbnails on mount
   useEffect(() => {
     loadPreviewFiles();
@@ -272,6 +488,11 @@ function App() {
     }
   }, [path]);
 
+  // An estimate only holds for the selection it was made for
+  useEffect(() => {
+    setEstimate(null);
+  }, [path, selectedPreviews]);
+
   async function loadPreviewFiles() {
     setIsLoadingPreviews(true);
     try {
@@ -324,19 +545,27 @@ function App() {
   function sanitizeFileName(name: string) {
     const cleaned = sanitizeSegment(name);
     if (cleaned.length === 0 || cleaned === "." || cleaned === "..") {
-      return "untitled.png";
+      return "untitled";
     }
     return cleaned;
   }
 
-  function ensurePng(name: string) {
-    return name.toLowerCase().endsWith(".png") ? name : `${name}.png`;
+  // Keep the original's extension, replacing an image extension in the name
+  function withImageExtension(name: string, originalPath: string) {
+    const dot = originalPath.lastIndexOf(".");
+    const extension =
+      dot > originalPath.lastIndexOf("/") ? originalPath.slice(dot + 1).toLowerCase() : "png";
+    const nameDot = name.lastIndexOf(".");
+    const nameExtension = nameDot >= 0 ? name.slice(nameDot + 1).toLowerCase() : "";
+    if (nameExtension === extension) return name;
+    const stem = IMAGE_EXTENSIONS.includes(nameExtension) ? name.slice(0, nameDot) : name;
+    return `${stem}.${extension}`;
   }
 
   function getDestinationPath(file: FileProposal, rootPath: string) {
     const parentDir = file.original_path.substring(0, file.original_path.lastIndexOf("/"));
     const safeCategory = sanitizeCategoryPath(file.proposed_category) || "Other";
-    const safeName = ensurePng(sanitizeFileName(file.proposed_name));
+    const safeName = withImageExtension(sanitizeFileName(file.proposed_name), file.original_path);
     const newPath = `${parentDir}/${safeCategory}/${safeName}`;
     return { newPath, safeCategory, safeName, rootPath };
   }
@@ -364,7 +593,10 @@ function App() {
         setSelectedCategory(null);
         setSelectedFile(null);
         setSkippedFiles([]);
+        setKeptLocalFiles([]);
         setShowSkippedNotice(false);
+        setDuplicateGroups([]);
+        setShowDuplicates(false);
         setMoveErrors({});
         setLastMoveBatch([]);
         setLastMoveRoot(null);
@@ -375,6 +607,8 @@ function App() {
         setOverwriteIds(new Set());
         setProcessedPreviewPaths(new Set());
         setActivityEvents([]);
+        setRunCost(null);
+        setBatchStatus(null);
         setShowHome(false);
         setHasScanned(true);
         setHasOptimized(false); // Reset optimization flag
@@ -382,10 +616,15 @@ function App() {
           setPath(options.path);
         }
         setScanStartedAt(Date.now());
+        const budgetUsd = parseFloat(budget);
         await invoke("start_watch", {
           path: scanPath,
-          apiKey,
           selectedPaths: selectedPaths && selectedPaths.length > 0 ? selectedPaths : undefined,
+          options: {
+            budget_usd: budgetUsd > 0 ? budgetUsd : undefined,
+            batch: batchMode || undefined,
+            layout,
+          },
         });
         setIsScanning(true);
       } catch (e) {
@@ -412,24 +651,110 @@ function App() {
     setProcessedPreviewPaths(new Set());
     setActivityEvents([]);
     setScanStartedAt(null);
+    setPlanFile(null);
     setShowHome(true);
     loadPreviewFiles();
   }
 
+  // Write the pending moves as a CSV next to the screenshots for review
+  async function exportPlan() {
+    if (!runId) return;
+    try {
+      const file = await invoke<string>("export_plan", { runId, format: "csv" });
+      setPlanFile(file);
+      setActivityEvents(prev => [
+        {
+          id: `export-${Date.now()}`,
+          type: "info" as const,
+          title: "Plan exported",
+          detail: file,
+          time: Date.now(),
+        },
+        ...prev,
+      ].slice(0, 120));
+    } catch (err) {
+      console.error("Failed to export plan:", err);
+    }
+  }
+
+  // Apply the exported plan after it was edited, then reload the run
+  async function applyPlan() {
+    if (!runId || !planFile) return;
+    try {
+      const outcomes = await invoke<{ id: string; error: string | null }[]>("apply_plan", { path: planFile });
+      const failed = outcomes.filter(outcome => outcome.error).length;
+      setActivityEvents(prev => [
+        {
+          id: `apply-plan-${Date.now()}`,
+          type: failed > 0 ? "failed" as const : "info" as const,
+          title: `Plan applied: ${outcomes.length - failed} moved${failed > 0 ? `, ${failed} failed` : ""}`,
+          detail: planFile,
+          time: Date.now(),
+        },
+        ...prev,
+      ].slice(0, 120));
+      await resumeSession(runId);
+    } catch (err) {
+      console.error("Failed to apply plan:", err);
+      setActivityEvents(prev => [
+        {
+          id: `apply-plan-${Date.now()}`,
+          type: "failed" as const,
+          title: "Plan not applied",
+          detail: String(err),
+          time: Date.now(),
+        },
+        ...prev,
+      ].slice(0, 120));
+    }
+  }
+
+  // Write the HTML gallery and Markdown summary into the scan folder
+  async function generateReport() {
+    if (!runId) return;
+    try {
+      const files = await invoke<{ html: string; markdown: string }>("generate_report", { runId });
+      setActivityEvents(prev => [
+        {
+          id: `report-${Date.now()}`,
+          type: "info" as const,
+          title: "Report saved",
+          detail: files.html,
+          time: Date.now(),
+        },
+        ...prev,
+      ].slice(0, 120));
+    } catch (err) {
+      console.error("Failed to generate report:", err);
+    }
+  }
+
+  function persistEdit(proposal: FileProposal) {
+    if (!runId) return;
+    invoke("update_proposal", {
+      runId,
+      id: proposal.id,
+      proposedName: proposal.proposed_name,
+      proposedCategory: proposal.proposed_category,
+      selected: proposal.selected,
+    }).catch(console.error);
+  }
+
+  // Updaters may run more than once, so the journal is written outside them
   function toggleFileSelection(id: string) {
-    setProposals(prev =>
-      prev.map(p => (p.id === id ? { ...p, selected: !p.selected } : p))
-    );
+    const current = proposals.find(p => p.id === id);
+    if (!current) return;
+    const selected = !current.selected;
+    setProposals(prev => prev.map(p => (p.id === id ? { ...p, selected } : p)));
+    persistEdit({ ...current, selected });
   }
 
   function handleEditSave(id: string, name: string, category: string) {
-    setProposals(prev =>
-      prev.map(p =>
-        p.id === id
-          ? { ...p, proposed_name: name, proposed_category: category }
-          : p
-      )
-    );
+    const current = proposals.find(p => p.id === id);
+    if (!current) return;
+    const edit = { proposed_name: name, proposed_category: category };
+    setProposals(prev => prev.map(p => (p.id === id ? { ...p, ...edit } : p)));
+    persistEdit({ ...current, ...edit });
     setMoveErrors(prev => {
       if (!prev[id]) return prev;
       const next = { ...prev };
@@ -443,9 +768,62 @@ function App() {
     setEditingFile(null);
   }
 
-  function handleSettingsSave(newApiKey: string, newPath: string) {
-    setApiKey(newApiKey);
+  function handleSettingsSave(newHasApiKey: boolean, newPath: string, newBudget: string, newBatchMode: boolean, newLayout: FolderLayout) {
+    setHasApiKey(newHasApiKey);
     setPath(newPath);
+    setBudget(newBudget);
+    setBatchMode(newBatchMode);
+    setLayout(newLayout);
+  }
+
+  // Move the marked duplicates to the Trash; the keepers stay where they are
+  async function trashDuplicates(paths: string[]) {
+    setIsTrashingDuplicates(true);
+    setDuplicateError(null);
+    try {
+      const result = await invoke<TrashResult>("trash_duplicates", { paths, rootPath: path });
+      const trashed = new Set(result.trashed);
+      setDuplicateGroups(prev =>
+        prev
+          .map(group => ({ ...group, duplicates: group.duplicates.filter(member => !trashed.has(member.path)) }))
+          .filter(group => group.duplicates.length > 0)
+      );
+      setPreviewFiles(prev => prev.filter(file => !trashed.has(file.path)));
+      setActivityEvents(prev => [
+        {
+          id: `trashed-${Date.now()}`,
+          type: "info" as const,
+          title: `Moved ${result.trashed.length} duplicate${result.trashed.length === 1 ? "" : "s"} to the Trash`,
+          time: Date.now(),
+        },
+        ...prev,
+      ].slice(0, 120));
+      if (result.failed.length > 0) {
+        setDuplicateError(`${result.failed.length} not moved: ${result.failed[0][1]}`);
+      } else {
+        setShowDuplicates(false);
+      }
+    } catch (err) {
+      setDuplicateError(String(err));
+    } finally {
+      setIsTrashingDuplicates(false);
+    }
+  }
+
+  // Predict what scanning the selected files would cost
+  async function estimateScan() {
+    setIsEstimating(true);
+    try {
+      setEstimate(await invoke<CostEstimate>("estimate_scan", {
+        path,
+        selectedPaths: Array.from(selectedPreviews),
+        options: batchMode ? { batch: true } : undefined,
+      }));
+    } catch (err) {
+      console.error("Failed to estimate scan:", err);
+    } finally {
+      setIsEstimating(false);
+    }
   }
 
   // Track if we've already optimized/subdivided to prevent re-runs
@@ -585,6 +963,8 @@ function App() {
           newPath,
           rootPath: path,
           overwrite,
+          runId,
+          proposalId: p.id,
         });
         movedRecords.push({
           proposal: { ...p, proposed_category: safeCategory, proposed_name: safeName },
@@ -607,6 +987,7 @@ function App() {
       setLastMoveRoot(path);
       setShowUndo(true);
       setUndoStatus(null);
+      generateReport();
     }
 
     setExitingFileIds(new Set());
@@ -629,6 +1010,8 @@ function App() {
           originalPath: record.moved_path,
           newPath: record.proposal.original_path,
           rootPath,
+          runId,
+          proposalId: record.proposal.id,
         });
         restored.push(record.proposal);
       } catch (e) {
@@ -740,6 +1123,48 @@ function App() {
               Scanning {processedFiles}/{totalFiles}
             </div>
           )}
+          {!hasScanned && selectedPreviews.size > 0 && (
+            <button
+              onClick={estimateScan}
+              disabled={isEstimating}
+              className="px-2.5 py-1 rounded-full text-[11px] text-white/50 hover:text-white/70 bg-white/5 border border-white/10 font-mono"
+              title={estimate ? `${estimate.requests} requests · ~${estimate.input_tokens.toLocaleString()} input / ~${estimate.output_tokens.toLocaleString()} output tokens${estimate.escalation_model ? ` · up to ${estimate.max_cost_usd === null ? "an unknown cost" : formatCost(estimate.max_cost_usd)} if every request escalates to ${estimate.escalation_model}` : ""}${estimate.prompt_cached ? "" : ` · prompt too short for ${estimate.model} to cache`}` : "Estimate the cost of this scan"}
+            >
+              {isEstimating
+                ? "Estimating…"
+                : estimate
+                  ? `≈ ${estimate.cost_usd === null ? "unknown" : formatCost(estimate.cost_usd)}${estimate.max_cost_usd === null ? "" : `, up to ${formatCost(estimate.max_cost_usd)}`}`
+                  : "Estimate cost"}
+            </button>
+          )}
+          {duplicateGroups.length > 0 && (
+            <button
+              onClick={() => {
+                setDuplicateError(null);
+                setShowDuplicates(true);
+              }}
+              className="px-2.5 py-1 rounded-full text-[11px] text-white/50 hover:text-white/70 bg-white/5 border border-white/10"
+              title="Duplicates skipped from this scan"
+            >
+              {duplicateGroups.reduce((count, group) => count + group.duplicates.length, 0)} duplicates
+            </button>
+          )}
+          {batchStatus && batchStatus.status !== "ended" && (
+            <div
+              className="px-2.5 py-1 rounded-full text-[11px] text-white/50 bg-white/5 border border-white/10 font-mono"
+              title={`Message Batch ${batchStatus.batch_id}: ${batchStatus.status}`}
+            >
+              Batch {batchProgress(batchStatus)}
+            </div>
+          )}
+          {runCost && (
+            <div
+              className="px-2.5 py-1 rounded-full text-[11px] text-white/50 bg-white/5 border border-white/10 font-mono"
+              title={`${runCost.requests} requests · ${runCost.input_tokens.toLocaleString()} input / ${runCost.output_tokens.toLocaleString()} output tokens · ${runCost.cache_read_input_tokens.toLocaleString()} read from cache`}
+            >
+              {formatCost(runCost.cost_usd)}
+            </div>
+          )}
           <motion.button
             onClick={() => setShowFolderPicker(true)}
             className="p-2 rounded-lg hover:bg-white/5 text-white/50 hover:text-white/70"
@@ -750,6 +1175,30 @@ function App() {
             <FolderOpen className="w-4 h-4" />
           </motion.button>
 
+          {runId && hasResults && (
+            <motion.button
+              onClick={exportPlan}
+              className="p-2 rounded-lg hover:bg-white/5 text-white/50 hover:text-white/70"
+              whileHover={{ scale: 1.02 }}
+              whileTap={{ scale: 0.98 }}
+              title="Export Plan"
+            >
+              <FileDown className="w-4 h-4" />
+            </motion.button>
+          )}
+
+          {runId && planFile && (
+            <motion.button
+              onClick={applyPlan}
+              className="p-2 rounded-lg hover:bg-white/5 text-white/50 hover:text-white/70"
+              whileHover={{ scale: 1.02 }}
+              whileTap={{ scale: 0.98 }}
+              title="Apply Edited Plan"
+            >
+              <FileUp className="w-4 h-4" />
+            </motion.button>
+          )}
+
           <motion.button
             onClick={() => setShowSettings(true)}
             className="p-2 rounded-lg hover:bg-white/5 text-white/50 hover:text-white/70"
@@ -762,7 +1211,7 @@ function App() {
 
           <Button
             onClick={() => startScan()}
-            disabled={!apiKey || (!hasScanned && selectedPreviews.size === 0)}
+            disabled={!hasApiKey || (!hasScanned && selectedPreviews.size === 0)}
             variant={isScanning ? "destructive" : "default"}
             className={
               isScanning
@@ -909,7 +1358,27 @@ function App() {
                           Preview screenshots
                         </Button>
                       </div>
-                      {!apiKey && (
+                      {sessions.some(session => session.proposed > session.applied) && (
+                        <div className="mt-6 space-y-1.5">
+                          <p className="text-[10px] uppercase tracking-wider text-white/35">Unfinished reviews</p>
+                          {sessions
+                            .filter(session => session.proposed > session.applied)
+                            .slice(0, 4)
+                            .map(session => (
+                              <button
+                                key={session.run_id}
+                                onClick={() => resumeSession(session.run_id)}
+                                className="w-full flex items-center justify-between gap-4 rounded-lg border border-white/10 bg-white/[0.03] px-3 py-2 text-left hover:bg-white/[0.06]"
+                              >
+                                <span className="text-[12px] text-white/70 font-mono truncate">{session.root_path}</span>
+                                <span className="text-[11px] text-white/35 shrink-0">
+                                  {session.proposed - session.applied} to review · {new Date(session.started_at).toLocaleDateString()}
+                                </span>
+                              </button>
+                            ))}
+                        </div>
+                      )}
+                      {!hasApiKey && (
                         <div className="mt-3 inline-flex items-center gap-2 rounded-full border border-amber-400/20 bg-amber-400/10 px-3 py-1 text-[11px] text-amber-200/80">
                           <AlertTriangle className="h-3 w-3" />
                           Add your Anthropic API key in Settings to enable smart sorting.
@@ -1063,6 +1532,12 @@ function App() {
                         <span className="text-amber-400/60">{skippedFiles.length} skipped</span>
                       </>
                     )}
+                    {keptLocalFiles.length > 0 && (
+                      <>
+                        <span>•</span>
+                        <span className="text-sky-400/60">{keptLocalFiles.length} kept local</span>
+                      </>
+                    )}
                   </div>
                 </div>
               )}
@@ -1177,9 +1652,11 @@ function App() {
                                     ? "bg-green-400"
                                     : event.type === "skipped"
                                       ? "bg-amber-400"
-                                      : event.type === "failed"
-                                        ? "bg-red-400"
-                                        : "bg-white/40"
+                                      : event.type === "kept"
+                                        ? "bg-sky-400"
+                                        : event.type === "failed"
+                                          ? "bg-red-400"
+                                          : "bg-white/40"
                                 }`}
                               />
                               <div className="flex-1 min-w-0">
@@ -1229,8 +1706,11 @@ function App() {
       <SettingsSheet
         open={showSettings}
         onOpenChange={setShowSettings}
-        apiKey={apiKey}
+        hasApiKey={hasApiKey}
         path={path}
+        budget={budget}
+        batchMode={batchMode}
+        layout={layout}
         onSave={handleSettingsSave}
       />
 
@@ -1265,6 +1745,16 @@ function App() {
         onClose={() => setShowConflictDialog(false)}
       />
 
+      {/* Duplicates Dialog */}
+      <DuplicatesDialog
+        open={showDuplicates}
+        groups={duplicateGroups}
+        isTrashing={isTrashingDuplicates}
+        error={duplicateError}
+        onTrash={trashDuplicates}
+        onClose={() => setShowDuplicates(false)}
+      />
+
     </div>
   );
 }
@@ -0,0 +1,142 @@
+import { useEffect, useState } from "react";
+import { Copy, Loader2, Trash2 } from "lucide-react";
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogHeader,
+  DialogTitle,
+} from "@/components/ui/dialog";
+import { Button } from "@/components/ui/button";
+import { ScrollArea } from "@/components/ui/scroll-area";
+
+export interface DuplicateMember {
+  path: string;
+  name: string;
+  size: number;
+  similarity: number;
+}
+
+export interface DuplicateGroup {
+  id: string;
+  kind: "exact" | "near";
+  keeper: DuplicateMember;
+  duplicates: DuplicateMember[];
+}
+
+interface DuplicatesDialogProps {
+  open: boolean;
+  groups: DuplicateGroup[];
+  isTrashing: boolean;
+  error: string | null;
+  onTrash: (paths: string[]) => void;
+  onClose: () => void;
+}
+
+export function DuplicatesDialog({
+  open,
+  groups,
+  isTrashing,
+  error,
+  onTrash,
+  onClose,
+}: DuplicatesDialogProps) {
+  // Every duplicate starts out marked; the keeper is never offered
+  const [marked, setMarked] = useState<Set<string>>(new Set());
+
+  useEffect(() => {
+    if (open) {
+      setMarked(new Set(groups.flatMap(group => group.duplicates.map(member => member.path))));
+    }
+  }, [open, groups]);
+
+  function toggle(path: string) {
+    setMarked(prev => {
+      const next = new Set(prev);
+      if (next.has(path)) next.delete(path);
+      else next.add(path);
+      return next;
+    });
+  }
+
+  return (
+    <Dialog open={open} onOpenChange={(next) => !next && onClose()}>
+      <DialogContent className="max-w-2xl bg-[#0a0a0a] border-white/10 p-0 overflow-hidden">
+        <DialogHeader className="px-6 py-5 border-b border-white/5">
+          <DialogTitle className="text-white text-[15px] flex items-center gap-2">
+            <Copy className="w-4 h-4 text-white/60" />
+            Duplicates
+          </DialogTitle>
+          <DialogDescription className="text-white/40 text-[13px]">
+            These were skipped from the scan. Marked copies go to the Trash and
+            can be restored from there.
+          </DialogDescription>
+        </DialogHeader>
+
+        <ScrollArea className="max-h-[520px]">
+          <div className="px-6 py-5 space-y-4">
+            {groups.map((group) => (
+              <div
+                key={group.id}
+                className="rounded-xl border border-white/10 bg-white/5 p-4 space-y-2"
+              >
+                <div className="flex items-center justify-between gap-4">
+                  <p className="text-[13px] text-white/80 font-medium truncate">
+                    {group.keeper.name}
+                  </p>
+                  <span className="text-[10px] px-2 py-1 rounded-full bg-white/10 text-white/50 shrink-0">
+                    Keep · {group.kind === "exact" ? "identical copies" : "similar shots"}
+                  </span>
+                </div>
+                {group.duplicates.map((member) => (
+                  <label
+                    key={member.path}
+                    className="flex items-center gap-3 text-[12px] text-white/60 cursor-pointer"
+                  >
+                    <input
+                      type="checkbox"
+                      checked={marked.has(member.path)}
+                      onChange={() => toggle(member.path)}
+                      className="accent-white"
+                    />
+                    <span className="truncate flex-1">{member.name}</span>
+                    <span className="text-[11px] text-white/30 font-mono">
+                      {Math.round(member.similarity * 100)}%
+                    </span>
+                  </label>
+                ))}
+              </div>
+            ))}
+          </div>
+        </ScrollArea>
+
+        <div className="px-6 py-4 border-t border-white/5 flex items-center justify-between">
+          <div className="text-[12px] text-white/40">
+            {error ?? `${marked.size} file${marked.size === 1 ? "" : "s"} marked`}
+          </div>
+          <div className="flex items-center gap-3">
+            <Button
+              variant="outline"
+              className="border-white/10 bg-transparent text-white/60 hover:bg-white/5"
+              onClick={onClose}
+            >
+              Keep all
+            </Button>
+            <Button
+              className="bg-white text-black hover:bg-white/90"
+              onClick={() => onTrash(Array.from(marked))}
+              disabled={marked.size === 0 || isTrashing}
+            >
+              {isTrashing ? (
+                <Loader2 className="w-3.5 h-3.5 mr-2 animate-spin" />
+              ) : (
+                <Trash2 className="w-3.5 h-3.5 mr-2" />
+              )}
+              Move to Trash
+            </Button>
+          </div>
+        </div>
+      </DialogContent>
+    </Dialog>
+  );
+}
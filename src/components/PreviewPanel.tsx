@@ -1,6 +1,6 @@
 import { motion, AnimatePresence } from "framer-motion";
 import { convertFileSrc } from "@tauri-apps/api/core";
-import { X, Pencil, ArrowRight, ImageIcon } from "lucide-react";
+import { X, Pencil, ArrowRight, ImageIcon, ShieldCheck } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { Separator } from "@/components/ui/separator";
 import { ScrollArea } from "@/components/ui/scroll-area";
@@ -12,6 +12,8 @@ interface FileProposal {
   proposed_name: string;
   proposed_category: string;
   reasoning: string;
+  model?: string;
+  redactions?: string[];
   selected: boolean;
 }
 
@@ -130,6 +132,18 @@ export function PreviewPanel({ file, onClose, onEdit }: PreviewPanelProps) {
                 </p>
               </div>
 
+              {/* Privacy */}
+              {(file.model === "offline" || (file.redactions?.length ?? 0) > 0) && (
+                <div className="flex items-start gap-2 rounded-lg border border-emerald-400/15 bg-emerald-400/5 px-3 py-2">
+                  <ShieldCheck className="w-3.5 h-3.5 mt-0.5 text-emerald-300/70 shrink-0" />
+                  <p className="text-[11px] text-emerald-200/70 leading-relaxed">
+                    {file.model === "offline"
+                      ? "Classified on this computer; the image was not uploaded"
+                      : `Blacked out before upload: ${file.redactions!.join(", ")}`}
+                  </p>
+                </div>
+              )}
+
               {/* Edit Button */}
               <Button
                 onClick={onEdit}
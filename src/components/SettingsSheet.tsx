@@ -1,5 +1,6 @@
 import { useState } from "react";
-import { Key, Save } from "lucide-react";
+import { invoke } from "@tauri-apps/api/core";
+import { Ban, CalendarDays, Cpu, DollarSign, Globe, Key, Layers, Plus, Save, Server, ShieldCheck, X } from "lucide-react";
 import {
   Sheet,
   SheetContent,
@@ -10,34 +11,192 @@ import {
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 
+interface ApiSettings {
+  enabled: boolean;
+  running: boolean;
+  port: number;
+  token: string;
+}
+
+interface ModelSettings {
+  classify: string;
+  refine: string;
+  summarize: string;
+  escalate_from: string | null;
+  min_confidence: number;
+}
+
+interface NetworkSettings {
+  base_url: string | null;
+  connect_timeout_secs: number;
+  read_timeout_secs: number;
+  proxy: string | null;
+  ca_certificates: string[];
+}
+
+export type FolderLayout = "category" | "category_date" | "date_category";
+
+type PrivacyMode = "off" | "redact" | "offline";
+
+type RuleKind = "folder" | "pattern" | "app";
+
+interface NeverUpload {
+  kind: RuleKind;
+  value: string;
+  category: string | null;
+}
+
+interface PrivacySettings {
+  mode: PrivacyMode;
+  tesseract: string | null;
+  never_upload: NeverUpload[];
+}
+
+const FOLDER_LAYOUTS: { layout: FolderLayout; label: string; example: string }[] = [
+  { layout: "category", label: "Category", example: "Finance/Invoices" },
+  { layout: "category_date", label: "Category/Date", example: "Finance/Invoices/2025/03" },
+  { layout: "date_category", label: "Date/Category", example: "2025/03/Finance/Invoices" },
+];
+
+const PRIVACY_MODES: { mode: PrivacyMode; label: string }[] = [
+  { mode: "off", label: "Off" },
+  { mode: "redact", label: "Redact" },
+  { mode: "offline", label: "Keep local" },
+];
+
+const RULE_KINDS: { kind: RuleKind; label: string; placeholder: string }[] = [
+  { kind: "folder", label: "Folder", placeholder: "~/Documents/Banking" },
+  { kind: "pattern", label: "File name", placeholder: "*statement*" },
+  { kind: "app", label: "App", placeholder: "1Password" },
+];
+
+const MODEL_TASKS: { key: "classify" | "refine" | "summarize"; label: string }[] = [
+  { key: "classify", label: "Classify" },
+  { key: "refine", label: "Refine" },
+  { key: "summarize", label: "Summarize" },
+];
+
 interface SettingsSheetProps {
   open: boolean;
   onOpenChange: (open: boolean) => void;
-  apiKey: string;
+  hasApiKey: boolean;
   path: string;
-  onSave: (apiKey: string, path: string) => void;
+  budget: string;
+  batchMode: boolean;
+  layout: FolderLayout;
+  onSave: (hasApiKey: boolean, path: string, budget: string, batchMode: boolean, layout: FolderLayout) => void;
 }
 
 export function SettingsSheet({
   open,
   onOpenChange,
-  apiKey,
+  hasApiKey,
   path,
+  budget,
+  batchMode,
+  layout,
   onSave,
 }: SettingsSheetProps) {
-  const [tempApiKey, setTempApiKey] = useState(apiKey);
+  // A new key to check and save; the saved key is never shown
+  const [tempApiKey, setTempApiKey] = useState("");
+  const [removeKey, setRemoveKey] = useState(false);
+  const [keyError, setKeyError] = useState<string | null>(null);
+  const [checkingKey, setCheckingKey] = useState(false);
   const [tempPath, setTempPath] = useState(path);
+  const [tempBudget, setTempBudget] = useState(budget);
+  const [tempBatchMode, setTempBatchMode] = useState(batchMode);
+  const [tempLayout, setTempLayout] = useState(layout);
+  const [models, setModels] = useState<ModelSettings | null>(null);
+  const [privacy, setPrivacy] = useState<PrivacySettings | null>(null);
+  const [network, setNetwork] = useState<NetworkSettings | null>(null);
+  const [networkError, setNetworkError] = useState<string | null>(null);
+  const [api, setApi] = useState<ApiSettings | null>(null);
+  const [apiError, setApiError] = useState<string | null>(null);
+
+  const toggleApi = async () => {
+    if (!api) return;
+    setApiError(null);
+    try {
+      setApi(await invoke<ApiSettings>("set_api_enabled", { enabled: !api.enabled }));
+    } catch (err) {
+      setApiError(String(err));
+    }
+  };
+
+  const updateRule = (index: number, change: Partial<NeverUpload>) => {
+    setPrivacy(prev =>
+      prev && {
+        ...prev,
+        never_upload: prev.never_upload.map((rule, i) => (i === index ? { ...rule, ...change } : rule)),
+      }
+    );
+  };
 
-  const handleSave = () => {
-    onSave(tempApiKey, tempPath);
+  const handleSave = async () => {
+    let keySaved = hasApiKey;
+    if (tempApiKey.trim() || removeKey) {
+      setKeyError(null);
+      setCheckingKey(true);
+      try {
+        await invoke("set_api_key", { apiKey: removeKey ? "" : tempApiKey });
+        keySaved = !removeKey;
+      } catch (err) {
+        setKeyError(String(err));
+        return;
+      } finally {
+        setCheckingKey(false);
+      }
+    }
+    if (network) {
+      try {
+        await invoke("set_network_settings", {
+          network: {
+            ...network,
+            base_url: network.base_url?.trim() || null,
+            proxy: network.proxy?.trim() || null,
+            ca_certificates: network.ca_certificates.map(path => path.trim()).filter(Boolean),
+          },
+        });
+      } catch (err) {
+        setNetworkError(String(err));
+        return;
+      }
+    }
+    if (privacy) {
+      invoke("set_privacy_settings", {
+        privacy: {
+          ...privacy,
+          never_upload: privacy.never_upload
+            .map(rule => ({ ...rule, value: rule.value.trim(), category: rule.category?.trim() || null }))
+            .filter(rule => rule.value),
+        },
+      }).catch(console.error);
+    }
+    if (models) {
+      invoke("set_model_settings", {
+        models: { ...models, escalate_from: models.escalate_from?.trim() || null },
+      }).catch(console.error);
+    }
+    onSave(keySaved, tempPath, tempBudget, tempBatchMode, tempLayout);
     onOpenChange(false);
   };
 
   // Reset temp values when sheet opens
   const handleOpenChange = (newOpen: boolean) => {
     if (newOpen) {
-      setTempApiKey(apiKey);
+      setTempApiKey("");
+      setRemoveKey(false);
+      setKeyError(null);
       setTempPath(path);
+      setTempBudget(budget);
+      setTempBatchMode(batchMode);
+      setTempLayout(layout);
+      setApiError(null);
+      setNetworkError(null);
+      invoke<ModelSettings>("get_model_settings").then(setModels).catch(console.error);
+      invoke<PrivacySettings>("get_privacy_settings").then(setPrivacy).catch(console.error);
+      invoke<NetworkSettings>("get_network_settings").then(setNetwork).catch(console.error);
+      invoke<ApiSettings>("get_api_settings").then(setApi).catch(console.error);
     }
     onOpenChange(newOpen);
   };
@@ -65,8 +224,49 @@ export function SettingsSheet({
             <Input
               type="password"
               value={tempApiKey}
-              onChange={(e) => setTempApiKey(e.target.value)}
-              placeholder="sk-ant-..."
+              onChange={(e) => {
+                setTempApiKey(e.target.value);
+                setRemoveKey(false);
+              }}
+              placeholder={hasApiKey && !removeKey ? "Saved — enter a new key to replace it" : "sk-ant-..."}
+              className="
+                bg-white/5 border-white/10 text-white text-[13px] font-mono
+                placeholder:text-white/20
+                focus:border-white/20 focus:ring-1 focus:ring-white/10
+              "
+            />
+            {keyError && <p className="text-[11px] text-red-400">{keyError}</p>}
+            <div className="flex items-center justify-between">
+              <p className="text-[11px] text-white/30">
+                Checked with Anthropic, then stored encrypted on this computer
+              </p>
+              {hasApiKey && (
+                <button
+                  onClick={() => {
+                    setTempApiKey("");
+                    setRemoveKey(!removeKey);
+                  }}
+                  className="text-[11px] text-white/40 hover:text-white/70"
+                >
+                  {removeKey ? "Keep" : "Remove"}
+                </button>
+              )}
+            </div>
+          </div>
+
+          {/* Budget */}
+          <div className="space-y-2">
+            <label className="text-[11px] font-medium text-white/50 uppercase tracking-wider flex items-center gap-2">
+              <DollarSign className="w-3.5 h-3.5" />
+              Budget per Scan
+            </label>
+            <Input
+              type="number"
+              min="0"
+              step="0.5"
+              value={tempBudget}
+              onChange={(e) => setTempBudget(e.target.value)}
+              placeholder="No limit"
               className="
                 bg-white/5 border-white/10 text-white text-[13px] font-mono
                 placeholder:text-white/20
@@ -74,7 +274,252 @@ export function SettingsSheet({
               "
             />
             <p className="text-[11px] text-white/30">
-              Your Anthropic API key for Claude
+              Files left when the budget runs out are deferred, in USD
+            </p>
+          </div>
+
+          {/* Batch mode */}
+          <div className="space-y-2">
+            <label className="text-[11px] font-medium text-white/50 uppercase tracking-wider flex items-center gap-2">
+              <Layers className="w-3.5 h-3.5" />
+              Batch Mode
+            </label>
+            <Button
+              variant="outline"
+              onClick={() => setTempBatchMode(!tempBatchMode)}
+              className="w-full border-white/10 bg-white/5 text-white text-[13px] hover:bg-white/10"
+            >
+              {tempBatchMode ? "Batch mode on" : "Batch mode off"}
+            </Button>
+            <p className="text-[11px] text-white/30">
+              Half the price for large backlogs; results can take up to a day and
+              are picked up again after a restart
+            </p>
+          </div>
+
+          {/* Folder layout */}
+          <div className="space-y-2">
+            <label className="text-[11px] font-medium text-white/50 uppercase tracking-wider flex items-center gap-2">
+              <CalendarDays className="w-3.5 h-3.5" />
+              Folder Layout
+            </label>
+            <div className="grid grid-cols-3 gap-2">
+              {FOLDER_LAYOUTS.map(option => (
+                <Button
+                  key={option.layout}
+                  variant="outline"
+                  onClick={() => setTempLayout(option.layout)}
+                  className={`border-white/10 text-[12px] hover:bg-white/10 ${
+                    tempLayout === option.layout ? "bg-white/15 text-white" : "bg-white/5 text-white/50"
+                  }`}
+                >
+                  {option.label}
+                </Button>
+              ))}
+            </div>
+            <p className="text-[11px] text-white/30">
+              Files go to {FOLDER_LAYOUTS.find(option => option.layout === tempLayout)?.example}, dated
+              by the capture time in the file name
+            </p>
+          </div>
+
+          {/* Privacy */}
+          <div className="space-y-2">
+            <label className="text-[11px] font-medium text-white/50 uppercase tracking-wider flex items-center gap-2">
+              <ShieldCheck className="w-3.5 h-3.5" />
+              Privacy
+            </label>
+            <div className="grid grid-cols-3 gap-2">
+              {PRIVACY_MODES.map(({ mode, label }) => (
+                <Button
+                  key={mode}
+                  variant="outline"
+                  disabled={!privacy}
+                  onClick={() => privacy && setPrivacy({ ...privacy, mode })}
+                  className={`border-white/10 text-[13px] hover:bg-white/10 ${
+                    privacy?.mode === mode ? "bg-white/15 text-white" : "bg-white/5 text-white/50"
+                  }`}
+                >
+                  {label}
+                </Button>
+              ))}
+            </div>
+            <p className="text-[11px] text-white/30">
+              Reads each screenshot locally first; passwords, keys, card numbers and
+              contact details are blacked out, or the file is sorted offline. Needs
+              Tesseract installed
+            </p>
+          </div>
+
+          {/* Never upload */}
+          <div className="space-y-2">
+            <label className="text-[11px] font-medium text-white/50 uppercase tracking-wider flex items-center gap-2">
+              <Ban className="w-3.5 h-3.5" />
+              Never Upload
+            </label>
+            {privacy?.never_upload.map((rule, index) => (
+              <div key={index} className="flex items-center gap-2">
+                <select
+                  value={rule.kind}
+                  onChange={(e) => updateRule(index, { kind: e.target.value as RuleKind })}
+                  className="h-9 rounded-md bg-white/5 border border-white/10 px-2 text-[12px] text-white/70 focus:outline-none focus:border-white/20"
+                >
+                  {RULE_KINDS.map(({ kind, label }) => (
+                    <option key={kind} value={kind} className="bg-neutral-900">
+                      {label}
+                    </option>
+                  ))}
+                </select>
+                <Input
+                  value={rule.value}
+                  onChange={(e) => updateRule(index, { value: e.target.value })}
+                  placeholder={RULE_KINDS.find(({ kind }) => kind === rule.kind)?.placeholder}
+                  className="
+                    flex-1 bg-white/5 border-white/10 text-white text-[13px] font-mono
+                    placeholder:text-white/20
+                    focus:border-white/20 focus:ring-1 focus:ring-white/10
+                  "
+                />
+                <Input
+                  value={rule.category ?? ""}
+                  onChange={(e) => updateRule(index, { category: e.target.value })}
+                  placeholder="Category"
+                  className="
+                    w-28 bg-white/5 border-white/10 text-white text-[13px]
+                    placeholder:text-white/20
+                    focus:border-white/20 focus:ring-1 focus:ring-white/10
+                  "
+                />
+                <button
+                  onClick={() =>
+                    setPrivacy(prev =>
+                      prev && { ...prev, never_upload: prev.never_upload.filter((_, i) => i !== index) }
+                    )
+                  }
+                  className="text-white/30 hover:text-white/70"
+                  aria-label="Remove rule"
+                >
+                  <X className="w-4 h-4" />
+                </button>
+              </div>
+            ))}
+            <Button
+              variant="outline"
+              disabled={!privacy}
+              onClick={() =>
+                privacy &&
+                setPrivacy({
+                  ...privacy,
+                  never_upload: [...privacy.never_upload, { kind: "folder", value: "", category: null }],
+                })
+              }
+              className="w-full border-white/10 bg-white/5 text-white/70 text-[13px] hover:bg-white/10"
+            >
+              <Plus className="w-4 h-4 mr-2" />
+              Add rule
+            </Button>
+            <p className="text-[11px] text-white/30">
+              Matching screenshots are never sent to Anthropic, whatever the privacy
+              mode. They go to the given category, or are sorted offline. App rules
+              also look for the name in the screenshot and need Tesseract
+            </p>
+          </div>
+
+          {/* Models */}
+          <div className="space-y-2">
+            <label className="text-[11px] font-medium text-white/50 uppercase tracking-wider flex items-center gap-2">
+              <Cpu className="w-3.5 h-3.5" />
+              Models
+            </label>
+            {MODEL_TASKS.map(({ key, label }) => (
+              <div key={key} className="flex items-center gap-3">
+                <span className="w-20 text-[12px] text-white/40">{label}</span>
+                <Input
+                  value={models?.[key] ?? ""}
+                  onChange={(e) => models && setModels({ ...models, [key]: e.target.value })}
+                  disabled={!models}
+                  className="
+                    bg-white/5 border-white/10 text-white text-[13px] font-mono
+                    focus:border-white/20 focus:ring-1 focus:ring-white/10
+                  "
+                />
+              </div>
+            ))}
+            <div className="flex items-center gap-3">
+              <span className="w-20 text-[12px] text-white/40">Try first</span>
+              <Input
+                value={models?.escalate_from ?? ""}
+                onChange={(e) => models && setModels({ ...models, escalate_from: e.target.value })}
+                disabled={!models}
+                placeholder="Off"
+                className="
+                  bg-white/5 border-white/10 text-white text-[13px] font-mono
+                  placeholder:text-white/20
+                  focus:border-white/20 focus:ring-1 focus:ring-white/10
+                "
+              />
+            </div>
+            <p className="text-[11px] text-white/30">
+              A cheaper model to try first; unsure or unreadable answers are
+              classified again by the Classify model
+            </p>
+          </div>
+
+          {/* Network */}
+          <div className="space-y-2">
+            <label className="text-[11px] font-medium text-white/50 uppercase tracking-wider flex items-center gap-2">
+              <Globe className="w-3.5 h-3.5" />
+              Network
+            </label>
+            {([
+              ["Base URL", "https://api.anthropic.com", network?.base_url ?? "", (value: string) => network && setNetwork({ ...network, base_url: value })],
+              ["Proxy", "From HTTPS_PROXY", network?.proxy ?? "", (value: string) => network && setNetwork({ ...network, proxy: value })],
+              ["CA file", "System certificates", network?.ca_certificates[0] ?? "", (value: string) => network && setNetwork({ ...network, ca_certificates: value ? [value] : [] })],
+            ] as const).map(([label, placeholder, value, onChange]) => (
+              <div key={label} className="flex items-center gap-3">
+                <span className="w-20 text-[12px] text-white/40">{label}</span>
+                <Input
+                  value={value}
+                  onChange={(e) => onChange(e.target.value)}
+                  disabled={!network}
+                  placeholder={placeholder}
+                  className="
+                    bg-white/5 border-white/10 text-white text-[13px] font-mono
+                    placeholder:text-white/20
+                    focus:border-white/20 focus:ring-1 focus:ring-white/10
+                  "
+                />
+              </div>
+            ))}
+            {networkError && <p className="text-[11px] text-red-400">{networkError}</p>}
+            <p className="text-[11px] text-white/30">
+              For corporate proxies and gateways; timeouts are set in network.json
+            </p>
+          </div>
+
+          {/* Local API */}
+          <div className="space-y-2">
+            <label className="text-[11px] font-medium text-white/50 uppercase tracking-wider flex items-center gap-2">
+              <Server className="w-3.5 h-3.5" />
+              Local API
+            </label>
+            <Button
+              variant="outline"
+              onClick={toggleApi}
+              disabled={!api}
+              className="w-full border-white/10 bg-white/5 text-white text-[13px] hover:bg-white/10"
+            >
+              {api?.running ? "Stop API server" : "Start API server"}
+            </Button>
+            {api?.running && (
+              <div className="space-y-1 text-[11px] text-white/40 font-mono break-all select-text">
+                <p>http://127.0.0.1:{api.port}/v1</p>
+                <p>Bearer {api.token}</p>
+              </div>
+            )}
+            {apiError && <p className="text-[11px] text-red-400">{apiError}</p>}
+            <p className="text-[11px] text-white/30">
+              Lets other tools on this computer scan, apply, undo and search
             </p>
           </div>
         </div>
@@ -82,10 +527,11 @@ export function SettingsSheet({
         <div className="absolute bottom-0 left-0 right-0 p-6 border-t border-white/5 bg-[#0a0a0a]">
           <Button
             onClick={handleSave}
+            disabled={checkingKey}
             className="w-full bg-white text-black hover:bg-white/90 font-medium"
           >
             <Save className="w-4 h-4 mr-2" />
-            Save Settings
+            {checkingKey ? "Checking API key..." : "Save Settings"}
           </Button>
         </div>
       </SheetContent>